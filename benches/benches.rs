@@ -15,7 +15,7 @@ use exonum::crypto;
 use exonum::crypto::{gen_keypair, PublicKey};
 use exonum_testkit::{ApiKind, TestKitBuilder};
 
-use timestamping::{TimestampService, TxTimestamp};
+use timestamping::{unix_time, TimestampService, TxTimestamp};
 
 use rand::{Rng, thread_rng};
 
@@ -25,7 +25,7 @@ fn bench_submit_10(b: &mut Bencher) {
     // Create testkit for network with four validators
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Check results with api
@@ -40,7 +40,7 @@ fn bench_submit_10(b: &mut Bencher) {
         let s = format!("{}{:06}", letter, number);
 
         let keypair = gen_keypair();
-        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), &keypair.1);
+        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), unix_time(), &keypair.1);
 
         data.push(tx);
     }
@@ -61,7 +61,7 @@ fn bench_submit_50(b: &mut Bencher) {
     // Create testkit for network with four validators
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Check results with api
@@ -76,7 +76,7 @@ fn bench_submit_50(b: &mut Bencher) {
         let s = format!("{}{:06}", letter, number);
 
         let keypair = gen_keypair();
-        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), &keypair.1);
+        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), unix_time(), &keypair.1);
 
         data.push(tx);
     }
@@ -97,7 +97,7 @@ fn bench_submit_100(b: &mut Bencher) {
     // Create testkit for network with four validators
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Check results with api
@@ -112,7 +112,7 @@ fn bench_submit_100(b: &mut Bencher) {
         let s = format!("{}{:06}", letter, number);
 
         let keypair = gen_keypair();
-        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), &keypair.1);
+        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), unix_time(), &keypair.1);
 
         data.push(tx);
     }
@@ -133,7 +133,7 @@ fn bench_submit_200(b: &mut Bencher) {
     // Create testkit for network with four validators
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Check results with api
@@ -148,7 +148,7 @@ fn bench_submit_200(b: &mut Bencher) {
         let s = format!("{}{:06}", letter, number);
 
         let keypair = gen_keypair();
-        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), &keypair.1);
+        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), unix_time(), &keypair.1);
 
         data.push(tx);
     }
@@ -168,7 +168,7 @@ fn bench_submit_500(b: &mut Bencher) {
     // Create testkit for network with four validators
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Check results with api
@@ -183,7 +183,7 @@ fn bench_submit_500(b: &mut Bencher) {
         let s = format!("{}{:06}", letter, number);
 
         let keypair = gen_keypair();
-        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), &keypair.1);
+        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), unix_time(), &keypair.1);
 
         data.push(tx);
     }
@@ -204,7 +204,7 @@ fn bench_submit_1000(b: &mut Bencher) {
     // Create testkit for network with four validators
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Check results with api
@@ -219,7 +219,7 @@ fn bench_submit_1000(b: &mut Bencher) {
         let s = format!("{}{:06}", letter, number);
 
         let keypair = gen_keypair();
-        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), &keypair.1);
+        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), unix_time(), &keypair.1);
 
         data.push(tx);
     }
@@ -236,11 +236,47 @@ fn bench_submit_1000(b: &mut Bencher) {
     );
 }
 
+fn bench_submit_batch_1000(b: &mut Bencher) {
+    // Create testkit for network with four validators
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(TimestampService::new())
+        .create();
+
+    // Check results with api
+    let api = testkit.api();
+
+    let mut rng = thread_rng();
+
+    let mut data: Vec<serde_json::Value> = Vec::with_capacity(1000);
+    for _ in 0..1000 {
+        let letter: char = rng.gen_range(b'A', b'Z') as char;
+        let number: u32 = rng.gen_range(0, 999999);
+        let s = format!("{}{:06}", letter, number);
+
+        let keypair = gen_keypair();
+        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), unix_time(), &keypair.1);
+
+        data.push(serde_json::to_value(&tx).unwrap());
+    }
+
+    b.iter(
+        || { for chunk in data.chunks(100) {
+            api.post::<Vec<serde_json::Value>, serde_json::Value>(
+                ApiKind::Service("timestamp"),
+                "v1/submit_batch", &chunk.to_vec()
+                );
+            testkit.create_block();
+            }
+        }
+    );
+}
+
 fn bench_search_10(b: &mut Bencher) {
     // Create testkit for network with four validators
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Check results with api
@@ -256,7 +292,7 @@ fn bench_search_10(b: &mut Bencher) {
         let s = format!("{}{:06}", letter, number);
 
         let keypair = gen_keypair();
-        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), &keypair.1);
+        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), unix_time(), &keypair.1);
 
         data.push(tx);
     }
@@ -269,7 +305,7 @@ fn bench_search_10(b: &mut Bencher) {
     }
     testkit.create_block();
 
-    let req = format!("/v1/timestamp/{}", PublicKey::to_hex(&data[0].from()));
+    let req = format!("/v1/timestamp/{}", PublicKey::to_hex(data[0].from()));
 
     b.iter(|| { api.get::<serde_json::Value>(ApiKind::Service("timestamp"), &req); });
 }
@@ -278,7 +314,7 @@ fn bench_search_50(b: &mut Bencher) {
     // Create testkit for network with four validators
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Check results with api
@@ -294,7 +330,7 @@ fn bench_search_50(b: &mut Bencher) {
         let s = format!("{}{:06}", letter, number);
 
         let keypair = gen_keypair();
-        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), &keypair.1);
+        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), unix_time(), &keypair.1);
 
         data.push(tx);
     }
@@ -307,7 +343,7 @@ fn bench_search_50(b: &mut Bencher) {
     }
     testkit.create_block();
 
-    let req = format!("/v1/timestamp/{}", PublicKey::to_hex(&data[0].from()));
+    let req = format!("/v1/timestamp/{}", PublicKey::to_hex(data[0].from()));
 
     b.iter(|| { api.get::<serde_json::Value>(ApiKind::Service("timestamp"), &req); });
 }
@@ -316,7 +352,7 @@ fn bench_search_100(b: &mut Bencher) {
     // Create testkit for network with four validators
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Check results with api
@@ -332,7 +368,7 @@ fn bench_search_100(b: &mut Bencher) {
         let s = format!("{}{:06}", letter, number);
 
         let keypair = gen_keypair();
-        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), &keypair.1);
+        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), unix_time(), &keypair.1);
 
         data.push(tx);
     }
@@ -345,7 +381,7 @@ fn bench_search_100(b: &mut Bencher) {
         if i % 10 == 0 { testkit.create_block(); }
     }
 
-    let req = format!("/v1/timestamp/{}", PublicKey::to_hex(&data[0].from()));
+    let req = format!("/v1/timestamp/{}", PublicKey::to_hex(data[0].from()));
 
     b.iter(|| { api.get::<serde_json::Value>(ApiKind::Service("timestamp"), &req); });
 }
@@ -354,7 +390,7 @@ fn bench_search_200(b: &mut Bencher) {
     // Create testkit for network with four validators
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Check results with api
@@ -370,7 +406,7 @@ fn bench_search_200(b: &mut Bencher) {
         let s = format!("{}{:06}", letter, number);
 
         let keypair = gen_keypair();
-        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), &keypair.1);
+        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), unix_time(), &keypair.1);
 
         data.push(tx);
     }
@@ -383,7 +419,7 @@ fn bench_search_200(b: &mut Bencher) {
         if i % 50 == 0 { testkit.create_block(); }
     }
 
-    let req = format!("/v1/timestamp/{}", PublicKey::to_hex(&data[0].from()));
+    let req = format!("/v1/timestamp/{}", PublicKey::to_hex(data[0].from()));
 
     b.iter(|| { api.get::<serde_json::Value>(ApiKind::Service("timestamp"), &req); });
 }
@@ -392,7 +428,7 @@ fn bench_search_500(b: &mut Bencher) {
     // Create testkit for network with four validators
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Check results with api
@@ -408,7 +444,7 @@ fn bench_search_500(b: &mut Bencher) {
         let s = format!("{}{:06}", letter, number);
 
         let keypair = gen_keypair();
-        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), &keypair.1);
+        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), unix_time(), &keypair.1);
 
         data.push(tx);
     }
@@ -421,7 +457,7 @@ fn bench_search_500(b: &mut Bencher) {
         if i % 50 == 0 { testkit.create_block(); }
     }
 
-    let req = format!("/v1/timestamp/{}", PublicKey::to_hex(&data[0].from()));
+    let req = format!("/v1/timestamp/{}", PublicKey::to_hex(data[0].from()));
 
     b.iter(|| { api.get::<serde_json::Value>(ApiKind::Service("timestamp"), &req); });
 }
@@ -429,7 +465,7 @@ fn bench_search_1000(b: &mut Bencher) {
     // Create testkit for network with four validators
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Check results with api
@@ -445,7 +481,7 @@ fn bench_search_1000(b: &mut Bencher) {
         let s = format!("{}{:06}", letter, number);
 
         let keypair = gen_keypair();
-        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), &keypair.1);
+        let tx = TxTimestamp::new(&keypair.0, &crypto::hash(s.as_bytes()), unix_time(), &keypair.1);
 
         data.push(tx);
     }
@@ -458,13 +494,14 @@ fn bench_search_1000(b: &mut Bencher) {
         if i % 100 == 0 { testkit.create_block(); }
     }
 
-    let req = format!("/v1/timestamp/{}", PublicKey::to_hex(&data[0].from()));
+    let req = format!("/v1/timestamp/{}", PublicKey::to_hex(data[0].from()));
 
     b.iter(|| { api.get::<serde_json::Value>(ApiKind::Service("timestamp"), &req); });
 }
 
 benchmark_group!(benches, bench_submit_10, bench_submit_50, bench_submit_100,
                  bench_submit_200, bench_submit_500, bench_submit_1000,
+                 bench_submit_batch_1000,
                  bench_search_10, bench_search_50, bench_search_100,
                  bench_search_200, bench_search_500, bench_search_1000);
 benchmark_main!(benches);
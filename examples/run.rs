@@ -1,11 +1,18 @@
+extern crate clap;
 extern crate exonum;
 extern crate timestamping;
 
+use clap::{App, Arg};
+
 use exonum::blockchain::{GenesisConfig, ValidatorKeys};
+use exonum::helpers::config::ConfigFile;
 use exonum::node::{Node, NodeApiConfig, NodeConfig};
-use exonum::storage::MemoryDB;
+use exonum::storage::{Database, MemoryDB, RocksDB, RocksDBOptions};
+
+use std::path::Path;
 
 use timestamping::TimestampService;
+use timestamping::anchoring::{FileSink, DEFAULT_ANCHOR_INTERVAL};
 
 fn node_config() -> NodeConfig {
     let (consensus_public_key, consensus_secret_key) = exonum::crypto::gen_keypair();
@@ -42,14 +49,55 @@ fn node_config() -> NodeConfig {
     }
 }
 
+// A persistent node has to keep its keys and genesis between restarts, so the generated
+// config is stored next to the database and reused.
+fn persistent_node_config(db_path: &Path) -> NodeConfig {
+    let config_path = db_path.join("node.toml");
+    if config_path.exists() {
+        ConfigFile::load(&config_path).unwrap()
+    } else {
+        let config = node_config();
+        ConfigFile::save(&config, &config_path).unwrap();
+        config
+    }
+}
+
+fn open_database(db_path: &Path) -> Box<dyn Database> {
+    let mut options = RocksDBOptions::default();
+    options.create_if_missing(true);
+    Box::new(RocksDB::open(db_path, &options).unwrap())
+}
+
 fn main() {
     exonum::helpers::init_logger().unwrap();
 
-    let node = Node::new(
-        MemoryDB::new(),
-        vec![Box::new(TimestampService)],
-        node_config(),
-    );
+    let matches = App::new("run")
+        .about("Runs a single validator with the timestamping service")
+        .arg(Arg::with_name("db-path")
+            .long("db-path")
+            .takes_value(true)
+            .help("Directory of a RocksDB database; timestamps live in memory without it"))
+        .arg(Arg::with_name("anchor-file")
+            .long("anchor-file")
+            .takes_value(true)
+            .help("Log to append anchors of the chain state to"))
+        .get_matches();
+
+    let (db, config): (Box<dyn Database>, _) = match matches.value_of("db-path") {
+        Some(path) => {
+            let path = Path::new(path);
+            std::fs::create_dir_all(path).unwrap();
+            (open_database(path), persistent_node_config(path))
+        }
+        None => (Box::new(MemoryDB::new()), node_config()),
+    };
+
+    let mut service = TimestampService::new();
+    if let Some(path) = matches.value_of("anchor-file") {
+        service = service.with_anchoring(DEFAULT_ANCHOR_INTERVAL, Box::new(FileSink::new(path)));
+    }
+
+    let node = Node::new(db, vec![Box::new(service)], config);
     println!("Starting a node");
     node.run().unwrap();
 }
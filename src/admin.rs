@@ -0,0 +1,325 @@
+// Operator API of the service, mounted on the node's private API address.
+
+use exonum::api::{Api, ApiError};
+use exonum::blockchain::{Blockchain, Schema};
+use exonum::crypto::{CryptoHash, Hash, PublicKey, SecretKey};
+use exonum::node::{ApiSender, TransactionSend};
+use exonum::storage::Snapshot;
+
+use iron::prelude::*;
+
+use router::Router;
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json;
+
+use super::snapshot::{ServiceSnapshot, SnapshotSchedule};
+use super::{TimestampConfig, TimestampResponse, TimestampSchema, TxSetQuota, TxTimestamp};
+
+// default limits of `PendingTransactions`
+pub const MAX_PENDING: usize = 10_000;
+pub const MAX_PENDING_AGE: Duration = Duration::from_secs(600);
+
+// Timestamps submitted through this node which are not committed yet; this is not the node's
+// pool, transactions received from other nodes are not tracked. A transaction the network drops is
+// never committed, so transactions are forgotten after `max_age` and the oldest ones go first
+// once there are `max_len` of them.
+pub struct PendingTransactions {
+    txs: Mutex<PendingQueue>,
+    max_len: usize,
+    max_age: Duration,
+}
+
+struct PendingQueue {
+    // transactions with the moments they were accepted and numbers of their acceptance
+    txs: BTreeMap<Hash, (TxTimestamp, Instant, u64)>,
+    // acceptances in their order; a hash may stay here after its transaction is gone
+    order: VecDeque<(u64, Instant, Hash)>,
+    next: u64,
+}
+
+impl PendingQueue {
+    // forgets transactions older than `max_age`, then the oldest ones over `max_len`
+    fn evict(&mut self, max_len: usize, max_age: Duration) {
+        while let Some(&(number, accepted, tx_hash)) = self.order.front() {
+            if accepted.elapsed() < max_age && self.txs.len() <= max_len {
+                break;
+            }
+            self.order.pop_front();
+            // a transaction submitted again is kept until its latest acceptance expires
+            if self.txs.get(&tx_hash).map_or(false, |&(_, _, latest)| latest == number) {
+                self.txs.remove(&tx_hash);
+            }
+        }
+    }
+}
+
+impl PendingTransactions {
+    pub fn new() -> Self {
+        PendingTransactions::with_limits(MAX_PENDING, MAX_PENDING_AGE)
+    }
+
+    pub fn with_limits(max_len: usize, max_age: Duration) -> Self {
+        let queue = PendingQueue {
+            txs: BTreeMap::new(),
+            order: VecDeque::new(),
+            next: 0,
+        };
+        PendingTransactions {
+            txs: Mutex::new(queue),
+            max_len,
+            max_age,
+        }
+    }
+
+    pub fn add(&self, tx: TxTimestamp) {
+        let mut queue = self.txs.lock().unwrap();
+        let (now, number) = (Instant::now(), queue.next);
+        queue.next += 1;
+        queue.order.push_back((number, now, tx.hash()));
+        queue.txs.insert(tx.hash(), (tx, now, number));
+        queue.evict(self.max_len, self.max_age);
+    }
+
+    pub fn list(&self) -> Vec<TxTimestamp> {
+        let mut queue = self.txs.lock().unwrap();
+        queue.evict(self.max_len, self.max_age);
+        queue.txs.values().map(|&(ref tx, _, _)| tx.clone()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        let mut queue = self.txs.lock().unwrap();
+        queue.evict(self.max_len, self.max_age);
+        queue.txs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // forgets transactions committed to the blockchain, returns how long each of them waited
+    pub fn prune(&self, snapshot: &Snapshot) -> Vec<Duration> {
+        let schema = Schema::new(snapshot);
+        let locations = schema.tx_location_by_tx_hash();
+        let mut waited = Vec::new();
+        let mut queue = self.txs.lock().unwrap();
+        queue.txs.retain(|tx_hash, &mut (_, accepted, _)| {
+            let committed = locations.contains(tx_hash);
+            if committed {
+                waited.push(accepted.elapsed());
+            }
+            !committed
+        });
+        let PendingQueue { ref txs, ref mut order, .. } = *queue;
+        order.retain(|&(_, _, ref tx_hash)| txs.contains_key(tx_hash));
+        waited
+    }
+}
+
+impl Default for PendingTransactions {
+    fn default() -> Self {
+        PendingTransactions::new()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ServiceStats {
+    pub height: u64,
+    pub timestamps: u64,
+    pub revealed: u64,
+    // timestamps submitted through this node and not committed yet, see `PendingTransactions`
+    pub pending: u64,
+    pub last_anchor: Option<u64>,
+}
+
+// Configuration of the service: the part agreed in the genesis block and settings of this node
+#[derive(Serialize, Deserialize)]
+pub struct ConfigResponse {
+    pub genesis: TimestampConfig,
+    pub max_hash_size: u64,
+    pub anchor_interval: Option<u64>,
+    pub snapshot_interval: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct QuotaRequest {
+    pub pub_key: PublicKey,
+    pub quota: u64,
+}
+
+#[derive(Clone)]
+pub struct AdminApi {
+    pub channel: ApiSender,
+    pub blockchain: Blockchain,
+    pub pending: Arc<PendingTransactions>,
+    pub service_keys: (PublicKey, SecretKey),
+    pub max_hash_size: u64,
+    pub anchor_interval: Option<u64>,
+    pub snapshots: Option<Arc<SnapshotSchedule>>,
+}
+
+// Registering handlers for the private API. We define 6 endpoints
+impl Api for AdminApi {
+    fn wire(&self, router: &mut Router) {
+        self.clone().set_stats(router);
+        self.clone().set_pending(router);
+        self.clone().set_config(router);
+        self.clone().set_quota(router);
+        self.clone().set_index_sizes(router);
+        self.clone().set_snapshot(router);
+    }
+}
+
+impl AdminApi {
+    // helpers to register all endpoints
+    fn set_stats(self, router: &mut Router) {
+        let stats = move |req: &mut Request| self.stats(req);
+        router.get("/v1/stats", stats, "admin_stats");
+    }
+
+    fn set_pending(self, router: &mut Router) {
+        let pending = move |req: &mut Request| self.pending(req);
+        router.get("/v1/local_pending", pending, "admin_local_pending");
+    }
+
+    fn set_config(self, router: &mut Router) {
+        let config = move |req: &mut Request| self.config(req);
+        router.get("/v1/config", config, "admin_config");
+    }
+
+    fn set_quota(self, router: &mut Router) {
+        let quota = move |req: &mut Request| self.quota(req);
+        router.post("/v1/quota", quota, "admin_quota");
+    }
+
+    fn set_index_sizes(self, router: &mut Router) {
+        let index_sizes = move |req: &mut Request| self.index_sizes(req);
+        router.get("/v1/index_sizes", index_sizes, "admin_index_sizes");
+    }
+
+    fn set_snapshot(self, router: &mut Router) {
+        let snapshot = move |req: &mut Request| self.snapshot(req);
+        router.get("/v1/snapshot", snapshot, "admin_snapshot");
+    }
+
+    // Endpoint for a summary of the service.
+    // Input: nothing
+    // Effect: counts timestamps and transactions waiting to be committed
+    // Return value: the height, numbers of timestamps and reveals, pending timestamps of this
+    // node and the height of the latest anchor
+    fn stats(&self, _: &mut Request) -> IronResult<Response> {
+        let stats = {
+            let snapshot = self.blockchain.snapshot();
+            let height = Schema::new(&snapshot).height().0;
+            let schema = TimestampSchema::new(&snapshot);
+            ServiceStats {
+                height,
+                timestamps: schema.history().len(),
+                revealed: schema.index_size("reveals"),
+                pending: self.pending.len() as u64,
+                last_anchor: schema.anchors().last().map(|anchor| anchor.height()),
+            }
+        };
+        self.ok_response(&serde_json::to_value(stats).unwrap())
+    }
+
+    // Endpoint for timestamps submitted through this node and not committed yet. It is not the
+    // node's pool, hence the name.
+    // Input: nothing
+    // Effect: reads TxTimestamp transactions sent to this node's API, without transactions of
+    // other nodes and without those forgotten by `PendingTransactions`
+    // Return value: the transactions
+    fn pending(&self, _: &mut Request) -> IronResult<Response> {
+        self.ok_response(&serde_json::to_value(self.pending.list()).unwrap())
+    }
+
+    // Endpoint for the current configuration.
+    // Input: nothing
+    // Effect: reads the service configuration of the genesis block and settings of this node
+    // Return value: the configuration
+    fn config(&self, _: &mut Request) -> IronResult<Response> {
+        let genesis = {
+            let snapshot = self.blockchain.snapshot();
+            let configuration = Schema::new(&snapshot).actual_configuration();
+            configuration
+                .services
+                .get("timestamp")
+                .and_then(|value| serde_json::from_value(value.clone()).ok())
+                .unwrap_or_default()
+        };
+        let config = ConfigResponse {
+            genesis,
+            max_hash_size: self.max_hash_size,
+            anchor_interval: self.anchor_interval,
+            snapshot_interval: self.snapshots.as_ref().map(|snapshots| snapshots.interval()),
+        };
+        self.ok_response(&serde_json::to_value(config).unwrap())
+    }
+
+    // Endpoint for overriding a relayer quota.
+    // Input: a relayer's public key and its new quota
+    // Effect: sends TxSetQuota signed with the service key of this node; unknown relayers are
+    // registered, used quota is kept
+    // Return value: a hash of the transaction
+    fn quota(&self, req: &mut Request) -> IronResult<Response> {
+        match req.get::<::bodyparser::Struct<QuotaRequest>>() {
+            Ok(Some(request)) => {
+                let (ref public_key, ref secret_key) = self.service_keys;
+                let tx = TxSetQuota::new(public_key, &request.pub_key, request.quota, secret_key);
+                let tx_hash = tx.hash();
+                self.channel.send(Box::new(tx)).map_err(ApiError::from)?;
+                let json = TimestampResponse { tx_hash };
+                self.ok_response(&serde_json::to_value(&json).unwrap())
+            }
+            Ok(None) => Err(ApiError::BadRequest("Empty request".into()))?,
+            Err(e) => Err(ApiError::BadRequest(e.to_string()))?,
+        }
+    }
+
+    // Endpoint for sizes of the service indexes.
+    // Input: nothing
+    // Effect: counts entries of every index; this walks whole indexes, so it is slow on large chains
+    // Return value: a number of entries by index name
+    fn index_sizes(&self, _: &mut Request) -> IronResult<Response> {
+        let sizes = {
+            let snapshot = self.blockchain.snapshot();
+            TimestampSchema::new(&snapshot).index_sizes()
+        };
+        self.ok_response(&serde_json::to_value(sizes).unwrap())
+    }
+
+    // Endpoint for disaster recovery.
+    // Input: an optional `height`, the latest block by default
+    // Effect: reads the whole state of the service at the latest block with proofs of it; earlier
+    // states are gone, so other heights are read from snapshots this node saved on commit, see
+    // `SnapshotSchedule`
+    // Return value: a snapshot to be checked with `ServiceSnapshot::verify` and restored elsewhere
+    fn snapshot(&self, req: &mut Request) -> IronResult<Response> {
+        let snapshot = ServiceSnapshot::take(&*self.blockchain.snapshot());
+        let height = match super::query_param::<u64>(req, "height")? {
+            Some(height) if height != snapshot.height() => height,
+            _ => return self.ok_response(&serde_json::to_value(snapshot).unwrap()),
+        };
+        let saved = match self.snapshots {
+            Some(ref snapshots) => snapshots.load(height).map_err(ApiError::from)?,
+            None => None,
+        };
+        match saved {
+            Some(snapshot) => self.ok_response(&serde_json::to_value(snapshot).unwrap()),
+            None => {
+                let message = match self.snapshots {
+                    Some(ref snapshots) => format!(
+                        "No snapshot at height {}, snapshots are saved every {} blocks",
+                        height,
+                        snapshots.interval()
+                    ),
+                    None => format!("No snapshot at height {}, this node saves none", height),
+                };
+                self.not_found_response(&serde_json::to_value(message).unwrap())
+            }
+        }
+    }
+}
@@ -0,0 +1,282 @@
+// Anchoring of the chain state in external systems.
+//
+// Every `interval` blocks one validator sends the hash of the latest block to an `AnchorSink` and
+// records the reference it gets back with `TxAnchor`. A block hash commits to the previous block,
+// so the external record fixes the history up to the anchored block: rewriting a timestamp of an
+// earlier block would change the anchored hash.
+//
+// `AnchorProof` links a committed transaction to the first anchor after it: the transaction is
+// proved in the transactions of its block, and headers from that block up to the anchored one are
+// linked by `prev_hash`. Headers are kept for every height, so proofs do not need the state of the
+// anchored block. A node anchors when it runs with `--anchor-file`, see `AnchoringArgs`; every
+// validator should, since validators take turns.
+
+use exonum::blockchain::{Block, Schema, ServiceContext};
+use exonum::crypto::{CryptoHash, Hash};
+use exonum::helpers::fabric::{Argument, CommandExtension, Context};
+use exonum::helpers::Height;
+use exonum::storage::{ListProof, Snapshot};
+
+use failure;
+
+use std::error::Error;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::{Anchor, TimestampSchema, TxAnchor};
+
+// anchoring schedule used when none is given
+pub const DEFAULT_ANCHOR_INTERVAL: u64 = 1000;
+
+// arguments of `timestamping-node run`, see `AnchoringArgs`
+pub const ANCHOR_FILE: &str = "ANCHOR_FILE";
+pub const ANCHOR_INTERVAL: &str = "ANCHOR_INTERVAL";
+
+#[derive(Debug)]
+pub enum AnchorError {
+    Io(io::Error),
+    // an external system refused the anchor
+    Rejected(String),
+    // an `AnchorProof` does not link its transaction to the anchor
+    InvalidProof(String),
+}
+
+impl fmt::Display for AnchorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AnchorError::Io(ref e) => write!(f, "{}", e),
+            AnchorError::Rejected(ref e) => write!(f, "Anchor rejected: {}", e),
+            AnchorError::InvalidProof(ref e) => write!(f, "Invalid anchor proof: {}", e),
+        }
+    }
+}
+
+impl Error for AnchorError {
+    fn description(&self) -> &str {
+        match *self {
+            AnchorError::Io(_) => "I/O error",
+            AnchorError::Rejected(_) => "anchor rejected",
+            AnchorError::InvalidProof(_) => "invalid anchor proof",
+        }
+    }
+}
+
+impl From<io::Error> for AnchorError {
+    fn from(e: io::Error) -> Self {
+        AnchorError::Io(e)
+    }
+}
+
+// What is written to an external system
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnchorPayload {
+    pub height: u64,
+    pub block_hash: Hash,
+    pub state_hash: Hash,
+}
+
+// Proof that a transaction is fixed by an external anchor
+#[derive(Serialize, Deserialize)]
+pub struct AnchorProof {
+    pub tx_hash: Hash,
+    // the transaction among the transactions of its block, the first of `blocks`
+    pub tx_proof: ListProof<Hash>,
+    // headers from the block of the transaction up to the anchored block
+    pub blocks: Vec<Block>,
+    // where the anchored block hash is recorded
+    pub anchor: Anchor,
+}
+
+impl AnchorProof {
+    // the proof for a committed transaction, if a block at its height or above is anchored
+    pub fn new(snapshot: &Snapshot, tx_hash: &Hash) -> Option<Self> {
+        let core = Schema::new(snapshot);
+        let location = core.tx_location_by_tx_hash().get(tx_hash)?;
+        let height = location.block_height().0;
+        let anchor = TimestampSchema::new(snapshot).anchor_covering(height)?;
+        let tx_proof = core.block_txs(location.block_height()).get_proof(location.position_in_block());
+        let blocks = (height..anchor.height() + 1)
+            .map(|height| {
+                let block_hash = core.block_hash_by_height(Height(height)).unwrap();
+                core.blocks().get(&block_hash).unwrap()
+            })
+            .collect();
+        Some(AnchorProof {
+            tx_hash: *tx_hash,
+            tx_proof,
+            blocks,
+            anchor,
+        })
+    }
+
+    // Checks the proof against the record found in the external system by `anchor.reference`;
+    // the node serving the proof is not trusted.
+    pub fn verify(&self, external: &AnchorPayload) -> Result<(), AnchorError> {
+        let invalid = |message: &str| Err(AnchorError::InvalidProof(message.to_owned()));
+
+        let first = match self.blocks.first() {
+            Some(block) => block,
+            None => return invalid("no blocks"),
+        };
+        let txs = self.tx_proof
+            .validate(*first.tx_hash(), u64::from(first.tx_count()))
+            .map_err(|e| AnchorError::InvalidProof(format!("{:?}", e)))?;
+        if txs.len() != 1 || *txs[0].1 != self.tx_hash {
+            return invalid("the transaction is not in its block");
+        }
+
+        if self.blocks.windows(2).any(|pair| *pair[1].prev_hash() != pair[0].hash()) {
+            return invalid("blocks are not linked");
+        }
+        let last = &self.blocks[self.blocks.len() - 1];
+        if last.height().0 != external.height || last.hash() != external.block_hash {
+            return invalid("the last block is not the anchored one");
+        }
+        Ok(())
+    }
+}
+
+// An external system keeping anchors. Sinks are called from `handle_commit`, so a slow sink
+// delays the node.
+pub trait AnchorSink: Send + Sync {
+    // stores an anchor and returns a reference to find it in the external system
+    fn anchor(&self, payload: &AnchorPayload) -> Result<String, AnchorError>;
+}
+
+// Append-only log of anchors, one JSON line per anchor. The reference is `<path>:<offset>`.
+pub struct FileSink {
+    path: PathBuf,
+}
+
+impl FileSink {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        FileSink { path: path.as_ref().to_owned() }
+    }
+}
+
+impl AnchorSink for FileSink {
+    fn anchor(&self, payload: &AnchorPayload) -> Result<String, AnchorError> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        let offset = file.metadata()?.len();
+        let mut line = ::serde_json::to_vec(payload).unwrap();
+        line.push(b'\n');
+        file.write_all(&line)?;
+        // the anchor is referenced on chain, so it must survive a crash
+        file.sync_all()?;
+        Ok(format!("{}:{}", self.path.display(), offset))
+    }
+}
+
+// In-memory ledger for tests. Clones share the same entries, the reference is `mock:<index>`.
+#[derive(Clone, Default)]
+pub struct MockLedger {
+    entries: Arc<Mutex<Vec<AnchorPayload>>>,
+}
+
+impl MockLedger {
+    pub fn new() -> Self {
+        MockLedger::default()
+    }
+
+    pub fn entries(&self) -> Vec<AnchorPayload> {
+        self.entries.lock().unwrap().clone()
+    }
+}
+
+impl AnchorSink for MockLedger {
+    fn anchor(&self, payload: &AnchorPayload) -> Result<String, AnchorError> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push(payload.clone());
+        Ok(format!("mock:{}", entries.len() - 1))
+    }
+}
+
+// Anchoring schedule of a node
+pub struct Anchoring {
+    interval: u64,
+    sink: Box<AnchorSink>,
+}
+
+impl Anchoring {
+    pub fn new(interval: u64, sink: Box<AnchorSink>) -> Self {
+        assert!(interval > 0, "Anchoring interval must be positive");
+        Anchoring { interval, sink }
+    }
+
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    // Anchors the latest block if it is due. Validators take turns, so each block is anchored once.
+    pub fn handle_commit(&self, ctx: &ServiceContext) {
+        let schema = Schema::new(ctx.snapshot());
+        let height = schema.height().0;
+        if height == 0 || height % self.interval != 0 {
+            return;
+        }
+        let validators = ctx.validators().len() as u64;
+        match ctx.validator_id() {
+            Some(id) if u64::from(id.0) == (height / self.interval) % validators => {}
+            _ => return,
+        }
+
+        let block_hash = schema.block_hash_by_height(Height(height)).unwrap();
+        let block = schema.blocks().get(&block_hash).unwrap();
+        let payload = AnchorPayload {
+            height,
+            block_hash,
+            state_hash: *block.state_hash(),
+        };
+
+        match self.sink.anchor(&payload) {
+            Ok(reference) => {
+                let tx = TxAnchor::new(ctx.public_key(), height, &block_hash, &reference, ctx.secret_key());
+                if let Err(e) = ctx.transaction_sender().send(Box::new(tx)) {
+                    error!("Failed to record the anchor of block {}: {}", height, e);
+                }
+            }
+            // the next scheduled block is anchored anyway, so a failure only widens the gap
+            Err(e) => error!("Failed to anchor block {}: {}", height, e),
+        }
+    }
+
+    // the schedule given to `timestamping-node run`, if the node anchors
+    pub fn from_context(context: &Context) -> Option<Self> {
+        let path = context.arg::<String>(ANCHOR_FILE).ok()?;
+        let interval = context.arg::<u64>(ANCHOR_INTERVAL).unwrap_or(DEFAULT_ANCHOR_INTERVAL);
+        Some(Anchoring::new(interval, Box::new(FileSink::new(path))))
+    }
+}
+
+// Arguments of `timestamping-node run` enabling anchoring to a `FileSink`
+pub struct AnchoringArgs;
+
+impl CommandExtension for AnchoringArgs {
+    fn args(&self) -> Vec<Argument> {
+        vec![
+            Argument::new_named(
+                ANCHOR_FILE,
+                false,
+                "Anchors blocks to this append-only log.",
+                None,
+                "anchor-file",
+                false,
+            ),
+            Argument::new_named(
+                ANCHOR_INTERVAL,
+                false,
+                "Anchors every N-th block, 1000 by default.",
+                None,
+                "anchor-interval",
+                false,
+            ),
+        ]
+    }
+
+    fn execute(&self, context: Context) -> Result<Context, failure::Error> {
+        Ok(context)
+    }
+}
@@ -0,0 +1,325 @@
+// `cluster` command: a network of validators on localhost for testing real consensus.
+//
+// Every validator is a separate `timestamping-node run` process configured with the regular
+// generate-template/generate-config/finalize steps. After the start the command reads stdin:
+//
+//   stop <i>    kills validator i, e.g. to check that the network survives its loss
+//   start <i>   starts validator i again on its database
+//   status      lists validators and their API addresses
+//   quit        (or end of input) kills all validators
+//
+// Validator i listens for peers on `peer-port + i`, serves its public API on `api-port + i`
+// and its private API on `api-port + validators + i`. With `--anchor-interval` it anchors blocks
+// to `anchors_<i>.log` in the directory, with `--snapshot-interval` it saves snapshots to
+// `snapshots_<i>`. With `--restore-snapshot` every validator seeds the genesis block with the
+// same snapshot, checked against the `--snapshot-validators` of the network it was taken on.
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use exonum::helpers::config::ConfigFile;
+use exonum::node::NodeConfig;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command, Stdio};
+
+pub const COMMAND: &str = "cluster";
+
+// A node sends to its peers one message at a time and keeps trying to reconnect to a lost peer
+// for minutes by default; once messages to that peer fill its queue, the node stops sending to
+// everyone else as well. Validators of a cluster give up on a stopped one within seconds.
+const CONNECT_RETRY_TIMEOUT: u64 = 500;
+const CONNECT_MAX_RETRIES: u64 = 4;
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND)
+        .about("Runs a network of validators on localhost")
+        .arg(Arg::with_name("validators")
+            .long("validators")
+            .short("n")
+            .takes_value(true)
+            .default_value("4")
+            .help("Number of validators"))
+        .arg(Arg::with_name("peer-port")
+            .long("peer-port")
+            .takes_value(true)
+            .default_value("2000")
+            .help("Peer port of the first validator"))
+        .arg(Arg::with_name("api-port")
+            .long("api-port")
+            .takes_value(true)
+            .default_value("8000")
+            .help("Public API port of the first validator"))
+        .arg(Arg::with_name("anchor-interval")
+            .long("anchor-interval")
+            .takes_value(true)
+            .help("Anchors every N-th block to a log of each validator"))
+        .arg(Arg::with_name("snapshot-interval")
+            .long("snapshot-interval")
+            .takes_value(true)
+            .help("Saves a snapshot of every N-th block to a directory of each validator"))
+        .arg(Arg::with_name("restore-snapshot")
+            .long("restore-snapshot")
+            .takes_value(true)
+            .requires("snapshot-validators")
+            .help("Starts the network from this snapshot"))
+        .arg(Arg::with_name("snapshot-validators")
+            .long("snapshot-validators")
+            .takes_value(true)
+            .multiple(true)
+            .help("Consensus keys of the validators which signed the restored snapshot"))
+        .arg(Arg::with_name("dir")
+            .long("dir")
+            .takes_value(true)
+            .help("Directory for configs, databases and logs; a temporary one is removed on exit"))
+}
+
+struct Validator {
+    config: PathBuf,
+    db_path: PathBuf,
+    log: PathBuf,
+    // arguments of `run` enabling anchoring and snapshots
+    run_args: Vec<String>,
+    api_address: String,
+    process: Option<Child>,
+}
+
+// Running validators; dropping the cluster kills them and removes a temporary directory
+struct Cluster {
+    dir: PathBuf,
+    temporary: bool,
+    validators: Vec<Validator>,
+}
+
+impl Cluster {
+    fn start(&mut self, idx: usize) -> io::Result<()> {
+        let validator = &mut self.validators[idx];
+        if validator.process.is_some() {
+            return Ok(());
+        }
+
+        let log = File::create(&validator.log)?;
+        let child = Command::new(env::current_exe()?)
+            .arg("run")
+            .arg("--node-config")
+            .arg(&validator.config)
+            .arg("--db-path")
+            .arg(&validator.db_path)
+            .args(&validator.run_args)
+            .stdout(Stdio::from(log.try_clone()?))
+            .stderr(Stdio::from(log))
+            .spawn()?;
+        validator.process = Some(child);
+        Ok(())
+    }
+
+    fn stop(&mut self, idx: usize) -> io::Result<()> {
+        if let Some(mut child) = self.validators[idx].process.take() {
+            child.kill()?;
+            child.wait()?;
+        }
+        Ok(())
+    }
+
+    fn status(&mut self) {
+        for (idx, validator) in self.validators.iter_mut().enumerate() {
+            let state = match validator.process {
+                Some(ref mut child) => match child.try_wait() {
+                    Ok(None) => "running",
+                    _ => "exited",
+                },
+                None => "stopped",
+            };
+            println!(
+                "validator {}: {}, api http://{}, log {}",
+                idx,
+                state,
+                validator.api_address,
+                validator.log.display()
+            );
+        }
+    }
+}
+
+impl Drop for Cluster {
+    fn drop(&mut self) {
+        for idx in 0..self.validators.len() {
+            let _ = self.stop(idx);
+        }
+        if self.temporary {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+pub fn run(matches: &ArgMatches) {
+    let count = parse_arg(matches, "validators");
+    let peer_port = parse_arg(matches, "peer-port");
+    let api_port = parse_arg(matches, "api-port");
+    let anchor_interval = matches
+        .value_of("anchor-interval")
+        .map(|_| parse_arg(matches, "anchor-interval"));
+    let snapshot_interval = matches
+        .value_of("snapshot-interval")
+        .map(|_| parse_arg(matches, "snapshot-interval"));
+    if count == 0 {
+        exit_with("`--validators` must be positive");
+    }
+
+    let (dir, temporary) = match matches.value_of("dir") {
+        Some(dir) => (PathBuf::from(dir), false),
+        None => (env::temp_dir().join(format!("timestamping-cluster-{}", process::id())), true),
+    };
+    fs::create_dir_all(&dir).unwrap_or_else(|e| exit_with(&e.to_string()));
+
+    let mut cluster = Cluster {
+        dir: dir.clone(),
+        temporary,
+        validators: Vec::new(),
+    };
+
+    // the number of validators is not in the template, it is the number of public configs given
+    // to `finalize`
+    let common = dir.join("common.toml");
+    node_command(&["generate-template".into(), path_arg(&common)]);
+
+    for idx in 0..count {
+        node_command(&[
+            "generate-config".into(),
+            path_arg(&common),
+            path_arg(&dir.join(format!("pub_{}.toml", idx))),
+            path_arg(&dir.join(format!("sec_{}.toml", idx))),
+            "--peer-address".into(),
+            format!("127.0.0.1:{}", peer_port + idx),
+        ]);
+    }
+
+    // every validator is finalized with the public configs of all of them, so they share a genesis
+    let public_configs: Vec<String> = (0..count)
+        .map(|idx| path_arg(&dir.join(format!("pub_{}.toml", idx))))
+        .collect();
+
+    for idx in 0..count {
+        let api_address = format!("127.0.0.1:{}", api_port + idx);
+        let config = dir.join(format!("node_{}.toml", idx));
+
+        let mut args = vec![
+            "finalize".into(),
+            "--public-api-address".into(),
+            api_address.clone(),
+            "--private-api-address".into(),
+            format!("127.0.0.1:{}", api_port + count + idx),
+            path_arg(&dir.join(format!("sec_{}.toml", idx))),
+            path_arg(&config),
+            "--public-configs".into(),
+        ];
+        args.extend(public_configs.iter().cloned());
+        node_command(&args);
+        shorten_reconnects(&config);
+
+        let mut run_args: Vec<String> = Vec::new();
+        if let Some(interval) = anchor_interval {
+            run_args.extend(vec![
+                "--anchor-file".into(),
+                path_arg(&dir.join(format!("anchors_{}.log", idx))),
+                "--anchor-interval".into(),
+                interval.to_string(),
+            ]);
+        }
+        if let Some(interval) = snapshot_interval {
+            run_args.extend(vec![
+                "--snapshot-dir".into(),
+                path_arg(&dir.join(format!("snapshots_{}", idx))),
+                "--snapshot-interval".into(),
+                interval.to_string(),
+            ]);
+        }
+        if let Some(snapshot) = matches.value_of("restore-snapshot") {
+            run_args.extend(vec!["--restore-snapshot".into(), snapshot.into()]);
+            run_args.push("--snapshot-validators".into());
+            run_args.extend(matches.values_of("snapshot-validators").unwrap().map(String::from));
+        }
+
+        cluster.validators.push(Validator {
+            config,
+            db_path: dir.join(format!("db_{}", idx)),
+            log: dir.join(format!("node_{}.log", idx)),
+            run_args,
+            api_address,
+            process: None,
+        });
+    }
+
+    for idx in 0..count {
+        cluster.start(idx as usize).unwrap_or_else(|e| exit_with(&e.to_string()));
+    }
+    cluster.status();
+
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words.as_slice() {
+            ["quit"] => break,
+            ["status"] => {
+                cluster.status();
+                Ok(())
+            }
+            ["stop", idx] => validator_idx(idx, count).and_then(|idx| cluster.stop(idx)),
+            ["start", idx] => validator_idx(idx, count).and_then(|idx| cluster.start(idx)),
+            [] => Ok(()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "Unknown command")),
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+// runs one of the configuration commands of this binary and waits for it
+fn node_command(args: &[String]) {
+    let exe = env::current_exe().unwrap_or_else(|e| exit_with(&e.to_string()));
+    let status = Command::new(exe)
+        .args(args)
+        .status()
+        .unwrap_or_else(|e| exit_with(&e.to_string()));
+    if !status.success() {
+        exit_with(&format!("`{}` failed", args[0]));
+    }
+}
+
+fn shorten_reconnects(config: &Path) {
+    let mut node: NodeConfig = ConfigFile::load(config).unwrap_or_else(|e| exit_with(&e.to_string()));
+    node.network.tcp_connect_retry_timeout = CONNECT_RETRY_TIMEOUT;
+    node.network.tcp_connect_max_retries = CONNECT_MAX_RETRIES;
+    ConfigFile::save(&node, config).unwrap_or_else(|e| exit_with(&e.to_string()));
+}
+
+fn validator_idx(idx: &str, count: u16) -> io::Result<usize> {
+    match idx.parse::<u16>() {
+        Ok(idx) if idx < count => Ok(idx as usize),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "No such validator")),
+    }
+}
+
+fn parse_arg(matches: &ArgMatches, name: &str) -> u16 {
+    matches
+        .value_of(name)
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|_| exit_with(&format!("Invalid value of `--{}`", name)))
+}
+
+fn path_arg(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
@@ -0,0 +1,65 @@
+// A validator node running the timestamping service.
+//
+// A network of N validators is configured in several steps:
+//
+//   timestamping-node generate-template common.toml
+//   timestamping-node generate-config common.toml pub.toml sec.toml --peer-address 0.0.0.0:2000
+//   timestamping-node finalize --public-api-address 0.0.0.0:8000 --private-api-address 127.0.0.1:8010 \
+//       sec.toml node.toml --public-configs pub_1.toml pub_2.toml ... pub_N.toml
+//   timestamping-node run --node-config node.toml --db-path db
+//
+// `generate-config` is run by every validator, its `pub.toml` is shared with the others, while
+// `sec.toml` holding the node's secret keys never leaves the machine. Each validator finalizes its
+// config with the public configs of all N validators, its own included.
+//
+//   timestamping-node run --node-config node.toml --db-path db \
+//       --anchor-file anchors.log --anchor-interval 1000
+//
+// also anchors every 1000th block to an append-only log, see `timestamping::anchoring`.
+
+//   timestamping-node run --node-config node.toml --db-path db \
+//       --snapshot-dir snapshots --snapshot-interval 10000
+//
+// saves a snapshot of every 10000th block, served by `/v1/snapshot?height=` of the private API.
+// A new network is started from a snapshot by running every validator for the first time with
+//
+//   timestamping-node run --node-config node.toml --db-path db \
+//       --restore-snapshot snapshot.json --snapshot-validators <key_1> ... <key_N>
+//
+// where the keys are consensus keys of the validators of the old network; a snapshot they did
+// not sign stops the node. See `timestamping::snapshot`.
+//
+//   timestamping-node cluster --validators 4
+//
+// does all of the above for a network on localhost, see `cluster.rs`.
+
+// lints of idioms newer than the code, as in the library
+#![allow(clippy::unnecessary_map_or)]
+
+extern crate clap;
+extern crate exonum;
+extern crate timestamping;
+
+use clap::App;
+
+use exonum::helpers::fabric::NodeBuilder;
+
+use std::env;
+
+mod cluster;
+
+fn main() {
+    exonum::helpers::init_logger().unwrap();
+
+    if env::args().nth(1).map_or(false, |command| command == cluster::COMMAND) {
+        let matches = App::new("timestamping-node")
+            .subcommand(cluster::command())
+            .get_matches();
+        cluster::run(matches.subcommand_matches(cluster::COMMAND).unwrap());
+        return;
+    }
+
+    NodeBuilder::new()
+        .with_service(Box::new(timestamping::ServiceFactory))
+        .run();
+}
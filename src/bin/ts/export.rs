@@ -0,0 +1,76 @@
+// `ts export` writes timestamps of chosen keys and period to a file or stdout for audits.
+//
+// Keys are public keys in hex, e.g. the `Key:` lines printed by `ts stamp`. Records are streamed
+// from the node as they are read, so exports of any size are fine.
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use exonum::crypto::PublicKey;
+use exonum::encoding::serialize::FromHex;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use timestamping::export::{ExportFilter, ExportFormat};
+
+use super::node_client;
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("export")
+        .about("Exports timestamps as CSV or NDJSON")
+        .arg(Arg::with_name("format")
+            .long("format")
+            .takes_value(true)
+            .possible_values(&["csv", "ndjson"])
+            .default_value("csv"))
+        .arg(Arg::with_name("pub-key")
+            .long("pub-key")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Exports only timestamps of this key, may be repeated"))
+        .arg(Arg::with_name("from")
+            .long("from")
+            .takes_value(true)
+            .help("Earliest time, in seconds since the Unix epoch"))
+        .arg(Arg::with_name("to")
+            .long("to")
+            .takes_value(true)
+            .help("Time to stop before, in seconds since the Unix epoch"))
+        .arg(Arg::with_name("output")
+            .long("output")
+            .short("o")
+            .takes_value(true)
+            .help("File to write, stdout unless given"))
+}
+
+pub fn run(args: &ArgMatches) -> Result<(), String> {
+    let format = args.value_of("format").unwrap().parse::<ExportFormat>()?;
+    let keys = args.values_of("pub-key")
+        .map_or(Ok(Vec::new()), |keys| {
+            keys.map(|key| PublicKey::from_hex(key).map_err(|_| format!("Invalid public key `{}`", key)))
+                .collect()
+        })?;
+    let filter = ExportFilter {
+        keys,
+        from: time_arg(args, "from")?,
+        to: time_arg(args, "to")?,
+    };
+
+    let stdout = io::stdout();
+    let mut out: Box<Write> = match args.value_of("output") {
+        Some(path) => Box::new(BufWriter::new(File::create(path).map_err(|e| e.to_string())?)),
+        None => Box::new(stdout.lock()),
+    };
+    node_client(args)
+        .export(format, &filter, &mut out)
+        .map_err(|e| e.to_string())?;
+    out.flush().map_err(|e| e.to_string())
+}
+
+fn time_arg(args: &ArgMatches, name: &str) -> Result<Option<u64>, String> {
+    match args.value_of(name) {
+        Some(value) => value.parse().map(Some).map_err(|_| format!("Invalid value of `--{}`", name)),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,124 @@
+// `ts git` commands timestamping commits and tags of a local repository.
+//
+//   ts git stamp [REV]     timestamps the object id of REV (`HEAD` by default)
+//   ts git verify [REV]    checks the receipt of REV against the chain
+//
+// The content hash of a git object is SHA-256 of its id written in hex (the 40 characters, not the
+// object itself), so anyone can recompute it with `printf %s <id> | sha256sum`. The id already
+// commits to the tree and the history, so stamping it proves them all. Receipts are kept as git
+// notes under `refs/notes/timestamps` and travel with the repository once that ref is pushed.
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+
+use exonum::crypto::{self, Hash};
+
+use std::process::Command;
+
+use timestamping::client::Receipt;
+
+use super::{node_client, print_receipt, stamp_content};
+
+const NOTES_REF: &str = "refs/notes/timestamps";
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    let rev = Arg::with_name("REV").default_value("HEAD");
+    let repo = Arg::with_name("repo")
+        .long("repo")
+        .takes_value(true)
+        .default_value(".")
+        .help("Path to the repository");
+
+    SubCommand::with_name("git")
+        .about("Timestamps commits and tags")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("stamp")
+            .about("Timestamps a commit or a tag and stores the receipt as a git note")
+            .args(&[rev.clone(), repo.clone()])
+            .arg(Arg::with_name("timeout")
+                .long("timeout")
+                .takes_value(true)
+                .default_value("60")
+                .help("Seconds to wait for the commit")))
+        .subcommand(SubCommand::with_name("verify")
+            .about("Checks the receipt of a commit or a tag against the chain")
+            .args(&[rev, repo]))
+}
+
+pub fn run(args: &ArgMatches) -> Result<(), String> {
+    match args.subcommand() {
+        ("stamp", Some(args)) => stamp(args),
+        ("verify", Some(args)) => verify(args),
+        _ => unreachable!(),
+    }
+}
+
+fn stamp(args: &ArgMatches) -> Result<(), String> {
+    let repo = args.value_of("repo").unwrap();
+    let oid = object_id(repo, args.value_of("REV").unwrap())?;
+    let content = object_content(&oid);
+    let timeout = args.value_of("timeout")
+        .unwrap()
+        .parse::<u64>()
+        .map_err(|_| "Invalid value of `--timeout`".to_owned())?;
+
+    let info = stamp_content(args, &node_client(args), &content, timeout, false)?;
+
+    let receipt = Receipt::new(&info);
+    let note = ::serde_json::to_string_pretty(&receipt).unwrap();
+    git(repo, &["notes", "--ref", NOTES_REF, "add", "--force", "--message", &note, &oid])?;
+
+    println!("Object:  {}", oid);
+    print_receipt(&receipt);
+    Ok(())
+}
+
+fn verify(args: &ArgMatches) -> Result<(), String> {
+    let repo = args.value_of("repo").unwrap();
+    let oid = object_id(repo, args.value_of("REV").unwrap())?;
+    let content = object_content(&oid);
+
+    let note = git(repo, &["notes", "--ref", NOTES_REF, "show", &oid])
+        .map_err(|_| format!("{} has no timestamp receipt", oid))?;
+    let receipt: Receipt = ::serde_json::from_str(&note).map_err(|e| format!("Invalid receipt: {}", e))?;
+    if receipt.content != content {
+        return Err(format!("The receipt of {} is for another object", oid));
+    }
+
+    let info = node_client(args)
+        .timestamp_by_content(&content)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("{} is not timestamped", oid))?;
+    let chain = Receipt::new(&info);
+    if chain.tx_hash != receipt.tx_hash || chain.height != receipt.height || chain.time != receipt.time {
+        return Err(format!("The receipt of {} does not match the chain", oid));
+    }
+
+    println!("Object:  {}", oid);
+    print_receipt(&receipt);
+    println!("Receipt matches the chain");
+    Ok(())
+}
+
+// full id of a commit or a tag; annotated tags are timestamped themselves, not their commits
+fn object_id(repo: &str, rev: &str) -> Result<String, String> {
+    let oid = git(repo, &["rev-parse", "--verify", &format!("{}^{{object}}", rev)])?;
+    Ok(oid.trim().to_owned())
+}
+
+// SHA-256 of the hex id, not of the commit or tag object
+fn object_content(oid: &str) -> Hash {
+    crypto::hash(oid.as_bytes())
+}
+
+fn git(repo: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Cannot run git: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,129 @@
+// `ts key` commands managing the keystore.
+//
+//   ts key new [NAME]              generates a key
+//   ts key list                    lists keys with their public keys
+//   ts key import NAME FILE        adds a key from an exported key file or a hex-encoded secret key
+//   ts key export NAME FILE        writes the encrypted key file, or the hex secret key with `--plain`
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+
+use exonum::crypto::SecretKey;
+use exonum::encoding::serialize::FromHex;
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use timestamping::keystore::{create_key_file, EncryptedKey};
+
+use super::{keystore, password};
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("key")
+        .about("Manages signing keys")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("new")
+            .about("Generates a key")
+            .arg(Arg::with_name("NAME").default_value("default")))
+        .subcommand(SubCommand::with_name("list").about("Lists keys"))
+        .subcommand(SubCommand::with_name("import")
+            .about("Adds a key from an exported key file or a hex-encoded secret key")
+            .arg(Arg::with_name("NAME").required(true))
+            .arg(Arg::with_name("FILE").required(true)))
+        .subcommand(SubCommand::with_name("export")
+            .about("Writes a key to a file")
+            .arg(Arg::with_name("NAME").required(true))
+            .arg(Arg::with_name("FILE").required(true))
+            .arg(Arg::with_name("plain")
+                .long("plain")
+                .help("Writes the secret key unencrypted, as hex")))
+}
+
+pub fn run(args: &ArgMatches) -> Result<(), String> {
+    match args.subcommand() {
+        ("new", Some(args)) => new(args),
+        ("list", Some(args)) => list(args),
+        ("import", Some(args)) => import(args),
+        ("export", Some(args)) => export(args),
+        _ => unreachable!(),
+    }
+}
+
+fn new(args: &ArgMatches) -> Result<(), String> {
+    let name = args.value_of("NAME").unwrap();
+    let password = new_password(name)?;
+    let public_key = keystore(args)?
+        .generate(name, &password)
+        .map_err(|e| e.to_string())?;
+    println!("{} {}", name, public_key.to_hex());
+    Ok(())
+}
+
+fn list(args: &ArgMatches) -> Result<(), String> {
+    for key in keystore(args)?.list().map_err(|e| e.to_string())? {
+        match key.public_key {
+            Ok(public_key) => println!("{} {}", key.name, public_key.to_hex()),
+            Err(e) => eprintln!("{} {}", key.name, e),
+        }
+    }
+    Ok(())
+}
+
+fn import(args: &ArgMatches) -> Result<(), String> {
+    let name = args.value_of("NAME").unwrap();
+    let mut data = String::new();
+    File::open(args.value_of("FILE").unwrap())
+        .and_then(|mut file| file.read_to_string(&mut data))
+        .map_err(|e| e.to_string())?;
+    let keystore = keystore(args)?;
+
+    let public_key = match ::serde_json::from_str::<EncryptedKey>(&data) {
+        Ok(key) => {
+            // an exported key keeps its password, which has to be known
+            let password = password(&format!("Password for `{}`: ", name))?;
+            key.decrypt(&password).map_err(|e| e.to_string())?;
+            keystore.import(name, &key).map_err(|e| e.to_string())?;
+            key.public_key
+        }
+        Err(_) => {
+            let secret_key = Vec::<u8>::from_hex(data.trim())
+                .ok()
+                .and_then(|bytes| SecretKey::from_slice(&bytes))
+                .ok_or_else(|| "Neither a key file nor a hex-encoded secret key".to_owned())?;
+            let password = new_password(name)?;
+            keystore.add(name, &secret_key, &password).map_err(|e| e.to_string())?
+        }
+    };
+    println!("{} {}", name, public_key.to_hex());
+    Ok(())
+}
+
+fn export(args: &ArgMatches) -> Result<(), String> {
+    let name = args.value_of("NAME").unwrap();
+    let keystore = keystore(args)?;
+
+    let data = if args.is_present("plain") {
+        let password = password(&format!("Password for `{}`: ", name))?;
+        let (_, secret_key) = keystore.load(name, &password).map_err(|e| e.to_string())?;
+        secret_key.to_hex()
+    } else {
+        let key = keystore.export(name).map_err(|e| e.to_string())?;
+        ::serde_json::to_string_pretty(&key).unwrap()
+    };
+
+    create_key_file(Path::new(args.value_of("FILE").unwrap()))
+        .and_then(|mut file| file.write_all(data.as_bytes()))
+        .map_err(|e| e.to_string())
+}
+
+// asks for a password of a new key twice, unless it is given in `TS_PASSWORD`
+fn new_password(name: &str) -> Result<String, String> {
+    let first = password(&format!("New password for `{}`: ", name))?;
+    if ::std::env::var("TS_PASSWORD").is_err() {
+        let second = password("Repeat the password: ")?;
+        if first != second {
+            return Err("Passwords do not match".into());
+        }
+    }
+    Ok(first)
+}
@@ -0,0 +1,274 @@
+// Command-line client for timestamping files.
+//
+//   ts key new                creates a signing key
+//   ts stamp report.pdf       timestamps a file and writes `report.pdf.ts.json` next to it
+//   ts stamp --private f      timestamps only a salted commitment of a file, see `reveal.rs`
+//   ts stamp --anonymous f    timestamps a file without linking it to your key on chain
+//   ts check report.pdf       looks a file up by its content
+//   ts watch DIR...           timestamps new and changed files, see `watch.rs`
+//   ts git stamp [REV]        timestamps a commit or a tag, see `git.rs`
+//   ts export --pub-key K     exports timestamps as CSV or NDJSON, see `export.rs`
+//
+// The node is taken from `--node` or `TS_NODE` (`http://127.0.0.1:8000` by default). Keys live in
+// an encrypted keystore, `--keystore` or `TS_KEYSTORE` (`~/.ts/keys` by default); `--key` picks
+// one (`default` unless given). The password is read from `TS_PASSWORD` or asked for.
+//
+// A key may timestamp only once, so every file is signed with a key derived from yours and its
+// content, see `client::document_keypair`. The result is a TxTimestamp in the format of
+// `test.json`; `ts stamp` wraps it into TxOwned signed with your key, so your key is recorded as
+// its owner and `/v1/stats` and `ts export --pub-key` find it. With `--anonymous` the bare
+// TxTimestamp is sent and only you can tell the file is yours, by deriving its key again.
+
+// lints of idioms newer than the code, as in the library
+#![allow(bare_trait_objects)]
+#![allow(clippy::needless_borrowed_reference, clippy::unnecessary_map_or)]
+
+extern crate clap;
+extern crate exonum;
+extern crate notify;
+extern crate rpassword;
+extern crate serde_json;
+extern crate timestamping;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+
+use exonum::crypto::{Hash, PublicKey, SecretKey};
+use exonum::encoding::serialize::{encode_hex, FromHex};
+
+use std::env;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
+
+use timestamping::{commitment, unix_time, TimestampInfo, TxTimestamp};
+use timestamping::client::{self, Client, Receipt};
+use timestamping::keystore::Keystore;
+
+mod export;
+mod git;
+mod key;
+mod reveal;
+mod watch;
+
+const DEFAULT_NODE: &str = "http://127.0.0.1:8000";
+
+// receipts are named after the document with this suffix
+const RECEIPT_SUFFIX: &str = ".ts.json";
+
+fn main() {
+    let node = Arg::with_name("node")
+        .long("node")
+        .takes_value(true)
+        .global(true)
+        .help("Public API address of a node");
+    let keystore = Arg::with_name("keystore")
+        .long("keystore")
+        .takes_value(true)
+        .global(true)
+        .help("Keystore directory");
+    let key = Arg::with_name("key")
+        .long("key")
+        .takes_value(true)
+        .global(true)
+        .help("Name of the signing key in the keystore");
+
+    let matches = App::new("ts")
+        .about("Timestamps files")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .args(&[node, keystore, key])
+        .subcommand(key::command())
+        .subcommand(reveal::command())
+        .subcommand(watch::command())
+        .subcommand(git::command())
+        .subcommand(export::command())
+        .subcommand(SubCommand::with_name("stamp")
+            .about("Timestamps a file and writes a receipt next to it")
+            .arg(Arg::with_name("FILE").required(true))
+            .arg(Arg::with_name("private")
+                .long("private")
+                .help("Timestamps a salted commitment, so the content stays secret until revealed"))
+            .arg(Arg::with_name("anonymous")
+                .long("anonymous")
+                .help("Sends a bare TxTimestamp, so the chain does not record your key as the owner"))
+            .arg(Arg::with_name("timeout")
+                .long("timeout")
+                .takes_value(true)
+                .default_value("60")
+                .help("Seconds to wait for the commit")))
+        .subcommand(SubCommand::with_name("check")
+            .about("Looks a file up by its content")
+            .arg(Arg::with_name("FILE").required(true)))
+        .get_matches();
+
+    let result = match matches.subcommand() {
+        ("key", Some(args)) => key::run(args),
+        ("reveal", Some(args)) => reveal::run(args),
+        ("watch", Some(args)) => watch::run(args),
+        ("git", Some(args)) => git::run(args),
+        ("export", Some(args)) => export::run(args),
+        ("stamp", Some(args)) => stamp(args),
+        ("check", Some(args)) => check(args),
+        _ => unreachable!(),
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn stamp(args: &ArgMatches) -> Result<(), String> {
+    let path = Path::new(args.value_of("FILE").unwrap());
+    let timeout = args.value_of("timeout")
+        .unwrap()
+        .parse::<u64>()
+        .map_err(|_| "Invalid value of `--timeout`".to_owned())?;
+    let client = node_client(args);
+
+    let document = client::hash_file(path).map_err(|e| e.to_string())?;
+    let salt = if args.is_present("private") { Some(client::gen_salt()) } else { None };
+    let content = match salt {
+        Some(ref salt) => commitment(salt, &document),
+        None => document,
+    };
+
+    let info = stamp_content(args, &client, &content, timeout, args.is_present("anonymous"))?;
+
+    let mut receipt = Receipt::new(&info);
+    receipt.salt = salt.map(|salt| encode_hex(&salt));
+    let receipt_path = receipt_path(path);
+    let file = File::create(&receipt_path).map_err(|e| e.to_string())?;
+    serde_json::to_writer_pretty(file, &receipt).map_err(|e| e.to_string())?;
+
+    print_receipt(&receipt);
+    println!("Receipt: {}", receipt_path.display());
+    Ok(())
+}
+
+// Timestamps the content and waits for the commit. Content is timestamped only once, so a
+// timestamp which already exists is returned instead, possibly one made by someone else.
+fn stamp_content(
+    args: &ArgMatches,
+    client: &Client,
+    content: &Hash,
+    timeout: u64,
+    anonymous: bool,
+) -> Result<TimestampInfo, String> {
+    let (owner, owner_key) = load_key(args)?;
+    let (document, document_key) = client::document_keypair(&owner_key, content);
+
+    if let Some(info) = client.timestamp_by_content(content).map_err(|e| e.to_string())? {
+        if info.timestamp.pub_key() == &document || info.owner == Some(owner) {
+            println!("Already timestamped with this key");
+        } else {
+            println!("Already timestamped by another key, the receipt is for that timestamp");
+        }
+        return Ok(info);
+    }
+
+    let tx_hash = if anonymous {
+        let tx = TxTimestamp::new(&document, content, unix_time(), &document_key);
+        client.submit(&tx)
+    } else {
+        client.submit(&client::document_tx(&owner, &owner_key, content, unix_time()))
+    };
+    let tx_hash = tx_hash.map_err(|e| e.to_string())?;
+    client
+        .wait_for_commit(&tx_hash, Duration::from_secs(timeout))
+        .map_err(|e| e.to_string())?;
+    client
+        .timestamp_by_content(content)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "The timestamp is committed but not found".to_owned())
+}
+
+fn check(args: &ArgMatches) -> Result<(), String> {
+    let path = Path::new(args.value_of("FILE").unwrap());
+    let client = node_client(args);
+
+    let document = client::hash_file(path).map_err(|e| e.to_string())?;
+    let receipt_path = receipt_path(path);
+    let local = if receipt_path.exists() { Some(read_receipt(&receipt_path)?) } else { None };
+
+    // a private timestamp can only be found with the salt from its receipt
+    let content = match local.as_ref().and_then(|local| local.salt.as_ref()) {
+        Some(salt) => commitment(&salt_bytes(salt)?, &document),
+        None => document,
+    };
+    let info = client
+        .timestamp_by_content(&content)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("{} is not timestamped", path.display()))?;
+    let receipt = Receipt::new(&info);
+    print_receipt(&receipt);
+
+    // a local receipt has to describe the same timestamp as the chain does
+    if let Some(local) = local {
+        if local.content != receipt.content || local.tx_hash != receipt.tx_hash {
+            return Err(format!("{} does not match the chain", receipt_path.display()));
+        }
+        println!("Receipt {} matches", receipt_path.display());
+    }
+    Ok(())
+}
+
+fn read_receipt(path: &Path) -> Result<Receipt, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    serde_json::from_reader(file).map_err(|e| format!("Invalid receipt {}: {}", path.display(), e))
+}
+
+fn salt_bytes(salt: &str) -> Result<Vec<u8>, String> {
+    Vec::<u8>::from_hex(salt).map_err(|_| "Invalid salt in the receipt".to_owned())
+}
+
+fn print_receipt(receipt: &Receipt) {
+    println!("Content: {}", receipt.content.to_hex());
+    println!("Key:     {}", receipt.pub_key.to_hex());
+    println!("Time:    {}", receipt.time);
+    println!("Height:  {}", receipt.height);
+    println!("Tx:      {}", receipt.tx_hash.to_hex());
+}
+
+fn receipt_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap().to_os_string();
+    name.push(RECEIPT_SUFFIX);
+    path.with_file_name(name)
+}
+
+fn node_client(args: &ArgMatches) -> Client {
+    let node = args.value_of("node")
+        .map(str::to_owned)
+        .or_else(|| env::var("TS_NODE").ok())
+        .unwrap_or_else(|| DEFAULT_NODE.to_owned());
+    Client::new(&node)
+}
+
+fn keystore(args: &ArgMatches) -> Result<Keystore, String> {
+    let dir = args.value_of("keystore")
+        .map(PathBuf::from)
+        .or_else(|| env::var("TS_KEYSTORE").ok().map(PathBuf::from))
+        .unwrap_or_else(|| {
+            let home = env::var("HOME").unwrap_or_else(|_| ".".to_owned());
+            Path::new(&home).join(".ts").join("keys")
+        });
+    Keystore::open(dir).map_err(|e| e.to_string())
+}
+
+fn key_name<'a>(args: &'a ArgMatches) -> &'a str {
+    args.value_of("key").unwrap_or("default")
+}
+
+fn password(prompt: &str) -> Result<String, String> {
+    match env::var("TS_PASSWORD") {
+        Ok(password) => Ok(password),
+        Err(_) => rpassword::prompt_password_stdout(prompt).map_err(|e| e.to_string()),
+    }
+}
+
+// decrypts the signing key chosen with `--key`
+fn load_key(args: &ArgMatches) -> Result<(PublicKey, SecretKey), String> {
+    let keystore = keystore(args)?;
+    let password = password(&format!("Password for `{}`: ", key_name(args)))?;
+    keystore.load(key_name(args), &password).map_err(|e| e.to_string())
+}
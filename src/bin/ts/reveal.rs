@@ -0,0 +1,67 @@
+// `ts reveal` discloses a file timestamped with `ts stamp --private`.
+//
+// The private timestamp holds only `commitment(salt, document)`, and the salt stays in the
+// receipt. Revealing sends the salt and the document hash in TxReveal signed with the key of the
+// timestamp, after which anyone can link the document to the original timestamp.
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use std::path::Path;
+use std::time::Duration;
+
+use timestamping::{commitment, TxReveal};
+use timestamping::client;
+
+use super::{load_key, node_client, read_receipt, receipt_path, salt_bytes};
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("reveal")
+        .about("Discloses a file timestamped with `stamp --private`")
+        .arg(Arg::with_name("FILE").required(true))
+        .arg(Arg::with_name("timeout")
+            .long("timeout")
+            .takes_value(true)
+            .default_value("60")
+            .help("Seconds to wait for the commit"))
+}
+
+pub fn run(args: &ArgMatches) -> Result<(), String> {
+    let path = Path::new(args.value_of("FILE").unwrap());
+    let timeout = args.value_of("timeout")
+        .unwrap()
+        .parse::<u64>()
+        .map_err(|_| "Invalid value of `--timeout`".to_owned())?;
+
+    let receipt = read_receipt(&receipt_path(path))?;
+    let salt = match receipt.salt {
+        Some(ref salt) => salt_bytes(salt)?,
+        None => return Err(format!("{} is not timestamped privately", path.display())),
+    };
+    let document = client::hash_file(path).map_err(|e| e.to_string())?;
+    if commitment(&salt, &document) != receipt.content {
+        return Err(format!("{} was changed after it was timestamped", path.display()));
+    }
+
+    // the timestamp was signed with the key derived for its content
+    let (_, owner_key) = load_key(args)?;
+    let (public_key, secret_key) = client::document_keypair(&owner_key, &receipt.content);
+    if public_key != receipt.pub_key {
+        return Err("The receipt was signed with another key".into());
+    }
+
+    let client = node_client(args);
+    let tx = TxReveal::new(&public_key, &salt, &document, &secret_key);
+    let tx_hash = client.submit(&tx).map_err(|e| e.to_string())?;
+    client
+        .wait_for_commit(&tx_hash, Duration::from_secs(timeout))
+        .map_err(|e| e.to_string())?;
+
+    // a failed reveal is committed too, so its result is checked
+    match client.reveal(&public_key).map_err(|e| e.to_string())? {
+        Some(ref reveal) if reveal.tx_hash() == &tx_hash || reveal.document() == &document => {
+            println!("Revealed {} at height {}", path.display(), reveal.height());
+            Ok(())
+        }
+        _ => Err("The reveal was not accepted".into()),
+    }
+}
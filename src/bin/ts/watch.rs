@@ -0,0 +1,245 @@
+// `ts watch` daemon timestamping new and changed files in directories.
+//
+// Changes reported by the OS (inotify on Linux) are collected for `--interval` seconds, hashed
+// and sent in batches. Every step is appended to a journal (`--journal`, `.ts-journal` in the
+// working directory by default) as a JSON line: a file with its content hash, then the
+// transaction hash once submitted, then the receipt once committed. On start the journal is
+// replayed, the directories are rescanned for files changed while the daemon was down, and
+// transactions still waiting for a commit are checked and sent again once they are lost. When
+// the node cannot be reached, submissions are retried with a growing delay. The journal and the
+// retry schedule live in `timestamping::journal`.
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use exonum::crypto::{Hash, PublicKey, SecretKey};
+
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use timestamping::{unix_time, TxOwned, MAX_BATCH_SIZE};
+use timestamping::client::{self, Client, ClientError};
+use timestamping::journal::{Backoff, Journal, JournalEntry, Pending};
+
+use super::{load_key, node_client, RECEIPT_SUFFIX};
+
+// delays between retries when the node is unavailable
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+enum WatchError {
+    // the node failed, the work is retried later
+    Node(ClientError),
+    // the journal cannot be written, which stops the daemon
+    Journal(String),
+}
+
+impl From<ClientError> for WatchError {
+    fn from(e: ClientError) -> Self {
+        WatchError::Node(e)
+    }
+}
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("watch")
+        .about("Timestamps new and changed files in directories")
+        .arg(Arg::with_name("DIR").required(true).multiple(true))
+        .arg(Arg::with_name("journal")
+            .long("journal")
+            .takes_value(true)
+            .default_value(".ts-journal")
+            .help("Journal of timestamped files"))
+        .arg(Arg::with_name("interval")
+            .long("interval")
+            .takes_value(true)
+            .default_value("5")
+            .help("Seconds to collect changes before sending them"))
+}
+
+struct Daemon {
+    client: Client,
+    // the journal may be kept in a watched directory, its own changes are ignored
+    journal_path: PathBuf,
+    owner: PublicKey,
+    owner_key: SecretKey,
+    journal: Journal,
+    // changed files waiting to be hashed and sent
+    queue: BTreeSet<PathBuf>,
+    backoff: Backoff,
+}
+
+impl Daemon {
+    fn tx(&self, content: &Hash) -> TxOwned {
+        client::document_tx(&self.owner, &self.owner_key, content, unix_time())
+    }
+
+    fn enqueue(&mut self, path: PathBuf) {
+        let is_receipt = path.to_string_lossy().ends_with(RECEIPT_SUFFIX);
+        let is_journal = fs::canonicalize(&path).map_or(false, |path| path == self.journal_path);
+        if !is_receipt && !is_journal && path.is_file() {
+            self.queue.insert(path);
+        }
+    }
+
+    fn scan(&mut self, dir: &Path) -> Result<(), String> {
+        for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.is_dir() {
+                self.scan(&path)?;
+            } else {
+                self.enqueue(path);
+            }
+        }
+        Ok(())
+    }
+
+    // sends queued files and collects receipts; on a node failure everything is kept for a retry
+    fn process(&mut self) -> Result<(), String> {
+        if !self.backoff.ready(Instant::now()) {
+            return Ok(());
+        }
+        match self.submit_queue().and_then(|_| self.check_pending()) {
+            Ok(()) => {
+                self.backoff.succeeded();
+                Ok(())
+            }
+            Err(WatchError::Node(e)) => {
+                let delay = self.backoff.failed(Instant::now());
+                eprintln!("Node failed ({}), retrying in {:?}", e, delay);
+                Ok(())
+            }
+            Err(WatchError::Journal(e)) => Err(e),
+        }
+    }
+
+    fn submit_queue(&mut self) -> Result<(), WatchError> {
+        let mut hashed = Vec::new();
+        for path in &self.queue {
+            // a file may be gone or unreadable by now, it is reported again once it changes
+            match client::hash_file(path) {
+                Ok(content) => hashed.push((path.clone(), content)),
+                Err(e) => eprintln!("Skipping {}: {}", path.display(), e),
+            }
+        }
+        let batch = self.journal.unsent(hashed);
+
+        for chunk in batch.chunks(MAX_BATCH_SIZE) {
+            let txs: Vec<TxOwned> = chunk.iter().map(|&(_, ref content)| self.tx(content)).collect();
+            let results = self.client.submit_batch(&txs)?;
+
+            for (&(ref path, content), result) in chunk.iter().zip(results) {
+                match result.tx_hash {
+                    Some(tx_hash) => {
+                        let entry = JournalEntry {
+                            path: path.clone(),
+                            content,
+                            tx_hash: Some(tx_hash),
+                            receipt: None,
+                            sent: unix_time(),
+                        };
+                        self.journal.record(entry).map_err(|e| WatchError::Journal(e.to_string()))?;
+                        println!("Sent {}", path.display());
+                    }
+                    None => eprintln!("Rejected {}: {}", path.display(), result.error.unwrap_or_default()),
+                }
+                self.queue.remove(path);
+            }
+        }
+        self.queue.clear();
+        Ok(())
+    }
+
+    fn check_pending(&mut self) -> Result<(), WatchError> {
+        let mut lost = Vec::new();
+        for (mut entry, state) in self.journal.check_pending(&self.client, unix_time())? {
+            match state {
+                Pending::Committed(receipt) => {
+                    println!("Timestamped {} at height {}", entry.path.display(), receipt.height);
+                    entry.receipt = Some(receipt);
+                    self.journal.record(entry).map_err(|e| WatchError::Journal(e.to_string()))?;
+                }
+                Pending::Waiting => (),
+                Pending::Lost => lost.push(entry),
+            }
+        }
+
+        for chunk in lost.chunks(MAX_BATCH_SIZE) {
+            let txs: Vec<TxOwned> = chunk.iter().map(|entry| self.tx(&entry.content)).collect();
+            let results = self.client.submit_batch(&txs)?;
+            for (entry, result) in chunk.iter().zip(results) {
+                match result.tx_hash {
+                    Some(tx_hash) => {
+                        let entry = JournalEntry {
+                            tx_hash: Some(tx_hash),
+                            sent: unix_time(),
+                            ..entry.clone()
+                        };
+                        println!("Sent {} again", entry.path.display());
+                        self.journal.record(entry).map_err(|e| WatchError::Journal(e.to_string()))?;
+                    }
+                    None => eprintln!("Rejected {}: {}", entry.path.display(), result.error.unwrap_or_default()),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn run(args: &ArgMatches) -> Result<(), String> {
+    let dirs: Vec<PathBuf> = args.values_of("DIR").unwrap().map(PathBuf::from).collect();
+    let interval = args.value_of("interval")
+        .unwrap()
+        .parse::<u64>()
+        .map_err(|_| "Invalid value of `--interval`".to_owned())?;
+    let interval = Duration::from_secs(interval);
+
+    let (owner, owner_key) = load_key(args)?;
+    let journal_path = Path::new(args.value_of("journal").unwrap());
+    let journal = Journal::open(journal_path).map_err(|e| e.to_string())?;
+    let mut daemon = Daemon {
+        client: node_client(args),
+        journal_path: fs::canonicalize(journal_path).map_err(|e| e.to_string())?,
+        owner,
+        owner_key,
+        journal,
+        queue: BTreeSet::new(),
+        backoff: Backoff::new(MIN_RETRY_DELAY, MAX_RETRY_DELAY),
+    };
+
+    let (sender, receiver) = channel();
+    let mut watcher = watcher(sender, Duration::from_secs(1)).map_err(|e| e.to_string())?;
+    for dir in &dirs {
+        watcher
+            .watch(dir, RecursiveMode::Recursive)
+            .map_err(|e| e.to_string())?;
+        daemon.scan(dir)?;
+    }
+    println!("Watching {} directories", dirs.len());
+
+    loop {
+        daemon.process()?;
+
+        let deadline = Instant::now() + interval;
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            match receiver.recv_timeout(deadline - now) {
+                Ok(DebouncedEvent::Create(path)) |
+                Ok(DebouncedEvent::Write(path)) |
+                Ok(DebouncedEvent::Rename(_, path)) => daemon.enqueue(path),
+                Ok(DebouncedEvent::Error(e, path)) => {
+                    eprintln!("Watch error{}: {}", path.map_or(String::new(), |p| format!(" at {}", p.display())), e)
+                }
+                Ok(_) => (),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Err("Watcher stopped".into()),
+            }
+        }
+    }
+}
@@ -0,0 +1,344 @@
+// Typed client of the timestamping REST API.
+//
+// Requests go through a `Transport`, so the same client works against a node over HTTP
+// (`HttpTransport`) or against any other handler, e.g. a testkit in tests.
+
+use exonum::crypto::{self, gen_keypair_from_seed, Hash, HashStream, PublicKey, SecretKey, Seed};
+use exonum::encoding::serialize::FromHex;
+
+use hyper;
+use hyper::header::ContentType;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::export::{ExportFilter, ExportFormat};
+use super::{BatchItemResponse, Reveal, Timestamp, TimestampInfo, TimestampResponse, TransactionStatus,
+            TxOwned, TxTimestamp};
+
+// path of the service API relative to a node's address
+pub const SERVICE_PATH: &str = "api/services/timestamp";
+
+// how often `wait_for_commit` asks a node about a transaction
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// length of salts made by `gen_salt`
+pub const SALT_LENGTH: usize = 32;
+
+// files are hashed in chunks of this size
+const FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub enum ClientError {
+    // a node could not be reached or the connection failed
+    Transport(String),
+    // a node answered with an error status and a message
+    Api { status: u16, message: String },
+    // a response could not be parsed
+    Decode(String),
+    // a transaction was not committed within the given time
+    Timeout,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ClientError::Transport(ref e) => write!(f, "Transport error: {}", e),
+            ClientError::Api { status, ref message } => write!(f, "API error {}: {}", status, message),
+            ClientError::Decode(ref e) => write!(f, "Invalid response: {}", e),
+            ClientError::Timeout => write!(f, "Timed out waiting for commit"),
+        }
+    }
+}
+
+impl Error for ClientError {
+    fn description(&self) -> &str {
+        match *self {
+            ClientError::Transport(_) => "transport error",
+            ClientError::Api { .. } => "API error",
+            ClientError::Decode(_) => "invalid response",
+            ClientError::Timeout => "timed out",
+        }
+    }
+}
+
+// A raw HTTP response: a status code and a body
+pub struct RawResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+// Sends requests to a node. Paths are relative to the node's address, e.g. `api/services/...`
+pub trait Transport {
+    fn get(&self, path: &str) -> Result<RawResponse, ClientError>;
+    fn post(&self, path: &str, body: &[u8]) -> Result<RawResponse, ClientError>;
+
+    // GET request copying a successful response body to `out`. The default implementation reads
+    // the whole body first, transports able to stream should override it.
+    fn get_into(&self, path: &str, out: &mut Write) -> Result<(), ClientError> {
+        let response = check_status(self.get(path)?)?;
+        out.write_all(&response.body).map_err(|e| ClientError::Transport(e.to_string()))
+    }
+}
+
+// Transport talking to a node over HTTP
+pub struct HttpTransport {
+    url: String,
+    client: hyper::Client,
+}
+
+impl HttpTransport {
+    // `url` is the node's public API address, e.g. `http://127.0.0.1:8000`
+    pub fn new(url: &str) -> Self {
+        HttpTransport {
+            url: url.trim_end_matches('/').to_owned(),
+            client: hyper::Client::new(),
+        }
+    }
+
+    fn read(response: hyper::error::Result<hyper::client::Response>) -> Result<RawResponse, ClientError> {
+        let mut response = response.map_err(|e| ClientError::Transport(e.to_string()))?;
+        let mut body = Vec::new();
+        response
+            .read_to_end(&mut body)
+            .map_err(|e| ClientError::Transport(e.to_string()))?;
+        Ok(RawResponse {
+            status: response.status.to_u16(),
+            body,
+        })
+    }
+}
+
+impl Transport for HttpTransport {
+    fn get(&self, path: &str) -> Result<RawResponse, ClientError> {
+        let url = format!("{}/{}", self.url, path);
+        HttpTransport::read(self.client.get(&url).send())
+    }
+
+    fn post(&self, path: &str, body: &[u8]) -> Result<RawResponse, ClientError> {
+        let url = format!("{}/{}", self.url, path);
+        HttpTransport::read(
+            self.client
+                .post(&url)
+                .header(ContentType::json())
+                .body(body)
+                .send(),
+        )
+    }
+
+    fn get_into(&self, path: &str, out: &mut Write) -> Result<(), ClientError> {
+        let url = format!("{}/{}", self.url, path);
+        let mut response = self.client
+            .get(&url)
+            .send()
+            .map_err(|e| ClientError::Transport(e.to_string()))?;
+        if response.status.to_u16() >= 400 {
+            return HttpTransport::read(Ok(response)).and_then(check_status).map(|_| ());
+        }
+        io::copy(&mut response, out).map_err(|e| ClientError::Transport(e.to_string()))?;
+        Ok(())
+    }
+}
+
+pub struct Client<T = HttpTransport> {
+    transport: T,
+}
+
+impl Client<HttpTransport> {
+    // client of a node at `url`, e.g. `http://127.0.0.1:8000`
+    pub fn new(url: &str) -> Self {
+        Client::with_transport(HttpTransport::new(url))
+    }
+}
+
+impl<T: Transport> Client<T> {
+    pub fn with_transport(transport: T) -> Self {
+        Client { transport }
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    // sends a signed transaction of the service, returns its hash
+    pub fn submit<S: Serialize>(&self, tx: &S) -> Result<Hash, ClientError> {
+        let response: TimestampResponse = self.post("v1/submit", tx)?;
+        Ok(response.tx_hash)
+    }
+
+    // sends many signed transactions at once, returns a hash or an error for each of them
+    pub fn submit_batch<S: Serialize>(&self, txs: &[S]) -> Result<Vec<BatchItemResponse>, ClientError> {
+        self.post("v1/submit_batch", &txs)
+    }
+
+    // a timestamp of `pub_key`, if there is one
+    pub fn timestamp(&self, pub_key: &PublicKey) -> Result<Option<Timestamp>, ClientError> {
+        self.get_optional(&format!("v1/timestamp/{}", pub_key.to_hex()))
+    }
+
+    pub fn timestamps(&self) -> Result<Vec<Timestamp>, ClientError> {
+        self.get("v1/timestamps")
+    }
+
+    // the earliest timestamp of `content`, if there is one
+    pub fn timestamp_by_content(&self, content: &Hash) -> Result<Option<TimestampInfo>, ClientError> {
+        self.get_optional(&format!("v1/content/{}", content.to_hex()))
+    }
+
+    // the reveal of a timestamp made in the commitment mode, if there is one
+    pub fn reveal(&self, pub_key: &PublicKey) -> Result<Option<Reveal>, ClientError> {
+        self.get_optional(&format!("v1/reveal/{}", pub_key.to_hex()))
+    }
+
+    // streams timestamps matching `filter` to `out`
+    pub fn export(&self, format: ExportFormat, filter: &ExportFilter, out: &mut Write) -> Result<(), ClientError> {
+        let mut endpoint = format!("v1/export?format={}", format.as_str());
+        for key in &filter.keys {
+            endpoint.push_str(&format!("&pub_key={}", key.to_hex()));
+        }
+        if let Some(from) = filter.from {
+            endpoint.push_str(&format!("&from={}", from));
+        }
+        if let Some(to) = filter.to {
+            endpoint.push_str(&format!("&to={}", to));
+        }
+        self.transport.get_into(&service_path(&endpoint), out)
+    }
+
+    pub fn transaction_status(&self, tx_hash: &Hash) -> Result<TransactionStatus, ClientError> {
+        self.get(&format!("v1/transaction/{}", tx_hash.to_hex()))
+    }
+
+    // polls a node until the transaction is committed, returns the height of its block
+    pub fn wait_for_commit(&self, tx_hash: &Hash, timeout: Duration) -> Result<u64, ClientError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(height) = self.transaction_status(tx_hash)?.height {
+                return Ok(height);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(ClientError::Timeout);
+            }
+            thread::sleep(::std::cmp::min(POLL_INTERVAL, deadline - now));
+        }
+    }
+
+    // GET request to an endpoint of the service
+    pub fn get<D: DeserializeOwned>(&self, endpoint: &str) -> Result<D, ClientError> {
+        let response = self.transport.get(&service_path(endpoint))?;
+        decode(response)
+    }
+
+    // GET request to an endpoint of the service, `None` if it answers with 404
+    pub fn get_optional<D: DeserializeOwned>(&self, endpoint: &str) -> Result<Option<D>, ClientError> {
+        let response = self.transport.get(&service_path(endpoint))?;
+        if response.status == 404 {
+            return Ok(None);
+        }
+        decode(response).map(Some)
+    }
+
+    // POST request with a JSON body to an endpoint of the service
+    pub fn post<S: Serialize, D: DeserializeOwned>(&self, endpoint: &str, body: &S) -> Result<D, ClientError> {
+        let body = ::serde_json::to_vec(body).map_err(|e| ClientError::Decode(e.to_string()))?;
+        let response = self.transport.post(&service_path(endpoint), &body)?;
+        decode(response)
+    }
+}
+
+// Proof that a document was timestamped, kept next to the document. In the commitment mode the
+// content is `commitment(salt, document)`, and the receipt holds the hex salt needed to reveal it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Receipt {
+    pub content: Hash,
+    pub pub_key: PublicKey,
+    pub time: u64,
+    pub height: u64,
+    pub tx_hash: Hash,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+}
+
+impl Receipt {
+    pub fn new(info: &TimestampInfo) -> Self {
+        Receipt {
+            content: *info.timestamp.content(),
+            pub_key: *info.timestamp.pub_key(),
+            time: info.timestamp.time(),
+            height: info.height,
+            tx_hash: info.tx_hash,
+            salt: None,
+        }
+    }
+}
+
+// random salt for the commitment mode
+pub fn gen_salt() -> Vec<u8> {
+    ::exonum_sodiumoxide::randombytes::randombytes(SALT_LENGTH)
+}
+
+// SHA-256 of a file, the content hash for TxTimestamp
+pub fn hash_file<P: AsRef<Path>>(path: P) -> io::Result<Hash> {
+    let mut file = File::open(path)?;
+    let mut stream = HashStream::new();
+    let mut buf = vec![0; FILE_CHUNK_SIZE];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            return Ok(stream.hash());
+        }
+        stream = stream.update(&buf[..read]);
+    }
+}
+
+// Every key may timestamp only once, so documents are signed with keys derived from the owner's
+// secret key and the content. The same document always gets the same key, and the owner can
+// prove a derived key is theirs by deriving it again. Derived keys say nothing about the owner,
+// so timestamps are sent with `document_tx`, which records the owner's key on chain.
+pub fn document_keypair(secret_key: &SecretKey, content: &Hash) -> (PublicKey, SecretKey) {
+    // the bytes of a secret key are only exposed in hex
+    let mut material = Vec::<u8>::from_hex(secret_key.to_hex()).unwrap();
+    material.extend_from_slice(content.as_ref());
+    let seed = Seed::from_slice(crypto::hash(&material).as_ref()).unwrap();
+    gen_keypair_from_seed(&seed)
+}
+
+// TxTimestamp of `content` signed with its derived key, wrapped into TxOwned of the owner, so
+// statistics and exports of the owner's key include it
+pub fn document_tx(owner: &PublicKey, secret_key: &SecretKey, content: &Hash, time: u64) -> TxOwned {
+    let (public_key, document_key) = document_keypair(secret_key, content);
+    let tx = TxTimestamp::new(&public_key, content, time, &document_key);
+    TxOwned::wrap(&tx, owner, secret_key)
+}
+
+fn service_path(endpoint: &str) -> String {
+    format!("{}/{}", SERVICE_PATH, endpoint)
+}
+
+// turns error responses into `ClientError::Api`
+fn check_status(response: RawResponse) -> Result<RawResponse, ClientError> {
+    if response.status >= 400 {
+        // errors of the API are JSON strings, but a proxy in between may answer with anything
+        let message = ::serde_json::from_slice::<String>(&response.body)
+            .unwrap_or_else(|_| String::from_utf8_lossy(&response.body).into_owned());
+        return Err(ClientError::Api {
+            status: response.status,
+            message,
+        });
+    }
+    Ok(response)
+}
+
+fn decode<D: DeserializeOwned>(response: RawResponse) -> Result<D, ClientError> {
+    let response = check_status(response)?;
+    ::serde_json::from_slice(&response.body).map_err(|e| ClientError::Decode(e.to_string()))
+}
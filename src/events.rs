@@ -0,0 +1,179 @@
+// Push feed of committed timestamps. `handle_commit` of the service bumps the height in
+// `CommitNotifier`, and every open event stream reads new timestamps from the commit history.
+// A stream holds a worker thread of the API server while it is open, so `CommitNotifier` also
+// caps the number of open streams.
+
+use exonum::blockchain::Blockchain;
+
+use iron::response::WriteBody;
+
+use std::cmp;
+use std::io::{self, Write};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use super::{TimestampInfo, TimestampSchema};
+
+// a comment line is sent when there were no commits for this long, so dead connections are noticed
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+// number of events read from the commit history at once, so a long replay is never held in memory
+const READ_BATCH: u64 = 100;
+
+// number of event streams open at the same time unless the service sets another limit
+pub const DEFAULT_MAX_EVENT_STREAMS: usize = 8;
+
+// Height of the latest committed block shared between the service and event streams, and the
+// number of open streams
+pub struct CommitNotifier {
+    height: Mutex<u64>,
+    committed: Condvar,
+    streams: Mutex<usize>,
+    max_streams: usize,
+}
+
+impl CommitNotifier {
+    pub fn new() -> Self {
+        CommitNotifier::with_max_streams(DEFAULT_MAX_EVENT_STREAMS)
+    }
+
+    pub fn with_max_streams(max_streams: usize) -> Self {
+        CommitNotifier {
+            height: Mutex::new(0),
+            committed: Condvar::new(),
+            streams: Mutex::new(0),
+            max_streams,
+        }
+    }
+
+    pub fn height(&self) -> u64 {
+        *self.height.lock().unwrap()
+    }
+
+    pub fn notify(&self, height: u64) {
+        *self.height.lock().unwrap() = height;
+        self.committed.notify_all();
+    }
+
+    // blocks until a block above `height` is committed or `timeout` passes, returns the latest height
+    pub fn wait_above(&self, height: u64, timeout: Duration) -> u64 {
+        let current = self.height.lock().unwrap();
+        if *current > height {
+            return *current;
+        }
+        let (current, _) = self.committed.wait_timeout(current, timeout).unwrap();
+        *current
+    }
+
+    // takes a place for a new stream, `false` if all of them are taken
+    fn open_stream(&self) -> bool {
+        let mut streams = self.streams.lock().unwrap();
+        if *streams >= self.max_streams {
+            return false;
+        }
+        *streams += 1;
+        true
+    }
+
+    fn close_stream(&self) {
+        *self.streams.lock().unwrap() -= 1;
+    }
+}
+
+impl Default for CommitNotifier {
+    fn default() -> Self {
+        CommitNotifier::new()
+    }
+}
+
+// Body of a `text/event-stream` response. Event ids are block heights, so a client may resume with
+// the id of the last received event; events of that block are sent again and can be told apart by
+// `tx_hash`. The stream gives its place back to `CommitNotifier` when it is dropped.
+pub struct EventStream {
+    blockchain: Blockchain,
+    notifier: Arc<CommitNotifier>,
+    from_height: u64,
+    limit: Option<u64>,
+}
+
+impl EventStream {
+    // `None` if as many streams as `notifier` allows are already open
+    pub fn open(
+        blockchain: Blockchain,
+        notifier: Arc<CommitNotifier>,
+        from_height: u64,
+        limit: Option<u64>,
+    ) -> Option<Self> {
+        if !notifier.open_stream() {
+            return None;
+        }
+        Some(EventStream {
+            blockchain,
+            notifier,
+            from_height,
+            limit,
+        })
+    }
+
+    // reads at most `count` events starting from `position` in the commit history, returns them
+    // with the position following the last one
+    fn read_events(&self, position: Option<u64>, count: u64) -> (Vec<TimestampInfo>, u64) {
+        let snapshot = self.blockchain.snapshot();
+        let schema = TimestampSchema::new(&snapshot);
+
+        let start = position.unwrap_or_else(|| schema.history_position(self.from_height));
+        let events: Vec<TimestampInfo> = schema
+            .history()
+            .iter_from(start)
+            .take(count as usize)
+            .map(|pub_key| schema.info(&pub_key).unwrap())
+            .collect();
+        let next_position = start + events.len() as u64;
+        (events, next_position)
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.notifier.close_stream();
+    }
+}
+
+impl WriteBody for EventStream {
+    fn write_body(&mut self, res: &mut Write) -> io::Result<()> {
+        let mut position = None;
+        let mut sent = 0;
+        loop {
+            let height = self.notifier.height();
+            loop {
+                let count = self.limit.map_or(READ_BATCH, |limit| cmp::min(limit - sent, READ_BATCH));
+                if count == 0 {
+                    return Ok(());
+                }
+                let (events, next_position) = self.read_events(position, count);
+                position = Some(next_position);
+
+                for event in &events {
+                    write_event(res, event)?;
+                }
+                sent += events.len() as u64;
+                // a short batch is the end of the history committed so far
+                if (events.len() as u64) < count {
+                    break;
+                }
+            }
+
+            // a failed write means the client has gone, which ends the stream
+            res.flush()?;
+            if self.notifier.wait_above(height, KEEPALIVE_INTERVAL) == height {
+                res.write_all(b": keepalive\n\n")?;
+                res.flush()?;
+            }
+        }
+    }
+}
+
+fn write_event(out: &mut Write, event: &TimestampInfo) -> io::Result<()> {
+    let data = ::serde_json::to_string(event).unwrap();
+    write!(out, "id: {}\nevent: timestamp\ndata: {}\n\n", event.height, data)
+}
@@ -0,0 +1,17 @@
+// Static web explorer served by the service at `/explorer`. The pages are compiled into the
+// binary and read everything through the public REST API, see `explorer/app.js`.
+
+// name, content type and body of each file
+const ASSETS: &[(&str, &str, &str)] = &[
+    ("index.html", "text/html; charset=utf-8", include_str!("explorer/index.html")),
+    ("app.js", "application/javascript; charset=utf-8", include_str!("explorer/app.js")),
+    ("style.css", "text/css; charset=utf-8", include_str!("explorer/style.css")),
+];
+
+// content type and body of a file of the explorer
+pub fn asset(name: &str) -> Option<(&'static str, &'static str)> {
+    ASSETS
+        .iter()
+        .find(|&&(asset, _, _)| asset == name)
+        .map(|&(_, content_type, body)| (content_type, body))
+}
@@ -0,0 +1,256 @@
+// Explorer of the timestamping service. Everything is read from the public REST API of the node
+// which serves this page: the service API and the blockchain explorer API of Exonum.
+(function () {
+  'use strict';
+
+  // e.g. `/api/services/timestamp` for the page at `/api/services/timestamp/explorer`
+  var SERVICE = location.pathname.replace(/\/explorer\/?$/, '');
+  var BLOCKS = SERVICE.replace(/\/services\/[^/]+$/, '/explorer/v1/blocks');
+  var RECENT_BLOCKS = 10;
+
+  function $(id) {
+    return document.getElementById(id);
+  }
+
+  // resolves with parsed JSON or null for 404, rejects with the error message of the API
+  function getJson(url) {
+    return fetch(url).then(function (res) {
+      if (res.status === 404) {
+        return null;
+      }
+      return res.json().then(function (body) {
+        if (!res.ok) {
+          throw new Error(typeof body === 'string' ? body : res.statusText);
+        }
+        return body;
+      });
+    });
+  }
+
+  function text(value) {
+    var span = document.createElement('span');
+    span.textContent = value;
+    return span.innerHTML;
+  }
+
+  function formatTime(seconds) {
+    return new Date(Number(seconds) * 1000).toISOString().replace('T', ' ').replace(/\..*/, ' UTC');
+  }
+
+  function card(rows) {
+    var html = '<div class="card"><dl>';
+    rows.forEach(function (row) {
+      html += '<dt>' + text(row[0]) + '</dt><dd>' + row[1] + '</dd>';
+    });
+    return html + '</dl></div>';
+  }
+
+  function blockLink(height) {
+    return '<a href="#block/' + text(height) + '">' + text(height) + '</a>';
+  }
+
+  // a timestamp with its height and transaction, as returned by `/v1/content/:hash`
+  function infoCard(info) {
+    var timestamp = info.timestamp;
+    return card([
+      ['Content', text(timestamp.content)],
+      ['Key', text(timestamp.pub_key)],
+      ['Time', text(formatTime(timestamp.time))],
+      ['Block', blockLink(info.height)],
+      ['Transaction', text(info.tx_hash)]
+    ]);
+  }
+
+  function timestampCard(timestamp) {
+    return card([
+      ['Content', text(timestamp.content)],
+      ['Key', text(timestamp.pub_key)],
+      ['Time', text(formatTime(timestamp.time))]
+    ]);
+  }
+
+  function showError(target, error) {
+    target.innerHTML = '<p class="error">' + text(error.message || error) + '</p>';
+  }
+
+  // timestamps committed in the block at `height`
+  function blockTimestamps(height) {
+    var to = Number(height) + 1;
+    return getJson(SERVICE + '/v1/timestamps?from_height=' + height + '&to_height=' + to + '&limit=1000')
+      .then(function (page) {
+        return page ? page.timestamps : [];
+      });
+  }
+
+  function showRecent() {
+    var target = $('recent-list');
+    target.textContent = 'Loading…';
+    getJson(BLOCKS + '?count=' + RECENT_BLOCKS + '&skip_empty_blocks=true')
+      .then(function (res) {
+        var blocks = (res && (res.blocks || res)) || [];
+        return Promise.all(blocks.map(function (block) {
+          return blockTimestamps(block.height).then(function (timestamps) {
+            return { height: block.height, timestamps: timestamps };
+          });
+        }));
+      })
+      .then(function (blocks) {
+        var html = '';
+        blocks.forEach(function (block) {
+          if (block.timestamps.length) {
+            html += '<h3>Block ' + blockLink(block.height) + '</h3>';
+            html += block.timestamps.map(timestampCard).join('');
+          }
+        });
+        target.innerHTML = html || '<p>No timestamps yet.</p>';
+      })
+      .catch(function (e) {
+        showError(target, e);
+      });
+  }
+
+  // a content hash and a public key look alike, so both lookups are tried
+  function search(query) {
+    var target = $('search-result');
+    query = query.trim().toLowerCase();
+    if (!/^[0-9a-f]{64}$/.test(query)) {
+      showError(target, 'Enter 64 hexadecimal characters');
+      return;
+    }
+    target.textContent = 'Searching…';
+    getJson(SERVICE + '/v1/content/' + query)
+      .then(function (info) {
+        if (info) {
+          target.innerHTML = infoCard(info);
+          return;
+        }
+        return getJson(SERVICE + '/v1/timestamp/' + query).then(function (timestamp) {
+          target.innerHTML = timestamp ? timestampCard(timestamp) : '<p>Nothing found.</p>';
+        });
+      })
+      .catch(function (e) {
+        showError(target, e);
+      });
+  }
+
+  function hex(buffer) {
+    return Array.prototype.map.call(new Uint8Array(buffer), function (byte) {
+      return ('0' + byte.toString(16)).slice(-2);
+    }).join('');
+  }
+
+  // content hashes are SHA-256 of files, the same as `ts stamp` computes
+  function verify(file) {
+    var target = $('verify-result');
+    target.textContent = 'Hashing ' + file.name + '…';
+    var reader = new FileReader();
+    reader.onload = function () {
+      crypto.subtle.digest('SHA-256', reader.result)
+        .then(function (digest) {
+          var hash = hex(digest);
+          return Promise.all([
+            hash,
+            getJson(SERVICE + '/v1/content/' + hash),
+            getJson(SERVICE + '/v1/revealed/' + hash)
+          ]);
+        })
+        .then(function (res) {
+          var hash = res[0], info = res[1], revealed = res[2];
+          if (info) {
+            target.innerHTML = '<p class="ok">' + text(file.name) + ' is timestamped.</p>' + infoCard(info);
+          } else if (revealed) {
+            target.innerHTML = '<p class="ok">' + text(file.name) + ' was timestamped privately and revealed.</p>' +
+              card([['Key', text(revealed.pub_key)]]);
+          } else {
+            target.innerHTML = '<p class="error">' + text(file.name) + ' is not timestamped.</p>' +
+              card([['SHA-256', text(hash)]]);
+          }
+        })
+        .catch(function (e) {
+          showError(target, e);
+        });
+    };
+    reader.onerror = function () {
+      showError(target, 'Cannot read ' + file.name);
+    };
+    reader.readAsArrayBuffer(file);
+  }
+
+  function showBlock(height) {
+    var target = $('block-result');
+    $('block-input').value = height;
+    target.textContent = 'Loading…';
+    Promise.all([getJson(BLOCKS + '/' + height), blockTimestamps(height)])
+      .then(function (res) {
+        var info = res[0], timestamps = res[1];
+        if (!info) {
+          target.innerHTML = '<p>Block ' + text(height) + ' does not exist yet.</p>';
+          return;
+        }
+        var block = info.block || info;
+        var html = card([
+          ['Height', text(block.height)],
+          ['Transactions', text(block.tx_count)],
+          ['Previous', text(block.prev_hash)],
+          ['State hash', text(block.state_hash)]
+        ]);
+        html += '<h3>Timestamps</h3>';
+        html += timestamps.length ? timestamps.map(timestampCard).join('') : '<p>None in this block.</p>';
+        target.innerHTML = html;
+      })
+      .catch(function (e) {
+        showError(target, e);
+      });
+  }
+
+  // pages are selected by the fragment: `#recent`, `#search`, `#verify`, `#block/<height>`
+  function route() {
+    var parts = (location.hash.slice(1) || 'recent').split('/');
+    var page = $('page-' + parts[0]) ? parts[0] : 'recent';
+    Array.prototype.forEach.call(document.querySelectorAll('.page'), function (el) {
+      el.classList.toggle('active', el.id === 'page-' + page);
+    });
+    Array.prototype.forEach.call(document.querySelectorAll('nav a'), function (el) {
+      el.classList.toggle('active', el.getAttribute('href') === '#' + page);
+    });
+    if (page === 'recent') {
+      showRecent();
+    } else if (page === 'block' && parts[1]) {
+      showBlock(parts[1]);
+    }
+  }
+
+  $('search-form').addEventListener('submit', function (e) {
+    e.preventDefault();
+    search($('search-input').value);
+  });
+
+  $('block-form').addEventListener('submit', function (e) {
+    e.preventDefault();
+    location.hash = 'block/' + $('block-input').value;
+  });
+
+  var dropZone = $('drop-zone');
+  dropZone.addEventListener('dragover', function (e) {
+    e.preventDefault();
+    dropZone.classList.add('over');
+  });
+  dropZone.addEventListener('dragleave', function () {
+    dropZone.classList.remove('over');
+  });
+  dropZone.addEventListener('drop', function (e) {
+    e.preventDefault();
+    dropZone.classList.remove('over');
+    if (e.dataTransfer.files.length) {
+      verify(e.dataTransfer.files[0]);
+    }
+  });
+  $('file-input').addEventListener('change', function (e) {
+    if (e.target.files.length) {
+      verify(e.target.files[0]);
+    }
+  });
+
+  window.addEventListener('hashchange', route);
+  route();
+})();
@@ -0,0 +1,172 @@
+// Bulk export of timestamps for audits. Records are written one by one while walking the
+// `timestamps` index, or the time index for a time range, so an export of the whole chain never
+// sits in memory.
+
+use exonum::blockchain::Blockchain;
+use exonum::crypto::{Hash, PublicKey, PUBLIC_KEY_LENGTH};
+
+use iron::response::WriteBody;
+
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use super::{key_time, time_key, Timestamp, TimestampSchema};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    // comma-separated values with a header line
+    Csv,
+    // one JSON object per line
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match *self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "ndjson" => Ok(ExportFormat::Ndjson),
+            _ => Err(format!("Unknown export format `{}`", s)),
+        }
+    }
+}
+
+// Which timestamps to export: those of `keys` and of the document keys they own if any keys are
+// given, made at `from <= time < to`
+#[derive(Clone, Default)]
+pub struct ExportFilter {
+    pub keys: Vec<PublicKey>,
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+}
+
+impl ExportFilter {
+    fn matches(&self, timestamp: &Timestamp) -> bool {
+        let time = timestamp.time();
+        self.from.map_or(true, |from| time >= from) && self.to.map_or(true, |to| time < to)
+    }
+}
+
+// A line of an export
+#[derive(Serialize, Deserialize)]
+pub struct ExportRecord {
+    pub pub_key: PublicKey,
+    pub content: Hash,
+    pub time: u64,
+    pub height: u64,
+    pub tx_hash: Hash,
+}
+
+// Body of an export response, read from a snapshot taken when the body is written
+pub struct ExportStream {
+    blockchain: Blockchain,
+    format: ExportFormat,
+    filter: ExportFilter,
+}
+
+impl ExportStream {
+    pub fn new(blockchain: Blockchain, format: ExportFormat, mut filter: ExportFilter) -> Self {
+        // a key given more than once is exported once
+        let mut seen = HashSet::new();
+        filter.keys.retain(|key| seen.insert(*key));
+        ExportStream {
+            blockchain,
+            format,
+            filter,
+        }
+    }
+
+    fn write_record(&self, out: &mut Write, record: &ExportRecord) -> io::Result<()> {
+        match self.format {
+            ExportFormat::Csv => writeln!(
+                out,
+                "{},{},{},{},{}",
+                record.pub_key.to_hex(),
+                record.content.to_hex(),
+                record.time,
+                record.height,
+                record.tx_hash.to_hex()
+            ),
+            ExportFormat::Ndjson => {
+                ::serde_json::to_writer(&mut *out, record).map_err(io::Error::from)?;
+                out.write_all(b"\n")
+            }
+        }
+    }
+}
+
+impl WriteBody for ExportStream {
+    fn write_body(&mut self, res: &mut Write) -> io::Result<()> {
+        let snapshot = self.blockchain.snapshot();
+        let schema = TimestampSchema::new(&snapshot);
+        let records = schema.records();
+        let index = schema.timestamps();
+        let owned = schema.owned();
+        let owners = schema.owners();
+        let by_time = schema.by_time();
+        let keys = &self.filter.keys;
+
+        // with keys given, only they and the document keys they own are looked up; with a time
+        // range, the time index is walked from its start; otherwise all timestamps are
+        let timestamps: Box<Iterator<Item = Timestamp>> = if !keys.is_empty() {
+            let owned = &owned;
+            let owners = &owners;
+            let pub_keys = keys.iter().flat_map(move |key| {
+                // a document key of a given owner is found through the owner
+                let listed = owners.get(key).map_or(false, |owner| keys.contains(&owner));
+                let direct = if listed { None } else { Some(*key) };
+                let prefix = key.as_ref().to_vec();
+                let documents = owned
+                    .iter_from(&prefix)
+                    .take_while(move |&(ref owned_key, _)| owned_key.starts_with(&prefix))
+                    .map(|(_, pub_key)| pub_key);
+                direct.into_iter().chain(documents)
+            });
+            Box::new(pub_keys.filter_map(|key| index.get(&key)))
+        } else if self.filter.from.is_some() || self.filter.to.is_some() {
+            let start = time_key(self.filter.from.unwrap_or(0), &PublicKey::new([0; PUBLIC_KEY_LENGTH]));
+            let to = self.filter.to.unwrap_or(u64::max_value());
+            let pub_keys = by_time
+                .iter_from(&start)
+                .take_while(move |&(ref key, _)| key_time(key) < to)
+                .map(|(_, pub_key)| pub_key);
+            Box::new(pub_keys.filter_map(|key| index.get(&key)))
+        } else {
+            Box::new(index.values())
+        };
+
+        if self.format == ExportFormat::Csv {
+            res.write_all(b"pub_key,content,time,height,tx_hash\n")?;
+        }
+        for timestamp in timestamps.filter(|timestamp| self.filter.matches(timestamp)) {
+            let record = records.get(timestamp.pub_key()).unwrap();
+            let record = ExportRecord {
+                pub_key: *timestamp.pub_key(),
+                content: *timestamp.content(),
+                time: timestamp.time(),
+                height: record.height(),
+                tx_hash: *record.tx_hash(),
+            };
+            self.write_record(res, &record)?;
+        }
+        Ok(())
+    }
+}
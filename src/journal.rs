@@ -0,0 +1,172 @@
+// Journal and retry schedule of the `ts watch` daemon.
+//
+// The journal is an append-only file of JSON lines: a file with its content hash, then the
+// transaction hash once submitted, then the receipt once committed. Opening it replays the lines,
+// so a restarted daemon knows which files were sent and which transactions still wait for a
+// commit, and never stamps the same content of a file twice.
+//
+// A node may drop a transaction, e.g. when it restarts before the transaction reaches a block. An
+// entry whose content is still not timestamped `LOST_AFTER` seconds after it was sent is lost and
+// sent again; the new transaction has a new time and so a new hash, which replaces the old one in
+// the journal. Content timestamped in the meantime, by either transaction, is never sent again.
+
+use exonum::crypto::Hash;
+
+use std::cmp;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::client::{Client, ClientError, Receipt, Transport};
+
+// seconds to wait for the content of a sent transaction to be timestamped before sending it again
+pub const LOST_AFTER: u64 = 60;
+
+// A line of the journal
+#[derive(Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub path: PathBuf,
+    pub content: Hash,
+    pub tx_hash: Option<Hash>,
+    pub receipt: Option<Receipt>,
+    // UNIX time of the latest submission, 0 in journals written before it was kept
+    #[serde(default)]
+    pub sent: u64,
+}
+
+// What became of an entry waiting for a commit
+pub enum Pending {
+    // the content is timestamped
+    Committed(Receipt),
+    // the transaction may still be committed
+    Waiting,
+    // the transaction is lost and its content is to be sent again
+    Lost,
+}
+
+// The latest journal entry of every file, backed by an append-only file
+pub struct Journal {
+    file: File,
+    entries: HashMap<PathBuf, JournalEntry>,
+}
+
+impl Journal {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut entries = HashMap::new();
+        let mut torn = false;
+        if path.exists() {
+            let file = File::open(path)?;
+            for line in BufReader::new(file).lines() {
+                // a line cut short by a crash is skipped, the file is simply processed again
+                if let Ok(entry) = ::serde_json::from_str::<JournalEntry>(&line?) {
+                    entries.insert(entry.path.clone(), entry);
+                }
+            }
+            let mut file = File::open(path)?;
+            if file.seek(SeekFrom::End(0))? > 0 {
+                let mut last = [0u8];
+                file.seek(SeekFrom::End(-1))?;
+                file.read_exact(&mut last)?;
+                torn = last[0] != b'\n';
+            }
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        // the next entry must not be glued to a line cut short
+        if torn {
+            writeln!(file)?;
+        }
+        Ok(Journal { file, entries })
+    }
+
+    // appends an entry, which replaces the previous one of its file
+    pub fn record(&mut self, entry: JournalEntry) -> io::Result<()> {
+        let line = ::serde_json::to_string(&entry).unwrap();
+        writeln!(self.file, "{}", line)?;
+        self.file.sync_data()?;
+        self.entries.insert(entry.path.clone(), entry);
+        Ok(())
+    }
+
+    // whether the file with this content was already sent
+    pub fn is_known(&self, path: &Path, content: &Hash) -> bool {
+        self.entries
+            .get(path)
+            .map_or(false, |entry| entry.content == *content && entry.tx_hash.is_some())
+    }
+
+    // entries sent but not committed yet
+    pub fn pending(&self) -> Vec<JournalEntry> {
+        self.entries
+            .values()
+            .filter(|entry| entry.tx_hash.is_some() && entry.receipt.is_none())
+            .cloned()
+            .collect()
+    }
+
+    // Checks the entries waiting for a commit at UNIX time `now`. The content is looked up rather
+    // than the transaction, which may have been replaced by another one.
+    pub fn check_pending<T: Transport>(
+        &self,
+        client: &Client<T>,
+        now: u64,
+    ) -> Result<Vec<(JournalEntry, Pending)>, ClientError> {
+        let mut checked = Vec::new();
+        for entry in self.pending() {
+            let state = match client.timestamp_by_content(&entry.content)? {
+                Some(info) => Pending::Committed(Receipt::new(&info)),
+                None if now < entry.sent + LOST_AFTER => Pending::Waiting,
+                None => Pending::Lost,
+            };
+            checked.push((entry, state));
+        }
+        Ok(checked)
+    }
+
+    // files with their contents which are not sent yet, in the given order
+    pub fn unsent(&self, files: Vec<(PathBuf, Hash)>) -> Vec<(PathBuf, Hash)> {
+        files
+            .into_iter()
+            .filter(|&(ref path, ref content)| !self.is_known(path, content))
+            .collect()
+    }
+}
+
+// When to try again after failures: the delay doubles with every failure up to `max_delay` and
+// goes back to `min_delay` after a success
+pub struct Backoff {
+    min_delay: Duration,
+    max_delay: Duration,
+    delay: Duration,
+    next_attempt: Option<Instant>,
+}
+
+impl Backoff {
+    pub fn new(min_delay: Duration, max_delay: Duration) -> Self {
+        Backoff {
+            min_delay,
+            max_delay,
+            delay: min_delay,
+            next_attempt: None,
+        }
+    }
+
+    // whether the next attempt is due at `now`
+    pub fn ready(&self, now: Instant) -> bool {
+        self.next_attempt.map_or(true, |next_attempt| now >= next_attempt)
+    }
+
+    pub fn succeeded(&mut self) {
+        self.delay = self.min_delay;
+        self.next_attempt = None;
+    }
+
+    // schedules the next attempt after a failure at `now`, returns the delay before it
+    pub fn failed(&mut self, now: Instant) -> Duration {
+        let delay = self.delay;
+        self.next_attempt = Some(now + delay);
+        self.delay = cmp::min(delay * 2, self.max_delay);
+        delay
+    }
+}
@@ -0,0 +1,272 @@
+// Password-protected storage of Ed25519 signing keys.
+//
+// Every key is a JSON file `<name>.json` in the keystore directory. Its secret key is encrypted with
+// XSalsa20-Poly1305 (`secretbox`) under a key derived from the password with scrypt (`pwhash`),
+// both from `exonum_sodiumoxide`, the libsodium binding exonum itself depends on. KDF parameters are
+// stored in the file, so they may be raised later without breaking existing keys.
+
+use exonum::crypto::{self, gen_keypair, PublicKey, SecretKey, PUBLIC_KEY_LENGTH};
+use exonum::encoding::serialize::{encode_hex, FromHex};
+
+use exonum_sodiumoxide::crypto::pwhash::{self, MemLimit, OpsLimit, Salt};
+use exonum_sodiumoxide::crypto::secretbox::{self, Key, Nonce};
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+// version of the key file format
+pub const KEYFILE_VERSION: u32 = 1;
+
+const KEYFILE_EXTENSION: &str = "json";
+
+#[derive(Debug)]
+pub enum KeystoreError {
+    Io(io::Error),
+    // a key file is malformed
+    Format(String),
+    // the password does not decrypt the key
+    WrongPassword,
+    NotFound(String),
+    AlreadyExists(String),
+    // names are used as file names, so only letters, digits, `-` and `_` are allowed
+    InvalidName(String),
+}
+
+impl fmt::Display for KeystoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            KeystoreError::Io(ref e) => write!(f, "{}", e),
+            KeystoreError::Format(ref e) => write!(f, "Invalid key file: {}", e),
+            KeystoreError::WrongPassword => write!(f, "Wrong password"),
+            KeystoreError::NotFound(ref name) => write!(f, "Key `{}` not found", name),
+            KeystoreError::AlreadyExists(ref name) => write!(f, "Key `{}` already exists", name),
+            KeystoreError::InvalidName(ref name) => write!(f, "Invalid key name `{}`", name),
+        }
+    }
+}
+
+impl Error for KeystoreError {
+    fn description(&self) -> &str {
+        match *self {
+            KeystoreError::Io(_) => "I/O error",
+            KeystoreError::Format(_) => "invalid key file",
+            KeystoreError::WrongPassword => "wrong password",
+            KeystoreError::NotFound(_) => "key not found",
+            KeystoreError::AlreadyExists(_) => "key already exists",
+            KeystoreError::InvalidName(_) => "invalid key name",
+        }
+    }
+}
+
+impl From<io::Error> for KeystoreError {
+    fn from(e: io::Error) -> Self {
+        KeystoreError::Io(e)
+    }
+}
+
+// Parameters of the password-based key derivation
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub salt: String,
+    pub opslimit: usize,
+    pub memlimit: usize,
+}
+
+// Content of a key file; byte strings are hex-encoded
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EncryptedKey {
+    pub version: u32,
+    pub public_key: PublicKey,
+    pub kdf: KdfParams,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+impl EncryptedKey {
+    pub fn encrypt(secret_key: &SecretKey, password: &str) -> Result<Self, KeystoreError> {
+        crypto::init();
+
+        let salt = pwhash::gen_salt();
+        let kdf = KdfParams {
+            salt: encode_hex(&salt.0[..]),
+            opslimit: pwhash::OPSLIMIT_INTERACTIVE.0,
+            memlimit: pwhash::MEMLIMIT_INTERACTIVE.0,
+        };
+        let key = derive_key(password, &salt, &kdf)?;
+        let nonce = secretbox::gen_nonce();
+        let ciphertext = secretbox::seal(&secret_bytes(secret_key), &nonce, &key);
+
+        Ok(EncryptedKey {
+            version: KEYFILE_VERSION,
+            public_key: public_key_of(secret_key),
+            kdf,
+            nonce: encode_hex(&nonce.0[..]),
+            ciphertext: encode_hex(&ciphertext),
+        })
+    }
+
+    pub fn decrypt(&self, password: &str) -> Result<SecretKey, KeystoreError> {
+        crypto::init();
+
+        if self.version != KEYFILE_VERSION {
+            return Err(KeystoreError::Format(format!("unsupported version {}", self.version)));
+        }
+        let salt = Salt::from_slice(&decode(&self.kdf.salt)?)
+            .ok_or_else(|| KeystoreError::Format("invalid salt".into()))?;
+        let nonce = Nonce::from_slice(&decode(&self.nonce)?)
+            .ok_or_else(|| KeystoreError::Format("invalid nonce".into()))?;
+        let key = derive_key(password, &salt, &self.kdf)?;
+
+        let plain = secretbox::open(&decode(&self.ciphertext)?, &nonce, &key)
+            .map_err(|_| KeystoreError::WrongPassword)?;
+        let secret_key = SecretKey::from_slice(&plain)
+            .ok_or_else(|| KeystoreError::Format("invalid secret key".into()))?;
+
+        // the public key is not encrypted, so it is checked against the secret one
+        if public_key_of(&secret_key) != self.public_key {
+            return Err(KeystoreError::Format("public key does not match".into()));
+        }
+        Ok(secret_key)
+    }
+}
+
+// A key stored in a keystore
+pub struct KeyEntry {
+    pub name: String,
+    // the public key, or why the key file cannot be read
+    pub public_key: Result<PublicKey, KeystoreError>,
+}
+
+// Directory of password-protected keys
+pub struct Keystore {
+    dir: PathBuf,
+}
+
+impl Keystore {
+    // opens a keystore, creating its directory if needed
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, KeystoreError> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Keystore { dir: dir.as_ref().to_owned() })
+    }
+
+    // generates a new key
+    pub fn generate(&self, name: &str, password: &str) -> Result<PublicKey, KeystoreError> {
+        let (public_key, secret_key) = gen_keypair();
+        self.add(name, &secret_key, password)?;
+        Ok(public_key)
+    }
+
+    // stores an existing secret key
+    pub fn add(&self, name: &str, secret_key: &SecretKey, password: &str) -> Result<PublicKey, KeystoreError> {
+        let key = EncryptedKey::encrypt(secret_key, password)?;
+        self.import(name, &key)?;
+        Ok(key.public_key)
+    }
+
+    // stores a key which is already encrypted, e.g. exported from another keystore
+    pub fn import(&self, name: &str, key: &EncryptedKey) -> Result<(), KeystoreError> {
+        let path = self.path(name)?;
+        let file = create_key_file(&path).map_err(|e| match e.kind() {
+                io::ErrorKind::AlreadyExists => KeystoreError::AlreadyExists(name.to_owned()),
+                _ => KeystoreError::Io(e),
+            })?;
+        ::serde_json::to_writer_pretty(file, key).map_err(|e| KeystoreError::Format(e.to_string()))
+    }
+
+    // the encrypted key as it is stored
+    pub fn export(&self, name: &str) -> Result<EncryptedKey, KeystoreError> {
+        let path = self.path(name)?;
+        let file = File::open(&path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => KeystoreError::NotFound(name.to_owned()),
+            _ => KeystoreError::Io(e),
+        })?;
+        ::serde_json::from_reader(file).map_err(|e| KeystoreError::Format(e.to_string()))
+    }
+
+    // decrypts a key for signing
+    pub fn load(&self, name: &str, password: &str) -> Result<(PublicKey, SecretKey), KeystoreError> {
+        let key = self.export(name)?;
+        let secret_key = key.decrypt(password)?;
+        Ok((key.public_key, secret_key))
+    }
+
+    // all keys, a malformed file is listed with its error instead of failing the others
+    pub fn list(&self) -> Result<Vec<KeyEntry>, KeystoreError> {
+        let mut keys = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(true, |ext| ext != KEYFILE_EXTENSION) {
+                continue;
+            }
+            let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(name) => name.to_owned(),
+                None => continue,
+            };
+            let public_key = self.export(&name).map(|key| key.public_key);
+            keys.push(KeyEntry { name, public_key });
+        }
+        keys.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(keys)
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf, KeystoreError> {
+        let valid = !name.is_empty() &&
+            name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(KeystoreError::InvalidName(name.to_owned()));
+        }
+        Ok(self.dir.join(format!("{}.{}", name, KEYFILE_EXTENSION)))
+    }
+}
+
+// creates a file for a key, readable by its owner only; `create_new` never overwrites an existing key
+pub fn create_key_file(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+// the bytes of a secret key, which are only exposed in hex
+fn secret_bytes(secret_key: &SecretKey) -> Vec<u8> {
+    Vec::<u8>::from_hex(secret_key.to_hex()).unwrap()
+}
+
+// an Ed25519 secret key holds its public key in the last bytes
+fn public_key_of(secret_key: &SecretKey) -> PublicKey {
+    let bytes = secret_bytes(secret_key);
+    PublicKey::from_slice(&bytes[bytes.len() - PUBLIC_KEY_LENGTH..]).unwrap()
+}
+
+// the parameters of a key file are checked first, so a forged file cannot ask for unbounded work
+fn derive_key(password: &str, salt: &Salt, kdf: &KdfParams) -> Result<Key, KeystoreError> {
+    if kdf.opslimit < pwhash::OPSLIMIT_INTERACTIVE.0 || kdf.opslimit > pwhash::OPSLIMIT_SENSITIVE.0 {
+        return Err(KeystoreError::Format(format!("opslimit {} out of range", kdf.opslimit)));
+    }
+    if kdf.memlimit < pwhash::MEMLIMIT_INTERACTIVE.0 || kdf.memlimit > pwhash::MEMLIMIT_SENSITIVE.0 {
+        return Err(KeystoreError::Format(format!("memlimit {} out of range", kdf.memlimit)));
+    }
+    let mut key = Key([0; secretbox::KEYBYTES]);
+    {
+        let Key(ref mut bytes) = key;
+        pwhash::derive_key(
+            bytes,
+            password.as_bytes(),
+            salt,
+            OpsLimit(kdf.opslimit),
+            MemLimit(kdf.memlimit),
+        ).map_err(|_| KeystoreError::Format("key derivation failed".into()))?;
+    }
+    Ok(key)
+}
+
+fn decode(hex: &str) -> Result<Vec<u8>, KeystoreError> {
+    Vec::<u8>::from_hex(hex).map_err(|e| KeystoreError::Format(e.to_string()))
+}
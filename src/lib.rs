@@ -1,40 +1,127 @@
+#![recursion_limit = "256"]
+// Trait objects are written without `dyn` throughout the crate, and exonum's macros call the
+// deprecated `Error::description` and check a `cargo-clippy` feature newer compilers do not know
+// about. The clippy lints below suggest std methods and idioms newer than the rest of the code.
+#![allow(bare_trait_objects, deprecated, unexpected_cfgs)]
+#![allow(clippy::io_other_error, clippy::legacy_numeric_constants, clippy::manual_inspect,
+         clippy::manual_is_multiple_of, clippy::needless_borrowed_reference,
+         clippy::option_as_ref_deref, clippy::unnecessary_map_or)]
+
 extern crate bodyparser;
 
 #[macro_use]
 extern crate exonum;
+extern crate failure;
 
+extern crate hyper;
 extern crate iron;
+#[macro_use]
+extern crate log;
 
 extern crate router;
 
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
 extern crate serde_json;
+extern crate serde_cbor;
+
+extern crate exonum_sodiumoxide;
 
 extern crate time;
 
 use exonum::api::{Api, ApiError};
-use exonum::blockchain::{ApiContext, Blockchain, Service, Transaction,
-  TransactionSet, ExecutionResult};
-use exonum::crypto::{Hash, PublicKey};
+use exonum::blockchain::{ApiContext, Blockchain, Schema, Service, ServiceContext, Transaction,
+  TransactionSet, ExecutionError, ExecutionResult};
+use exonum::crypto::{self, CryptoHash, Hash, HashStream, PublicKey, SecretKey, Signature, PUBLIC_KEY_LENGTH,
+  SIGNATURE_LENGTH};
 use exonum::encoding;
-use exonum::encoding::serialize::FromHex;
+use exonum::encoding::serialize::{encode_hex, FromHex};
 use exonum::explorer::BlockchainExplorer;
+use exonum::helpers::fabric::{self, CommandExtension, Context};
 use exonum::messages::{Message, RawTransaction};
 use exonum::node::{ApiSender, TransactionSend};
-use exonum::storage::{Fork, ProofMapIndex, Snapshot};
+use exonum::helpers::Height;
+use exonum::storage::{Entry, Fork, ListIndex, MapIndex, ProofListIndex, ProofMapIndex, Snapshot};
 
 use iron::prelude::*;
 use iron::Handler;
 use iron::status::Status;
-use iron::headers::ContentType;
+use iron::headers::{ContentLength, ContentType};
 use iron::modifiers::Header;
+use iron::response::WriteBody;
 
 use router::Router;
 
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+pub mod admin;
+pub mod anchoring;
+pub mod client;
+mod events;
+mod explorer;
+pub mod export;
+pub mod journal;
+pub mod keystore;
+pub mod metrics;
+pub mod negotiation;
+pub mod openapi;
+pub mod snapshot;
+mod v2;
+
+use admin::{AdminApi, PendingTransactions};
+use anchoring::{AnchorProof, AnchorSink, Anchoring, AnchoringArgs};
+use events::EventStream;
+use export::{ExportFilter, ExportFormat, ExportStream};
+use metrics::Metrics;
+use negotiation::Encoding;
+use snapshot::{ServiceSnapshot, SnapshotSchedule};
+
+pub use events::{CommitNotifier, DEFAULT_MAX_EVENT_STREAMS};
+pub use v2::{Envelope, ErrorBody, Meta, Pagination};
+
 const SERVICE_ID: u16 = 13;
 
+// default upper bound for a body uploaded to `/v1/hash`
+pub const DEFAULT_MAX_HASH_SIZE: u64 = 64 * 1024 * 1024;
+
+// upper bound for a CBOR or binary body of `/v1/submit`
+pub const MAX_SUBMIT_SIZE: u64 = 64 * 1024;
+
+// the largest number of transactions accepted by `submit_batch` of v1 and v2 in one request
+pub const MAX_BATCH_SIZE: usize = 1000;
+
+// upper bound for a body of `submit_batch`, enough for `MAX_BATCH_SIZE` transactions in JSON
+pub const MAX_BATCH_BODY_SIZE: u64 = 1024 * 1024;
+
+// number of timestamps in a page of a range query unless `limit` is given, and its upper bound
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+// bounds of a salt length in TxReveal
+pub const MIN_SALT_LENGTH: usize = 16;
+pub const MAX_SALT_LENGTH: usize = 64;
+
+// width of the buckets of `/v1/stats?bucket=day`, days start at midnight UTC
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// How far the time of a timestamp may be from the clock of the node whose API accepts it. This is
+// an admission rule of the API, not of consensus: transactions broadcast by peers and those in
+// blocks a node catches up on are executed whatever their time, since a clock check in `verify`
+// or `execute` would make validators with different clocks, or a node syncing old blocks, disagree
+// on them. A time is therefore the author's claim vouched for by the node it was submitted to.
+pub const MAX_TIME_DRIFT: u64 = 5 * 60;
+
+// uploaded bodies are hashed in chunks of this size, so they are never buffered completely
+const HASH_CHUNK_SIZE: usize = 64 * 1024;
+
 // base data types.
 // A timestamp contains a public key, a hash of a document/content, time (UNIX time)
 encoding_struct! {
@@ -45,19 +132,154 @@ encoding_struct! {
     }
 }
 
+// Where a timestamp was committed: the transaction which created it and the height of its block
+encoding_struct! {
+    struct TimestampRecord {
+        tx_hash: &Hash,
+        height: u64,
+    }
+}
+
+// A disclosed commitment: the document behind a timestamp's content and where it was revealed
+encoding_struct! {
+    struct Reveal {
+        pub_key: &PublicKey,
+        document: &Hash,
+        salt: &[u8],
+        tx_hash: &Hash,
+        height: u64,
+    }
+}
+
+// A gateway allowed to submit timestamps on behalf of their authors, up to `quota` transactions
+encoding_struct! {
+    struct Relayer {
+        pub_key: &PublicKey,
+        quota: u64,
+        used: u64,
+    }
+}
+
+// A block hash stored in an external system; `reference` locates it there
+encoding_struct! {
+    struct Anchor {
+        height: u64,
+        block_hash: &Hash,
+        reference: &str,
+        tx_hash: &Hash,
+    }
+}
+
 // Any blockchain operation should be expressed as a transaction. In this case it is described with
 // the service's ID, a public key and data
 transactions! {
-    TimestampServiceTransactions {
+    pub TimestampServiceTransactions {
         const SERVICE_ID = SERVICE_ID;
 
+        // `time` is the UNIX time of the timestamp, chosen by its author. Nodes accept it only
+        // within `MAX_TIME_DRIFT` of their clocks, but execution never reads a clock, so every
+        // validator stores the same time. The field changed the wire format: TxTimestamp,
+        // TxRelayed and TxOwned signed without it neither parse nor verify and have to be signed
+        // again, `test.json` shows the current layout.
         struct TxTimestamp {
             from: &PublicKey,
             content: &Hash,
+            time: u64,
+        }
+
+        // Discloses the document of a timestamp made in the commitment mode, where the content
+        // is `commitment(salt, document)` and keeps the document confidential until now
+        struct TxReveal {
+            from: &PublicKey,
+            salt: &[u8],
+            document: &Hash,
+        }
+
+        // A timestamp forwarded by a registered relayer. `author_signature` is the author's
+        // signature of TxTimestamp { from: author, content, time }, i.e. of the bytes `/v1/prepare`
+        // returns; the relayer signs the whole envelope and pays for it with its quota.
+        struct TxRelayed {
+            relayer: &PublicKey,
+            author: &PublicKey,
+            content: &Hash,
+            time: u64,
+            author_signature: &Signature,
+        }
+
+        // A reference to an external anchor of the block at `height`, sent by a validator with its
+        // service key, see `anchoring`
+        struct TxAnchor {
+            from: &PublicKey,
+            height: u64,
+            block_hash: &Hash,
+            reference: &str,
+        }
+
+        // Overrides the quota of a relayer, registering it if needed. Sent by a validator with its
+        // service key through the private API.
+        struct TxSetQuota {
+            from: &PublicKey,
+            relayer: &PublicKey,
+            quota: u64,
+        }
+
+        // A timestamp of a document key derived by `client::document_keypair`, sent by the owner of
+        // the key. `author_signature` is the signature of TxTimestamp { from: author, content, time }
+        // by the document key; the owner signs the envelope, so the chain links the document key to
+        // the owner's key, which is counted in statistics and found by exports.
+        struct TxOwned {
+            owner: &PublicKey,
+            author: &PublicKey,
+            content: &Hash,
+            time: u64,
+            author_signature: &Signature,
+        }
+    }
+}
+
+// Reasons a transaction fails on execution
+#[repr(u8)]
+pub enum ErrorCode {
+    TimestampNotFound = 0,
+    AlreadyRevealed = 1,
+    CommitmentMismatch = 2,
+    UnknownRelayer = 3,
+    QuotaExceeded = 4,
+    NotValidator = 5,
+    UnknownBlock = 6,
+    AlreadyAnchored = 7,
+}
+
+impl ErrorCode {
+    // name of an error code in metrics
+    pub fn name(code: u8) -> &'static str {
+        match code {
+            0 => "timestamp_not_found",
+            1 => "already_revealed",
+            2 => "commitment_mismatch",
+            3 => "unknown_relayer",
+            4 => "quota_exceeded",
+            5 => "not_validator",
+            6 => "unknown_block",
+            7 => "already_anchored",
+            _ => "unknown",
         }
     }
 }
 
+// Current UNIX time for the `time` of a new TxTimestamp
+pub fn unix_time() -> u64 {
+    time::get_time().sec as u64
+}
+
+// Content of a timestamp in the commitment mode: H(salt || document hash). A random salt makes
+// the document impossible to guess from the content.
+pub fn commitment(salt: &[u8], document: &Hash) -> Hash {
+    let mut data = salt.to_vec();
+    data.extend_from_slice(document.as_ref());
+    crypto::hash(&data)
+}
+
 // Each transaction implements two basic operations:
 // verify - logical check BEFORE commiting to blockchain. In this case we verify senders ID
 // execute - storing a new timestamp withn blockchain
@@ -67,18 +289,173 @@ impl Transaction for TxTimestamp {
     }
 
     fn execute(&self, view: &mut Fork) -> ExecutionResult {
+        put_timestamp(view, self.from(), self.content(), self.time(), &self.hash(), self.from());
+        Ok(())
+    }
+}
+
+// Stores a timestamp of `pub_key` created by the transaction `tx_hash`, unless the key already has
+// one. `submitter` is the key which sent the transaction and is counted in statistics.
+fn put_timestamp(
+    view: &mut Fork,
+    pub_key: &PublicKey,
+    content: &Hash,
+    time: u64,
+    tx_hash: &Hash,
+    submitter: &PublicKey,
+) {
+    // the block being executed goes right after the last committed one
+    let height = Schema::new(&*view).block_hashes_by_height().len();
+    let mut schema = TimestampSchema::new(view);
+
+    if schema.timestamp(pub_key).is_none() {
+        let timestamp = Timestamp::new(pub_key, content, time);
+        let record = TimestampRecord::new(tx_hash, height);
+
+        schema.add_timestamp(timestamp, record, submitter);
+    }
+}
+
+impl TxRelayed {
+    // wraps a transaction signed by its author into an envelope of a relayer
+    pub fn wrap(tx: &TxTimestamp, relayer: &PublicKey, secret_key: &SecretKey) -> Self {
+        TxRelayed::new(relayer, tx.from(), tx.content(), tx.time(), tx.raw().signature(), secret_key)
+    }
+
+    // the author's transaction inside the envelope
+    pub fn author_tx(&self) -> TxTimestamp {
+        TxTimestamp::new_with_signature(self.author(), self.content(), self.time(), self.author_signature())
+    }
+}
+
+impl Transaction for TxRelayed {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.relayer()) && self.author_tx().verify()
+    }
+
+    fn execute(&self, view: &mut Fork) -> ExecutionResult {
+        let relayer = {
+            let schema = TimestampSchema::new(&mut *view);
+            schema.relayer(self.relayer()).ok_or_else(|| {
+                ExecutionError::with_description(ErrorCode::UnknownRelayer as u8, "Unknown relayer".to_owned())
+            })?
+        };
+        if relayer.used() >= relayer.quota() {
+            Err(ExecutionError::with_description(ErrorCode::QuotaExceeded as u8, "Relayer quota exceeded".to_owned()))?
+        }
+
+        // the author owns the timestamp, the relayer only pays for it
+        put_timestamp(view, self.author(), self.content(), self.time(), &self.hash(), self.relayer());
+
+        let relayer = Relayer::new(relayer.pub_key(), relayer.quota(), relayer.used() + 1);
+        TimestampSchema::new(view).put_relayer(relayer);
+        Ok(())
+    }
+}
+
+impl TxOwned {
+    // wraps a transaction signed by a document key into an envelope of its owner
+    pub fn wrap(tx: &TxTimestamp, owner: &PublicKey, secret_key: &SecretKey) -> Self {
+        TxOwned::new(owner, tx.from(), tx.content(), tx.time(), tx.raw().signature(), secret_key)
+    }
+
+    // the transaction of the document key inside the envelope
+    pub fn author_tx(&self) -> TxTimestamp {
+        TxTimestamp::new_with_signature(self.author(), self.content(), self.time(), self.author_signature())
+    }
+}
+
+impl Transaction for TxOwned {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.owner()) && self.author_tx().verify()
+    }
+
+    fn execute(&self, view: &mut Fork) -> ExecutionResult {
+        let existing = TimestampSchema::new(&*view).timestamp(self.author()).is_some();
+        put_timestamp(view, self.author(), self.content(), self.time(), &self.hash(), self.owner());
+        // a key timestamped before keeps the owner of its first timestamp
+        if !existing {
+            TimestampSchema::new(view).add_owner(self.author(), self.owner());
+        }
+        Ok(())
+    }
+}
+
+impl Transaction for TxReveal {
+    fn verify(&self) -> bool {
+        let salt_len = self.salt().len();
+        (MIN_SALT_LENGTH..=MAX_SALT_LENGTH).contains(&salt_len) && self.verify_signature(self.from())
+    }
+
+    fn execute(&self, view: &mut Fork) -> ExecutionResult {
+        let height = Schema::new(&*view).block_hashes_by_height().len();
         let mut schema = TimestampSchema::new(view);
 
-        if schema.timestamp(self.from()).is_none() {
-            let now = time::precise_time_s() as u64;
-            let timestamp = Timestamp::new(self.from(), self.content(), now);
+        let timestamp = schema.timestamp(self.from()).ok_or_else(|| {
+            ExecutionError::with_description(ErrorCode::TimestampNotFound as u8, "Timestamp not found".to_owned())
+        })?;
+        if schema.reveal(self.from()).is_some() {
+            Err(ExecutionError::with_description(ErrorCode::AlreadyRevealed as u8, "Already revealed".to_owned()))?
+        }
+        if commitment(self.salt(), self.document()) != *timestamp.content() {
+            Err(ExecutionError::with_description(
+                ErrorCode::CommitmentMismatch as u8,
+                "Salt and document do not match the content".to_owned(),
+            ))?
+        }
+
+        let reveal = Reveal::new(self.from(), self.document(), self.salt(), &self.hash(), height);
+        schema.add_reveal(reveal);
+        Ok(())
+    }
+}
+
+impl Transaction for TxAnchor {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.from())
+    }
+
+    fn execute(&self, view: &mut Fork) -> ExecutionResult {
+        check_validator(view, self.from())?;
+        if Schema::new(&*view).block_hash_by_height(Height(self.height())).as_ref() != Some(self.block_hash()) {
+            Err(ExecutionError::with_description(ErrorCode::UnknownBlock as u8, "Unknown block".to_owned()))?
+        }
 
-            schema.timestamps_mut().put(self.from(), timestamp);
+        let mut schema = TimestampSchema::new(view);
+        // anchors are kept in the order of heights
+        if schema.anchors().last().map_or(false, |last| last.height() >= self.height()) {
+            Err(ExecutionError::with_description(ErrorCode::AlreadyAnchored as u8, "Already anchored".to_owned()))?
         }
+        let anchor = Anchor::new(self.height(), self.block_hash(), self.reference(), &self.hash());
+        schema.anchors_mut().push(anchor);
+        Ok(())
+    }
+}
+
+impl Transaction for TxSetQuota {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.from())
+    }
+
+    fn execute(&self, view: &mut Fork) -> ExecutionResult {
+        check_validator(view, self.from())?;
+
+        let mut schema = TimestampSchema::new(view);
+        let used = schema.relayer(self.relayer()).map_or(0, |relayer| relayer.used());
+        schema.put_relayer(Relayer::new(self.relayer(), self.quota(), used));
         Ok(())
     }
 }
 
+// administrative transactions are accepted only from service keys of validators
+fn check_validator(view: &Fork, pub_key: &PublicKey) -> ExecutionResult {
+    let validators = Schema::new(view).actual_configuration().validator_keys;
+    if !validators.iter().any(|keys| keys.service_key == *pub_key) {
+        Err(ExecutionError::with_description(ErrorCode::NotValidator as u8, "Not a validator".to_owned()))?
+    }
+    Ok(())
+}
+
 // To interact with blockchain we should define two views: for reading and writing data
 // interface
 pub struct TimestampSchema<T> {
@@ -86,191 +463,1609 @@ pub struct TimestampSchema<T> {
 }
 
 // this one allows as to modify blockchain data
-impl<'a> TimestampSchema<&'a mut Fork> {
+impl TimestampSchema<&mut Fork> {
     pub fn timestamps_mut(&mut self) -> ProofMapIndex<&mut Fork, PublicKey, Timestamp> {
         ProofMapIndex::new("timestamp.timestamps", &mut self.view)
     }
-}
 
-// this one is read-only and provides access to a blockchain snapshot
-impl<T: AsRef<Snapshot>> TimestampSchema<T> {
-    pub fn new(view: T) -> Self {
-        TimestampSchema { view }
+    pub fn records_mut(&mut self) -> ProofMapIndex<&mut Fork, PublicKey, TimestampRecord> {
+        ProofMapIndex::new("timestamp.records", &mut self.view)
     }
 
-    pub fn timestamps(&self) -> ProofMapIndex<&Snapshot, PublicKey, Timestamp> {
-        ProofMapIndex::new("timestamp.timestamps", self.view.as_ref())
+    pub fn submitters_mut(&mut self) -> ProofMapIndex<&mut Fork, PublicKey, PublicKey> {
+        ProofMapIndex::new("timestamp.submitters", &mut self.view)
     }
 
-    pub fn timestamp(&self, pub_key: &PublicKey) -> Option<Timestamp> {
-        self.timestamps().get(pub_key)
+    pub fn history_mut(&mut self) -> ListIndex<&mut Fork, PublicKey> {
+        ListIndex::new("timestamp.history", &mut self.view)
     }
 
-    pub fn state_hash(&self) -> Vec<Hash> {
-        vec![self.timestamps().root_hash()]
+    // stores a new timestamp along with its record, appends it to the commit history and counts it
+    // in statistics of `submitter`
+    pub fn add_timestamp(&mut self, timestamp: Timestamp, record: TimestampRecord, submitter: &PublicKey) {
+        let pub_key = *timestamp.pub_key();
+        let timestamp_time = timestamp.time();
+        let content = *timestamp.content();
+        self.timestamps_mut().put(&pub_key, timestamp);
+        self.records_mut().put(&pub_key, record);
+        self.submitters_mut().put(&pub_key, *submitter);
+        self.history_mut().push(pub_key);
+        self.by_time_mut().put(&time_key(timestamp_time, &pub_key), pub_key);
+        if !self.by_content().contains(&content) {
+            self.by_content_mut().put(&content, pub_key);
+            self.count("by_content");
+        }
+        self.count_timestamp(timestamp_time / SECONDS_PER_DAY, submitter);
     }
-}
 
-// basic type to get REST response
-#[derive(Serialize, Deserialize)]
-pub struct TimestampResponse {
-    pub tx_hash: Hash,
-}
+    // updates aggregates read by `/v1/stats`, so it never scans timestamps
+    fn count_timestamp(&mut self, day: u64, submitter: &PublicKey) {
+        let daily = self.daily_counts().get(&day).unwrap_or(0);
+        if daily == 0 {
+            self.count("daily_counts");
+        }
+        self.daily_counts_mut().put(&day, daily + 1);
 
-// Interface for system's backend
-#[derive(Clone)]
-struct TimestampApi {
-    channel: ApiSender,
-    blockchain: Blockchain,
-}
+        let day_key = time_key(day, submitter);
+        let submitter_daily = self.daily_submitter_counts().get(&day_key).unwrap_or(0);
+        if submitter_daily == 0 {
+            let submitters = self.daily_submitters().get(&day).unwrap_or(0);
+            self.daily_submitters_mut().put(&day, submitters + 1);
+        }
+        self.daily_submitter_counts_mut().put(&day_key, submitter_daily + 1);
 
-// Registering handlers for REST API. We define 4 endpoints
-impl Api for TimestampApi {
-    fn wire(&self, router: &mut Router) {
-        self.clone().set_timestamp(router);
-        self.clone().set_timestamps(router);
-        self.clone().set_submit(router);
-        self.clone().set_block_stats(router);
+        let total = self.submitter_counts().get(submitter).unwrap_or(0);
+        if total == 0 {
+            let submitters = self.total_submitters().get().unwrap_or(0);
+            self.total_submitters_mut().set(submitters + 1);
+        }
+        self.submitter_counts_mut().put(submitter, total + 1);
     }
-}
 
-impl TimestampApi {
-    // helpers to register all endpoints
-    fn set_timestamp(self, router: &mut Router) {
-        let timestamp = move |req: &mut Request| self.timestamp(req);
-        router.get("/v1/timestamp/:pub_key", timestamp, "timestamp");
+    // stores a reveal of a timestamp. A document revealed by several timestamps is found by the
+    // earliest reveal, the same as a content is found by its earliest timestamp.
+    pub fn add_reveal(&mut self, reveal: Reveal) {
+        let document = *reveal.document();
+        let earlier = self.revealed_documents()
+            .get(&document)
+            .and_then(|pub_key| self.reveal(&pub_key))
+            .map_or(false, |existing| existing.height() <= reveal.height());
+        if !self.revealed_documents().contains(&document) {
+            self.count("revealed_documents");
+        }
+        if !earlier {
+            self.revealed_documents_mut().put(&document, *reveal.pub_key());
+        }
+        let pub_key = *reveal.pub_key();
+        if !self.reveals().contains(&pub_key) {
+            self.count("reveals");
+        }
+        self.reveals_mut().put(&pub_key, reveal);
     }
 
-    fn set_timestamps(self, router: &mut Router) {
-        let timestamps = move |req: &mut Request| self.timestamps(req);
-        router.get("/v1/timestamps", timestamps, "timestamps");
+    // links a document key to the key of its owner, see TxOwned
+    pub fn add_owner(&mut self, pub_key: &PublicKey, owner: &PublicKey) {
+        if !self.owners().contains(pub_key) {
+            self.count("owners");
+        }
+        self.owners_mut().put(pub_key, *owner);
+        self.owned_mut().put(&owned_key(owner, pub_key), *pub_key);
     }
 
-    fn set_submit(self, router: &mut Router) {
-        let submit = move |req: &mut Request| self.submit(req);
-        router.post("/v1/submit", submit, "submit");
+    // adds or updates a relayer
+    pub fn put_relayer(&mut self, relayer: Relayer) {
+        let pub_key = *relayer.pub_key();
+        if !self.relayers().contains(&pub_key) {
+            self.count("relayers");
+        }
+        self.relayers_mut().put(&pub_key, relayer);
     }
 
-    fn set_block_stats(self, router: &mut Router) {
-        let stats = move |req: &mut Request| self.block_stats(req);
-        router.get("/v1/block_stats/:id", stats, "block_stats");
+    // counts a new entry of the index `name`, read back by `index_size`
+    fn count(&mut self, name: &str) {
+        let size = self.index_size(name);
+        let mut entry: Entry<&mut Fork, u64> =
+            Entry::new(format!("timestamp.size.{}", name), &mut self.view);
+        entry.set(size + 1);
     }
 
-    // Endpoint for creating a new timestamp.
-    // Input: a transaction in JSON format
-    // Effect: serializes the input into TxTransaction, stores it into a blockchain
-    // Return value: transaction's hash
-    fn submit(&self, req: &mut Request) -> IronResult<Response> {
-        match req.get::<bodyparser::Struct<TimestampServiceTransactions>>() {
-            Ok(Some(transaction)) => {
-                let transaction: Box<Transaction> = transaction.into();
-                let tx_hash = transaction.hash();
-                self.channel.send(transaction).map_err(ApiError::from)?;
-                let json = TimestampResponse { tx_hash };
-                self.ok_response(&serde_json::to_value(&json).unwrap())
+    // writes the content of a snapshot into an empty schema, rebuilding all derived indexes
+    pub fn restore(&mut self, snapshot: &ServiceSnapshot) {
+        for entry in &snapshot.timestamps {
+            self.add_timestamp(entry.timestamp.clone(), entry.record.clone(), &entry.submitter);
+            if let Some(ref owner) = entry.owner {
+                self.add_owner(entry.timestamp.pub_key(), owner);
             }
-            Ok(None) => Err(ApiError::BadRequest("Empty request".into()))?,
-            Err(e) => Err(ApiError::BadRequest(e.to_string()))?,
         }
+        for reveal in &snapshot.reveals {
+            self.add_reveal(reveal.clone());
+        }
+        for relayer in &snapshot.relayers {
+            self.put_relayer(relayer.clone());
+        }
+        self.anchors_mut().extend(snapshot.anchors.iter().cloned());
     }
 
-    // Endpoint for searching for specific transaction.
-    // Input: a public key
-    // Effect: Finds a transaction by its public key
-    // Return value: a transaction data in JSON
-    fn timestamp(&self, req: &mut Request) -> IronResult<Response> {
-        let path = req.url.path();
-        let public_key = PublicKey::from_hex(path.last().unwrap()).map_err(
-            |e| {
-                IronError::new(e, (
-                        Status::BadRequest,
-                        Header(ContentType::json()),
-                        "\"Invalid request param: `pub_key`\"",
-                    )
-                )
-            }
-        )?;
+    pub fn daily_counts_mut(&mut self) -> MapIndex<&mut Fork, u64, u64> {
+        MapIndex::new("timestamp.daily_counts", &mut self.view)
+    }
 
-        let timestamp = {
-            let snapshot = self.blockchain.snapshot();
-            let schema = TimestampSchema::new(snapshot);
-            schema.timestamp(&public_key)
-        };
+    pub fn daily_submitters_mut(&mut self) -> MapIndex<&mut Fork, u64, u64> {
+        MapIndex::new("timestamp.daily_submitters", &mut self.view)
+    }
 
-        if let Some(timestamp) = timestamp {
-            self.ok_response(&serde_json::to_value(timestamp).unwrap())
-        } else {
-            self.not_found_response(&serde_json::to_value("Not found").unwrap())
-        }
+    pub fn daily_submitter_counts_mut(&mut self) -> MapIndex<&mut Fork, Vec<u8>, u64> {
+        MapIndex::new("timestamp.daily_submitter_counts", &mut self.view)
     }
 
-    // Endpoint for listing all available transactions.
-    // Input: N/A
-    // Effect: gets a blockchain's snapshot and gathers all transactions
-    // Return value: list of transactions
-    fn timestamps(&self, _: &mut Request) -> IronResult<Response> {
-        let snapshot = self.blockchain.snapshot();
-        let schema = TimestampSchema::new(snapshot);
-        let idx = schema.timestamps();
-        let timestamps: Vec<Timestamp> = idx.values().collect();
+    pub fn submitter_counts_mut(&mut self) -> MapIndex<&mut Fork, PublicKey, u64> {
+        MapIndex::new("timestamp.submitter_counts", &mut self.view)
+    }
 
-        self.ok_response(&serde_json::to_value(&timestamps).unwrap())
+    pub fn total_submitters_mut(&mut self) -> Entry<&mut Fork, u64> {
+        Entry::new("timestamp.total_submitters", &mut self.view)
     }
 
-    // Endpoint for searching for a specific trasactions block.
-    // Input: Block ID
-    // Effect: Creates a blockchain explorer, which is used to find a block
-    // Return value: list of transactions within a block
-    fn block_stats(&self, req: &mut Request) -> IronResult<Response> {
-        let path = req.url.path();
-        let block_id = exonum::helpers::Height(path.last().unwrap().parse::<u64>().unwrap());
+    pub fn by_content_mut(&mut self) -> MapIndex<&mut Fork, Hash, PublicKey> {
+        MapIndex::new("timestamp.by_content", &mut self.view)
+    }
 
-        let blockchain_explorer = BlockchainExplorer::new(&self.blockchain);
+    pub fn reveals_mut(&mut self) -> ProofMapIndex<&mut Fork, PublicKey, Reveal> {
+        ProofMapIndex::new("timestamp.reveals", &mut self.view)
+    }
 
-        match blockchain_explorer.block_info(block_id) {
-            Some(block_info) => {
-                let result = format!("Block info: {:?}", block_info);
-                self.ok_response(&serde_json::to_value(result).unwrap())
-            }
-            None => {
-                let result = format!("Block {:?} not found", block_id);
-                self.ok_response(&serde_json::to_value(result).unwrap())
-            }
-        }
+    pub fn revealed_documents_mut(&mut self) -> MapIndex<&mut Fork, Hash, PublicKey> {
+        MapIndex::new("timestamp.revealed_documents", &mut self.view)
     }
-}
 
-// Exonum model relies on introducing various public services to interact with blockchain
-pub struct TimestampService;
+    pub fn relayers_mut(&mut self) -> ProofMapIndex<&mut Fork, PublicKey, Relayer> {
+        ProofMapIndex::new("timestamp.relayers", &mut self.view)
+    }
 
-impl Service for TimestampService {
-    // mandatory identifications
-    fn service_name(&self) -> &'static str {
-        "timestamp"
+    pub fn anchors_mut(&mut self) -> ProofListIndex<&mut Fork, Anchor> {
+        ProofListIndex::new("timestamp.anchors", &mut self.view)
     }
 
-    fn service_id(&self) -> u16 {
-        SERVICE_ID
+    pub fn by_time_mut(&mut self) -> MapIndex<&mut Fork, Vec<u8>, PublicKey> {
+        MapIndex::new("timestamp.by_time", &mut self.view)
     }
 
-    fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<Transaction>, encoding::Error> {
-        let tx = TimestampServiceTransactions::tx_from_raw(raw)?;
-        Ok(tx.into())
+    pub fn owners_mut(&mut self) -> ProofMapIndex<&mut Fork, PublicKey, PublicKey> {
+        ProofMapIndex::new("timestamp.owners", &mut self.view)
     }
 
-    fn state_hash(&self, snapshot: &Snapshot) -> Vec<Hash> {
-        let schema = TimestampSchema::new(snapshot);
-        schema.state_hash()
+    pub fn owned_mut(&mut self) -> MapIndex<&mut Fork, Vec<u8>, PublicKey> {
+        MapIndex::new("timestamp.owned", &mut self.view)
     }
+}
 
-    // setup REST API
-    fn public_api_handler(&self, ctx: &ApiContext) -> Option<Box<Handler>> {
-        let mut router = Router::new();
-        let api = TimestampApi {
-            channel: ctx.node_channel().clone(),
-            blockchain: ctx.blockchain().clone(),
-        };
-        api.wire(&mut router);
-        Some(Box::new(router))
+// this one is read-only and provides access to a blockchain snapshot
+impl<T: AsRef<Snapshot>> TimestampSchema<T> {
+    pub fn new(view: T) -> Self {
+        TimestampSchema { view }
+    }
+
+    pub fn timestamps(&self) -> ProofMapIndex<&Snapshot, PublicKey, Timestamp> {
+        ProofMapIndex::new("timestamp.timestamps", self.view.as_ref())
+    }
+
+    pub fn timestamp(&self, pub_key: &PublicKey) -> Option<Timestamp> {
+        self.timestamps().get(pub_key)
+    }
+
+    pub fn records(&self) -> ProofMapIndex<&Snapshot, PublicKey, TimestampRecord> {
+        ProofMapIndex::new("timestamp.records", self.view.as_ref())
+    }
+
+    pub fn record(&self, pub_key: &PublicKey) -> Option<TimestampRecord> {
+        self.records().get(pub_key)
+    }
+
+    // key which sent each timestamp: its author, or the relayer or the owner of a wrapped one
+    pub fn submitters(&self) -> ProofMapIndex<&Snapshot, PublicKey, PublicKey> {
+        ProofMapIndex::new("timestamp.submitters", self.view.as_ref())
+    }
+
+    pub fn submitter(&self, pub_key: &PublicKey) -> Option<PublicKey> {
+        self.submitters().get(pub_key)
+    }
+
+    // a timestamp together with where it was committed
+    pub fn info(&self, pub_key: &PublicKey) -> Option<TimestampInfo> {
+        let timestamp = self.timestamp(pub_key)?;
+        let record = self.record(pub_key)?;
+        Some(TimestampInfo {
+            timestamp,
+            height: record.height(),
+            tx_hash: *record.tx_hash(),
+            owner: self.owner(pub_key),
+        })
+    }
+
+    // owner of each document key timestamped with TxOwned
+    pub fn owners(&self) -> ProofMapIndex<&Snapshot, PublicKey, PublicKey> {
+        ProofMapIndex::new("timestamp.owners", self.view.as_ref())
+    }
+
+    pub fn owner(&self, pub_key: &PublicKey) -> Option<PublicKey> {
+        self.owners().get(pub_key)
+    }
+
+    // document keys by owner, keys are made by `owned_key(owner, pub_key)`
+    pub fn owned(&self) -> MapIndex<&Snapshot, Vec<u8>, PublicKey> {
+        MapIndex::new("timestamp.owned", self.view.as_ref())
+    }
+
+    // public key of the earliest timestamp of each content
+    pub fn by_content(&self) -> MapIndex<&Snapshot, Hash, PublicKey> {
+        MapIndex::new("timestamp.by_content", self.view.as_ref())
+    }
+
+    pub fn info_by_content(&self, content: &Hash) -> Option<TimestampInfo> {
+        self.by_content().get(content).and_then(|pub_key| self.info(&pub_key))
+    }
+
+    // public keys of all timestamps in the order they were committed
+    pub fn history(&self) -> ListIndex<&Snapshot, PublicKey> {
+        ListIndex::new("timestamp.history", self.view.as_ref())
+    }
+
+    // position of the first timestamp in `history` committed at `height` or later.
+    // History is ordered by height, so it is a binary search.
+    pub fn history_position(&self, height: u64) -> u64 {
+        let history = self.history();
+        let records = self.records();
+        let (mut low, mut high) = (0, history.len());
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let pub_key = history.get(middle).unwrap();
+            if records.get(&pub_key).unwrap().height() < height {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        low
+    }
+
+    // public keys of all timestamps ordered by their time, see `time_key`
+    pub fn by_time(&self) -> MapIndex<&Snapshot, Vec<u8>, PublicKey> {
+        MapIndex::new("timestamp.by_time", self.view.as_ref())
+    }
+
+    // Walks timestamps with `from <= time < to`, starting at `cursor` if it is given.
+    // Returns a page and a cursor of the next page.
+    pub fn timestamps_by_time(
+        &self,
+        from: u64,
+        to: u64,
+        cursor: Option<Vec<u8>>,
+        limit: usize,
+    ) -> (Vec<Timestamp>, Option<Vec<u8>>) {
+        let start = cursor.unwrap_or_else(|| time_key(from, &PublicKey::new([0; PUBLIC_KEY_LENGTH])));
+        let by_time = self.by_time();
+        let mut keys = by_time
+            .iter_from(&start)
+            .take_while(|&(ref key, _)| key_time(key) < to);
+
+        let page: Vec<Timestamp> = keys.by_ref()
+            .take(limit)
+            .map(|(_, pub_key)| self.timestamp(&pub_key).unwrap())
+            .collect();
+        let next = keys.next().map(|(key, _)| key);
+        (page, next)
+    }
+
+    // Walks timestamps committed at `from_height <= height < to_height`, starting at history
+    // position `cursor` if it is given. Returns a page and a cursor of the next page.
+    pub fn timestamps_by_height(
+        &self,
+        from_height: u64,
+        to_height: u64,
+        cursor: Option<u64>,
+        limit: usize,
+    ) -> (Vec<Timestamp>, Option<u64>) {
+        let start = cursor.unwrap_or_else(|| self.history_position(from_height));
+        let end = self.history_position(to_height);
+        if start >= end {
+            return (Vec::new(), None);
+        }
+
+        let page: Vec<Timestamp> = self.history()
+            .iter_from(start)
+            .take(::std::cmp::min(limit as u64, end - start) as usize)
+            .map(|pub_key| self.timestamp(&pub_key).unwrap())
+            .collect();
+        let next = start + page.len() as u64;
+        (page, if next < end { Some(next) } else { None })
+    }
+
+    // reveals of timestamps made in the commitment mode, by the timestamp's public key
+    pub fn reveals(&self) -> ProofMapIndex<&Snapshot, PublicKey, Reveal> {
+        ProofMapIndex::new("timestamp.reveals", self.view.as_ref())
+    }
+
+    pub fn reveal(&self, pub_key: &PublicKey) -> Option<Reveal> {
+        self.reveals().get(pub_key)
+    }
+
+    // public key of the revealed timestamp of each document
+    pub fn revealed_documents(&self) -> MapIndex<&Snapshot, Hash, PublicKey> {
+        MapIndex::new("timestamp.revealed_documents", self.view.as_ref())
+    }
+
+    // registered relayers with their quotas and usage
+    pub fn relayers(&self) -> ProofMapIndex<&Snapshot, PublicKey, Relayer> {
+        ProofMapIndex::new("timestamp.relayers", self.view.as_ref())
+    }
+
+    pub fn relayer(&self, pub_key: &PublicKey) -> Option<Relayer> {
+        self.relayers().get(pub_key)
+    }
+
+    // external anchors ordered by height
+    pub fn anchors(&self) -> ProofListIndex<&Snapshot, Anchor> {
+        ProofListIndex::new("timestamp.anchors", self.view.as_ref())
+    }
+
+    // The first anchor of a block at `height` or above. The state of that block contains every
+    // timestamp made up to `height`.
+    pub fn anchor_covering(&self, height: u64) -> Option<Anchor> {
+        let anchors = self.anchors();
+        let (mut low, mut high) = (0, anchors.len());
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if anchors.get(middle).unwrap().height() < height {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        anchors.get(low)
+    }
+
+    // number of timestamps by day
+    pub fn daily_counts(&self) -> MapIndex<&Snapshot, u64, u64> {
+        MapIndex::new("timestamp.daily_counts", self.view.as_ref())
+    }
+
+    // number of distinct submitters by day
+    pub fn daily_submitters(&self) -> MapIndex<&Snapshot, u64, u64> {
+        MapIndex::new("timestamp.daily_submitters", self.view.as_ref())
+    }
+
+    // number of timestamps by day and submitter, keys are made by `time_key(day, submitter)`
+    pub fn daily_submitter_counts(&self) -> MapIndex<&Snapshot, Vec<u8>, u64> {
+        MapIndex::new("timestamp.daily_submitter_counts", self.view.as_ref())
+    }
+
+    // number of timestamps by submitter
+    pub fn submitter_counts(&self) -> MapIndex<&Snapshot, PublicKey, u64> {
+        MapIndex::new("timestamp.submitter_counts", self.view.as_ref())
+    }
+
+    pub fn total_submitters(&self) -> Entry<&Snapshot, u64> {
+        Entry::new("timestamp.total_submitters", self.view.as_ref())
+    }
+
+    // Daily buckets overlapping `from <= time < to`; days without timestamps are skipped. With a
+    // `submitter` only its timestamps are counted.
+    pub fn daily_stats(&self, from: u64, to: u64, submitter: Option<&PublicKey>) -> Vec<StatsBucket> {
+        let daily_counts = self.daily_counts();
+        let daily_submitters = self.daily_submitters();
+        let daily_submitter_counts = self.daily_submitter_counts();
+
+        daily_counts
+            .iter_from(&(from / SECONDS_PER_DAY))
+            .take_while(|&(day, _)| day * SECONDS_PER_DAY < to)
+            .filter_map(|(day, timestamps)| {
+                let (timestamps, submitters) = match submitter {
+                    Some(submitter) => {
+                        let count = daily_submitter_counts.get(&time_key(day, submitter)).unwrap_or(0);
+                        (count, if count > 0 { 1 } else { 0 })
+                    }
+                    None => (timestamps, daily_submitters.get(&day).unwrap_or(0)),
+                };
+                if timestamps == 0 {
+                    return None;
+                }
+                Some(StatsBucket {
+                    start: day * SECONDS_PER_DAY,
+                    timestamps,
+                    submitters,
+                })
+            })
+            .collect()
+    }
+
+    // number of entries of a map index, counted as they are added since maps have no length
+    pub fn index_size(&self, name: &str) -> u64 {
+        Entry::new(format!("timestamp.size.{}", name), self.view.as_ref()).get().unwrap_or(0)
+    }
+
+    // numbers of entries of all indexes, none of them is walked
+    pub fn index_sizes(&self) -> BTreeMap<&'static str, u64> {
+        let mut sizes = BTreeMap::new();
+        // every timestamp has exactly one entry in each of these
+        let timestamps = self.history().len();
+        sizes.insert("timestamps", timestamps);
+        sizes.insert("records", timestamps);
+        sizes.insert("history", timestamps);
+        sizes.insert("by_time", timestamps);
+        for name in &["by_content", "reveals", "revealed_documents", "relayers", "daily_counts"] {
+            sizes.insert(*name, self.index_size(name));
+        }
+        sizes.insert("anchors", self.anchors().len());
+        sizes.insert("submitter_counts", self.total_submitters().get().unwrap_or(0));
+        let owned = self.index_size("owners");
+        sizes.insert("owners", owned);
+        sizes.insert("owned", owned);
+        sizes
+    }
+
+    // Roots of the tables the block's state hash commits to, in the order of their table ids.
+    // Every other index is derived from these, so they are all a snapshot has to prove.
+    pub fn state_hash(&self) -> Vec<Hash> {
+        vec![
+            self.timestamps().root_hash(),
+            self.reveals().root_hash(),
+            self.relayers().root_hash(),
+            self.anchors().root_hash(),
+            self.records().root_hash(),
+            self.submitters().root_hash(),
+            self.owners().root_hash(),
+        ]
+    }
+}
+
+// Key of the time index: big-endian time followed by a public key, so keys are ordered by time
+fn time_key(time: u64, pub_key: &PublicKey) -> Vec<u8> {
+    let mut key = Vec::with_capacity(8 + PUBLIC_KEY_LENGTH);
+    for shift in (0..8).rev() {
+        key.push((time >> (shift * 8)) as u8);
+    }
+    key.extend_from_slice(pub_key.as_ref());
+    key
+}
+
+// Key of the owner index: the owner followed by a document key, so keys of an owner are adjacent
+fn owned_key(owner: &PublicKey, pub_key: &PublicKey) -> Vec<u8> {
+    let mut key = Vec::with_capacity(2 * PUBLIC_KEY_LENGTH);
+    key.extend_from_slice(owner.as_ref());
+    key.extend_from_slice(pub_key.as_ref());
+    key
+}
+
+fn key_time(key: &[u8]) -> u64 {
+    key[..8].iter().fold(0, |time, &byte| (time << 8) | u64::from(byte))
+}
+
+// A committed timestamp with the height of its block and its transaction's hash
+#[derive(Serialize, Deserialize)]
+pub struct TimestampInfo {
+    pub timestamp: Timestamp,
+    pub height: u64,
+    pub tx_hash: Hash,
+    // the owner of a document key, for timestamps sent with TxOwned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<PublicKey>,
+}
+
+// basic type to get REST response
+#[derive(Serialize, Deserialize)]
+pub struct TimestampResponse {
+    pub tx_hash: Hash,
+}
+
+// Request to build an unsigned TxTimestamp for an external signer
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PrepareRequest {
+    pub from: PublicKey,
+    pub content: Hash,
+    pub time: u64,
+}
+
+// Canonical bytes an external signer has to sign (hex) and the transaction layout without a signature
+#[derive(Serialize, Deserialize)]
+pub struct PrepareResponse {
+    pub message: String,
+    pub template: serde_json::Value,
+}
+
+impl PrepareResponse {
+    // builds TxTimestamp with an empty signature
+    pub fn new(request: &PrepareRequest) -> Self {
+        let unsigned = TxTimestamp::new_with_signature(
+            &request.from,
+            &request.content,
+            request.time,
+            &Signature::new([0; SIGNATURE_LENGTH]),
+        );
+
+        let mut template = serde_json::to_value(&unsigned).unwrap();
+        if let Some(fields) = template.as_object_mut() {
+            fields.remove("signature");
+        }
+
+        PrepareResponse {
+            message: encode_hex(unsigned.raw().body()),
+            template,
+        }
+    }
+}
+
+// TxTimestamp fields together with a signature produced by an external signer
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SignedRequest {
+    pub from: PublicKey,
+    pub content: Hash,
+    pub time: u64,
+    pub signature: Signature,
+}
+
+// A page of a range query over timestamps. `next` is the `cursor` to request the following page with
+#[derive(Serialize, Deserialize)]
+pub struct TimestampsPage {
+    pub timestamps: Vec<Timestamp>,
+    pub next: Option<String>,
+}
+
+// Activity within a bucket of `/v1/stats` starting at `start`
+#[derive(Serialize, Deserialize)]
+pub struct StatsBucket {
+    pub start: u64,
+    pub timestamps: u64,
+    pub submitters: u64,
+}
+
+// Buckets of the requested range and totals of the whole chain
+#[derive(Serialize, Deserialize)]
+pub struct StatsResponse {
+    pub buckets: Vec<StatsBucket>,
+    pub total_timestamps: u64,
+    pub total_submitters: u64,
+}
+
+// Whether a transaction is committed and the height of its block
+#[derive(Serialize, Deserialize)]
+pub struct TransactionStatus {
+    pub committed: bool,
+    pub height: Option<u64>,
+}
+
+// REST response for a single transaction of a batch: either its hash or a reason it was rejected
+#[derive(Serialize, Deserialize)]
+pub struct BatchItemResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<Hash>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// REST response for a hashed upload
+#[derive(Serialize, Deserialize)]
+pub struct HashResponse {
+    pub hash: Hash,
+    pub size: u64,
+}
+
+// Interface for system's backend
+#[derive(Clone)]
+struct TimestampApi {
+    channel: ApiSender,
+    blockchain: Blockchain,
+    max_hash_size: u64,
+    notifier: Arc<CommitNotifier>,
+    pending: Arc<PendingTransactions>,
+    metrics: Arc<Metrics>,
+}
+
+// A v1 handler
+type Action = fn(&TimestampApi, &mut Request) -> IronResult<Response>;
+
+// Registering handlers for REST API. The routes, the explorer and the `/v2` routes among them, are
+// those of `openapi::ROUTES`, so the document describes exactly what is served.
+impl Api for TimestampApi {
+    fn wire(&self, router: &mut Router) {
+        for route in openapi::ROUTES {
+            let handler = self.handler(route.name)
+                .unwrap_or_else(|| panic!("No handler for the route `{}`", route.name));
+            match route.method {
+                openapi::Method::Get => router.get(route.path, handler, route.name),
+                openapi::Method::Post => router.post(route.path, handler, route.name),
+            };
+        }
+    }
+}
+
+impl TimestampApi {
+    // the handler of a route by its name; routes of the API itself are timed under that name
+    fn handler(&self, name: &'static str) -> Option<Box<Handler>> {
+        let action: Action = match name {
+            "timestamp" => TimestampApi::timestamp,
+            "timestamps" => TimestampApi::timestamps,
+            "submit" => TimestampApi::submit,
+            "block_stats" => TimestampApi::block_stats,
+            "hash" => TimestampApi::hash,
+            "prepare" => TimestampApi::prepare,
+            "submit_signed" => TimestampApi::submit_signed,
+            "submit_batch" => TimestampApi::submit_batch,
+            "events" => TimestampApi::events,
+            "transaction_status" => TimestampApi::transaction_status,
+            "content" => TimestampApi::content,
+            "reveal" => TimestampApi::reveal,
+            "revealed" => TimestampApi::revealed,
+            "relayer" => TimestampApi::relayer,
+            "anchors" => TimestampApi::anchors,
+            "anchor" => TimestampApi::anchor,
+            "anchor_proof" => TimestampApi::anchor_proof,
+            "stats" => TimestampApi::stats,
+            "export" => TimestampApi::export,
+            "openapi" => TimestampApi::openapi,
+            "metrics" | "explorer" | "explorer_asset" => return Some(self.untimed(name)),
+            name => return self.handler_v2(name),
+        };
+        let api = self.clone();
+        let handler = move |req: &mut Request| action(&api, req);
+        Some(Box::new(Metrics::timed(&self.metrics, name, handler)))
+    }
+
+    // the scrape endpoint and the explorer are not part of the API they measure
+    fn untimed(&self, name: &str) -> Box<Handler> {
+        let action: Action = match name {
+            "metrics" => TimestampApi::metrics,
+            _ => TimestampApi::explorer,
+        };
+        let api = self.clone();
+        Box::new(move |req: &mut Request| action(&api, req))
+    }
+
+    // Endpoint for creating a new timestamp.
+    // Input: a transaction in JSON or CBOR format or a signed message in the binary format, by
+    // `Content-Type`
+    // Effect: serializes the input into TxTransaction, stores it into a blockchain
+    // Return value: transaction's hash, in the binary format just the hash
+    fn submit(&self, req: &mut Request) -> IronResult<Response> {
+        let transaction = read_transaction(req).map_err(|e| {
+            self.metrics.rejected("malformed");
+            e
+        })?;
+        let tx_hash = self.send(transaction)?;
+        match Encoding::accepted(req) {
+            Encoding::Binary => negotiation::respond_stored(req, tx_hash),
+            _ => negotiation::respond(req, &TimestampResponse { tx_hash }),
+        }
+    }
+
+    // Endpoint for creating many timestamps at once.
+    // Input: a JSON array of transactions or newline-delimited JSON with one transaction per line
+    // Effect: checks every transaction independently and passes the valid ones to a blockchain
+    // Return value: a hash or an error for each transaction, in the order of the input
+    fn submit_batch(&self, req: &mut Request) -> IronResult<Response> {
+        let body = read_batch_body(req)?.ok_or_else(|| payload_too_large(MAX_BATCH_BODY_SIZE))?;
+        let results = self.submit_items(&body)?;
+        self.ok_response(&serde_json::to_value(&results).unwrap())
+    }
+
+    // checks and sends every transaction of a batch, shared by both versions of `submit_batch`
+    fn submit_items(&self, body: &str) -> Result<Vec<BatchItemResponse>, ApiError> {
+        let items = parse_batch(body)?;
+        if items.is_empty() {
+            Err(ApiError::BadRequest("Empty request".into()))?
+        }
+        if items.len() > MAX_BATCH_SIZE {
+            Err(ApiError::BadRequest(format!("Batch exceeds {} transactions", MAX_BATCH_SIZE)))?
+        }
+
+        for _ in items.iter().filter(|item| item.is_err()) {
+            self.metrics.rejected("malformed");
+        }
+
+        Ok(items
+            .into_iter()
+            .map(|item| match item.and_then(|value| self.submit_value(value)) {
+                Ok(tx_hash) => BatchItemResponse { tx_hash: Some(tx_hash), error: None },
+                Err(e) => BatchItemResponse { tx_hash: None, error: Some(e) },
+            })
+            .collect())
+    }
+
+    // helper to validate and send a single transaction of a batch
+    fn submit_value(&self, value: serde_json::Value) -> Result<Hash, String> {
+        let transaction: TimestampServiceTransactions = serde_json::from_value(value).map_err(|e| {
+            self.metrics.rejected("malformed");
+            e.to_string()
+        })?;
+        self.send(transaction).map_err(|e| e.to_string())
+    }
+
+    // checks and passes a transaction to the node, remembering timestamps until they are committed
+    fn send(&self, transaction: TimestampServiceTransactions) -> Result<Hash, ApiError> {
+        self.metrics.submitted();
+        let timestamp = match transaction {
+            TimestampServiceTransactions::TxTimestamp(ref tx) => Some(tx.clone()),
+            TimestampServiceTransactions::TxOwned(ref tx) => Some(tx.author_tx()),
+            _ => None,
+        };
+        let time = match transaction {
+            TimestampServiceTransactions::TxTimestamp(ref tx) => Some(tx.time()),
+            TimestampServiceTransactions::TxRelayed(ref tx) => Some(tx.time()),
+            TimestampServiceTransactions::TxOwned(ref tx) => Some(tx.time()),
+            _ => None,
+        };
+        if let Some(time) = time {
+            let now = unix_time();
+            if time > now + MAX_TIME_DRIFT || time + MAX_TIME_DRIFT < now {
+                self.metrics.rejected("invalid_time");
+                return Err(ApiError::BadRequest(format!(
+                    "Time must be within {} seconds of the node's clock",
+                    MAX_TIME_DRIFT
+                )));
+            }
+        }
+        let transaction: Box<Transaction> = transaction.into();
+        if !transaction.verify() {
+            self.metrics.rejected("invalid_signature");
+            return Err(ApiError::BadRequest("Invalid signature".into()));
+        }
+
+        let tx_hash = transaction.hash();
+        self.channel.send(transaction).map_err(|e| {
+            self.metrics.rejected("node_unavailable");
+            ApiError::from(e)
+        })?;
+        self.metrics.accepted();
+        if let Some(tx) = timestamp {
+            self.pending.add(tx);
+        }
+        Ok(tx_hash)
+    }
+
+    // Endpoint for following new timestamps as Server-Sent Events.
+    // Input: optional `from_height` (or `Last-Event-ID` header) to resume from and `limit` of events
+    // Effect: replays timestamps committed since `from_height`, then pushes new ones after each block
+    // Return value: a `text/event-stream` of timestamps with their heights and transaction hashes,
+    // or 503 if the node already serves as many streams as it allows
+    fn events(&self, req: &mut Request) -> IronResult<Response> {
+        let last_event_id = req.headers
+            .get_raw("Last-Event-ID")
+            .and_then(|values| values.first())
+            .and_then(|value| String::from_utf8_lossy(value).trim().parse::<u64>().ok());
+        let from_height = match query_param::<u64>(req, "from_height")? {
+            Some(height) => height,
+            None => last_event_id.unwrap_or(0),
+        };
+        let limit = query_param::<u64>(req, "limit")?;
+
+        let stream = match EventStream::open(self.blockchain.clone(), self.notifier.clone(), from_height, limit) {
+            Some(stream) => stream,
+            None => {
+                let message = "Too many open event streams";
+                return Err(IronError::new(ResponseError(message.into()), (
+                        Status::ServiceUnavailable,
+                        Header(ContentType::json()),
+                        serde_json::to_string(message).unwrap(),
+                    )
+                ));
+            }
+        };
+        Ok(Response::with((
+            Status::Ok,
+            Header(ContentType("text/event-stream".parse().unwrap())),
+            Box::new(stream) as Box<WriteBody>,
+        )))
+    }
+
+    // Endpoint for preparing a transaction to be signed outside of the node (e.g. by an HSM).
+    // Input: a sender's public key and a content hash in JSON format
+    // Effect: builds TxTimestamp with an empty signature
+    // Return value: hex of the bytes to sign and the transaction template without a signature
+    fn prepare(&self, req: &mut Request) -> IronResult<Response> {
+        match req.get::<bodyparser::Struct<PrepareRequest>>() {
+            Ok(Some(request)) => {
+                let json = PrepareResponse::new(&request);
+                self.ok_response(&serde_json::to_value(&json).unwrap())
+            }
+            Ok(None) => Err(ApiError::BadRequest("Empty request".into()))?,
+            Err(e) => Err(ApiError::BadRequest(e.to_string()))?,
+        }
+    }
+
+    // Endpoint for submitting a transaction signed by an external signer.
+    // Input: fields of TxTimestamp and a signature of the bytes returned by `/v1/prepare`
+    // Effect: assembles TxTimestamp, checks the signature and stores it into a blockchain
+    // Return value: transaction's hash
+    fn submit_signed(&self, req: &mut Request) -> IronResult<Response> {
+        match req.get::<bodyparser::Struct<SignedRequest>>() {
+            Ok(Some(request)) => {
+                let transaction = TxTimestamp::new_with_signature(
+                    &request.from,
+                    &request.content,
+                    request.time,
+                    &request.signature,
+                );
+                let tx_hash = self.send(TimestampServiceTransactions::TxTimestamp(transaction))?;
+                let json = TimestampResponse { tx_hash };
+                self.ok_response(&serde_json::to_value(&json).unwrap())
+            }
+            Ok(None) => Err(ApiError::BadRequest("Empty request".into()))?,
+            Err(e) => Err(ApiError::BadRequest(e.to_string()))?,
+        }
+    }
+
+    // Endpoint for hashing a document on the server side.
+    // Input: raw document bytes as a request body
+    // Effect: streams the body through SHA-256 without storing it; bodies over the size cap are rejected
+    // Return value: the content hash to be used in TxTimestamp and the document size
+    fn hash(&self, req: &mut Request) -> IronResult<Response> {
+        if let Some(&ContentLength(len)) = req.headers.get::<ContentLength>() {
+            if len > self.max_hash_size {
+                return Err(payload_too_large(self.max_hash_size));
+            }
+        }
+
+        let mut stream = HashStream::new();
+        let mut buf = vec![0; HASH_CHUNK_SIZE];
+        let mut size = 0u64;
+        loop {
+            let read = req.body.read(&mut buf).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+            if read == 0 {
+                break;
+            }
+            size += read as u64;
+            if size > self.max_hash_size {
+                return Err(payload_too_large(self.max_hash_size));
+            }
+            stream = stream.update(&buf[..read]);
+        }
+
+        let json = HashResponse { hash: stream.hash(), size };
+        self.ok_response(&serde_json::to_value(&json).unwrap())
+    }
+
+    // Endpoint for searching for specific transaction.
+    // Input: a public key
+    // Effect: Finds a transaction by its public key
+    // Return value: a transaction data in JSON
+    fn timestamp(&self, req: &mut Request) -> IronResult<Response> {
+        let path = req.url.path();
+        let public_key = PublicKey::from_hex(path.last().unwrap()).map_err(
+            |e| {
+                IronError::new(e, (
+                        Status::BadRequest,
+                        Header(ContentType::json()),
+                        "\"Invalid request param: `pub_key`\"",
+                    )
+                )
+            }
+        )?;
+
+        let timestamp = {
+            let snapshot = self.blockchain.snapshot();
+            let schema = TimestampSchema::new(snapshot);
+            schema.timestamp(&public_key)
+        };
+
+        if let Some(timestamp) = timestamp {
+            negotiation::respond_stored(req, timestamp)
+        } else {
+            self.not_found_response(&serde_json::to_value("Not found").unwrap())
+        }
+    }
+
+    // Endpoint for listing all available transactions.
+    // Input: optionally a time range `from`/`to` (UNIX time) or a block range `from_height`/`to_height`,
+    // with `limit` and `cursor` for pagination; both ranges include the start and exclude the end
+    // Effect: gets a blockchain's snapshot and gathers all transactions, or walks an index for a range
+    // Return value: list of transactions, or a page of them for a range; in the binary encoding the
+    // cursor of the next page is in `X-Next-Cursor`
+    fn timestamps(&self, req: &mut Request) -> IronResult<Response> {
+        let range = TimestampRange::from_request(req)?;
+
+        let snapshot = self.blockchain.snapshot();
+        let schema = TimestampSchema::new(snapshot);
+
+        if range.is_unbounded() {
+            let idx = schema.timestamps();
+            let timestamps: Vec<Timestamp> = idx.values().collect();
+
+            return negotiation::respond_list(req, &timestamps, None, &timestamps);
+        }
+
+        let limit = page_limit(req)?;
+        let cursor = query_param::<String>(req, "cursor")?;
+        let page = range.page(&schema, cursor, limit)?;
+
+        negotiation::respond_list(req, &page.timestamps, page.next.as_ref().map(String::as_str), &page)
+    }
+
+    // Endpoint for searching for a timestamp by its content.
+    // Input: a content hash
+    // Effect: finds the earliest timestamp of the content
+    // Return value: the timestamp with its height and transaction hash
+    fn content(&self, req: &mut Request) -> IronResult<Response> {
+        let path = req.url.path();
+        let content = Hash::from_hex(path.last().unwrap()).map_err(|_| {
+            ApiError::BadRequest("Invalid request param: `hash`".into())
+        })?;
+
+        let info = {
+            let snapshot = self.blockchain.snapshot();
+            TimestampSchema::new(snapshot).info_by_content(&content)
+        };
+
+        if let Some(info) = info {
+            negotiation::respond(req, &info)
+        } else {
+            self.not_found_response(&serde_json::to_value("Not found").unwrap())
+        }
+    }
+
+    // Endpoint for searching for a reveal of a timestamp.
+    // Input: a public key of the timestamp
+    // Effect: finds a reveal of the timestamp's commitment
+    // Return value: the revealed document hash and salt
+    fn reveal(&self, req: &mut Request) -> IronResult<Response> {
+        let path = req.url.path();
+        let public_key = PublicKey::from_hex(path.last().unwrap()).map_err(|_| {
+            ApiError::BadRequest("Invalid request param: `pub_key`".into())
+        })?;
+
+        let reveal = {
+            let snapshot = self.blockchain.snapshot();
+            TimestampSchema::new(snapshot).reveal(&public_key)
+        };
+
+        if let Some(reveal) = reveal {
+            negotiation::respond_stored(req, reveal)
+        } else {
+            self.not_found_response(&serde_json::to_value("Not found").unwrap())
+        }
+    }
+
+    // Endpoint for searching for a revealed document.
+    // Input: a document hash
+    // Effect: finds the timestamp whose commitment revealed the document
+    // Return value: the reveal
+    fn revealed(&self, req: &mut Request) -> IronResult<Response> {
+        let path = req.url.path();
+        let document = Hash::from_hex(path.last().unwrap()).map_err(|_| {
+            ApiError::BadRequest("Invalid request param: `hash`".into())
+        })?;
+
+        let reveal = {
+            let snapshot = self.blockchain.snapshot();
+            let schema = TimestampSchema::new(snapshot);
+            schema.revealed_documents().get(&document).and_then(|pub_key| schema.reveal(&pub_key))
+        };
+
+        if let Some(reveal) = reveal {
+            negotiation::respond_stored(req, reveal)
+        } else {
+            self.not_found_response(&serde_json::to_value("Not found").unwrap())
+        }
+    }
+
+    // Endpoint for checking a relayer.
+    // Input: a relayer's public key
+    // Effect: finds the registered relayer
+    // Return value: the relayer's quota and the number of transactions it has already used
+    fn relayer(&self, req: &mut Request) -> IronResult<Response> {
+        let path = req.url.path();
+        let public_key = PublicKey::from_hex(path.last().unwrap()).map_err(|_| {
+            ApiError::BadRequest("Invalid request param: `pub_key`".into())
+        })?;
+
+        let relayer = {
+            let snapshot = self.blockchain.snapshot();
+            TimestampSchema::new(snapshot).relayer(&public_key)
+        };
+
+        if let Some(relayer) = relayer {
+            negotiation::respond_stored(req, relayer)
+        } else {
+            self.not_found_response(&serde_json::to_value("Not found").unwrap())
+        }
+    }
+
+    // Endpoint for charts of activity.
+    // Input: `bucket=day`, optional `from` and `to` times and `pub_key` of a submitter
+    // Effect: reads daily aggregates kept by the schema, no timestamps are scanned
+    // Return value: numbers of timestamps and distinct submitters by day, and totals of the chain
+    fn stats(&self, req: &mut Request) -> IronResult<Response> {
+        let stats = {
+            let snapshot = self.blockchain.snapshot();
+            read_stats(req, &TimestampSchema::new(snapshot))?
+        };
+        negotiation::respond(req, &stats)
+    }
+
+    // Endpoint for bulk export.
+    // Input: `format` (`csv` or `ndjson`), any number of `pub_key` params and optional `from` and `to`
+    // times
+    // Effect: walks timestamps of the given keys or of the whole chain, writing them as they are read
+    // Return value: a stream of records with keys, contents, times, heights and transaction hashes
+    fn export(&self, req: &mut Request) -> IronResult<Response> {
+        let format = match query_param::<String>(req, "format")? {
+            Some(format) => format.parse::<ExportFormat>().map_err(ApiError::BadRequest)?,
+            None => ExportFormat::Csv,
+        };
+        let keys = req.url
+            .as_ref()
+            .query_pairs()
+            .filter(|&(ref key, _)| key == "pub_key")
+            .map(|(_, value)| {
+                PublicKey::from_hex(value.as_ref()).map_err(|_| {
+                    ApiError::BadRequest("Invalid request param: `pub_key`".into())
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let filter = ExportFilter {
+            keys,
+            from: query_param::<u64>(req, "from")?,
+            to: query_param::<u64>(req, "to")?,
+        };
+
+        let stream = ExportStream::new(self.blockchain.clone(), format, filter);
+        Ok(Response::with((
+            Status::Ok,
+            Header(ContentType(format.content_type().parse().unwrap())),
+            Box::new(stream) as Box<WriteBody>,
+        )))
+    }
+
+    // Endpoint for describing the API.
+    // Input: nothing
+    // Effect: builds an OpenAPI 3 document of all routes of this API
+    // Return value: the document
+    fn openapi(&self, _: &mut Request) -> IronResult<Response> {
+        self.ok_response(&openapi::spec())
+    }
+
+    // Endpoint for the web explorer.
+    // Input: a file name, the page itself if none
+    // Effect: finds the file among those compiled into the binary
+    // Return value: the file
+    fn explorer(&self, req: &mut Request) -> IronResult<Response> {
+        let name = match req.url.path().last() {
+            Some(&"explorer") | None => "index.html",
+            Some(name) => *name,
+        };
+        match explorer::asset(name) {
+            Some((content_type, body)) => Ok(Response::with((
+                Status::Ok,
+                Header(ContentType(content_type.parse().unwrap())),
+                body,
+            ))),
+            None => self.not_found_response(&serde_json::to_value("Not found").unwrap()),
+        }
+    }
+
+    // Endpoint for Prometheus.
+    // Input: nothing
+    // Effect: reads counters of this node and sizes of the service indexes
+    // Return value: metrics in the Prometheus text format
+    fn metrics(&self, _: &mut Request) -> IronResult<Response> {
+        let body = {
+            let snapshot = self.blockchain.snapshot();
+            self.metrics.render(&*snapshot)
+        };
+        Ok(Response::with((
+            Status::Ok,
+            Header(ContentType("text/plain; version=0.0.4".parse().unwrap())),
+            body,
+        )))
+    }
+
+    // Endpoint for listing external anchors.
+    // Input: nothing
+    // Effect: reads all anchors
+    // Return value: anchors ordered by height
+    fn anchors(&self, req: &mut Request) -> IronResult<Response> {
+        let anchors: Vec<Anchor> = {
+            let snapshot = self.blockchain.snapshot();
+            TimestampSchema::new(snapshot).anchors().iter().collect()
+        };
+        negotiation::respond_list(req, &anchors, None, &anchors)
+    }
+
+    // Endpoint for the external anchor covering a block.
+    // Input: a block height, e.g. the height of a timestamp
+    // Effect: finds the first anchor of a block at that height or above
+    // Return value: the anchor
+    fn anchor(&self, req: &mut Request) -> IronResult<Response> {
+        let path = req.url.path();
+        let height = path.last().unwrap().parse::<u64>().map_err(|_| {
+            ApiError::BadRequest("Invalid request param: `height`".into())
+        })?;
+
+        let anchor = {
+            let snapshot = self.blockchain.snapshot();
+            TimestampSchema::new(snapshot).anchor_covering(height)
+        };
+
+        if let Some(anchor) = anchor {
+            negotiation::respond_stored(req, anchor)
+        } else {
+            self.not_found_response(&serde_json::to_value("Not anchored yet").unwrap())
+        }
+    }
+
+    // Endpoint for a proof linking a transaction to an external anchor.
+    // Input: a transaction hash, e.g. `tx_hash` of a timestamp's record
+    // Effect: proves the transaction in its block and collects headers up to the first anchored block
+    // Return value: the proof, see `anchoring::AnchorProof`
+    fn anchor_proof(&self, req: &mut Request) -> IronResult<Response> {
+        let path = req.url.path();
+        let tx_hash = Hash::from_hex(path.last().unwrap()).map_err(|_| {
+            ApiError::BadRequest("Invalid request param: `tx_hash`".into())
+        })?;
+
+        let proof = {
+            let snapshot = self.blockchain.snapshot();
+            AnchorProof::new(&*snapshot, &tx_hash)
+        };
+
+        if let Some(proof) = proof {
+            negotiation::respond(req, &proof)
+        } else {
+            self.not_found_response(&serde_json::to_value("Not committed or not anchored yet").unwrap())
+        }
+    }
+
+    // Endpoint for checking whether a transaction is committed.
+    // Input: a transaction hash
+    // Effect: looks the transaction up among committed ones
+    // Return value: commit status and the height of the transaction's block
+    fn transaction_status(&self, req: &mut Request) -> IronResult<Response> {
+        let path = req.url.path();
+        let tx_hash = Hash::from_hex(path.last().unwrap()).map_err(|_| {
+            ApiError::BadRequest("Invalid request param: `tx_hash`".into())
+        })?;
+
+        let snapshot = self.blockchain.snapshot();
+        let location = Schema::new(&snapshot).tx_location_by_tx_hash().get(&tx_hash);
+        let json = TransactionStatus {
+            committed: location.is_some(),
+            height: location.map(|location| location.block_height().0),
+        };
+        negotiation::respond(req, &json)
+    }
+
+    // Endpoint for searching for a specific trasactions block.
+    // Input: Block ID
+    // Effect: Creates a blockchain explorer, which is used to find a block
+    // Return value: list of transactions within a block
+    fn block_stats(&self, req: &mut Request) -> IronResult<Response> {
+        let path = req.url.path();
+        let block_id = exonum::helpers::Height(path.last().unwrap().parse::<u64>().unwrap());
+
+        let blockchain_explorer = BlockchainExplorer::new(&self.blockchain);
+
+        match blockchain_explorer.block_info(block_id) {
+            Some(block_info) => {
+                let result = format!("Block info: {:?}", block_info);
+                negotiation::respond(req, &result)
+            }
+            None => {
+                let result = format!("Block {:?} not found", block_id);
+                negotiation::respond(req, &result)
+            }
+        }
+    }
+}
+
+// Reads an optional query parameter, e.g. `from_height` in `/v1/events?from_height=10`
+fn query_param<T: FromStr>(req: &Request, name: &str) -> Result<Option<T>, ApiError> {
+    let value = req.url
+        .as_ref()
+        .query_pairs()
+        .find(|&(ref key, _)| key == name)
+        .map(|(_, value)| value.into_owned());
+
+    match value {
+        Some(value) => value.parse().map(Some).map_err(|_| {
+            ApiError::BadRequest(format!("Invalid request param: `{}`", name))
+        }),
+        None => Ok(None),
+    }
+}
+
+// Reads a body of `/v1/submit` in the encoding of its `Content-Type`
+fn read_transaction(req: &mut Request) -> IronResult<TimestampServiceTransactions> {
+    match Encoding::of_body(req) {
+        Encoding::Json => match req.get::<bodyparser::Struct<TimestampServiceTransactions>>() {
+            Ok(Some(transaction)) => Ok(transaction),
+            Ok(None) => Err(ApiError::BadRequest("Empty request".into()))?,
+            Err(e) => Err(ApiError::BadRequest(e.to_string()))?,
+        },
+        encoding => {
+            let mut body = Vec::new();
+            req.body
+                .by_ref()
+                .take(MAX_SUBMIT_SIZE + 1)
+                .read_to_end(&mut body)
+                .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+            if body.len() as u64 > MAX_SUBMIT_SIZE {
+                return Err(payload_too_large(MAX_SUBMIT_SIZE));
+            }
+            Ok(negotiation::decode_transaction(encoding, &body).map_err(ApiError::BadRequest)?)
+        }
+    }
+}
+
+// Error of a response built by hand; `ApiError` is not a `std::error::Error` for `IronError::new`
+#[derive(Debug)]
+struct ResponseError(String);
+
+impl fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl StdError for ResponseError {}
+
+fn payload_too_large(limit: u64) -> IronError {
+    let message = format!("Request body exceeds {} bytes", limit);
+    IronError::new(ResponseError(message.clone()), (
+            Status::PayloadTooLarge,
+            Header(ContentType::json()),
+            serde_json::to_string(&message).unwrap(),
+        )
+    )
+}
+
+// Reads `limit` of a page, `DEFAULT_PAGE_SIZE` if not given
+fn page_limit(req: &Request) -> Result<usize, ApiError> {
+    let limit = query_param::<usize>(req, "limit")?.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(ApiError::BadRequest(format!("`limit` must be within 1..{}", MAX_PAGE_SIZE)));
+    }
+    Ok(limit)
+}
+
+// Reads `/v1/stats` params and the daily aggregates they select
+fn read_stats<T: AsRef<Snapshot>>(req: &Request, schema: &TimestampSchema<T>) -> Result<StatsResponse, ApiError> {
+    let bucket = query_param::<String>(req, "bucket")?.unwrap_or_else(|| "day".into());
+    if bucket != "day" {
+        return Err(ApiError::BadRequest(format!("Unsupported bucket `{}`", bucket)));
+    }
+    let from = query_param::<u64>(req, "from")?.unwrap_or(0);
+    let to = query_param::<u64>(req, "to")?.unwrap_or(u64::max_value());
+    let submitter = match query_param::<String>(req, "pub_key")? {
+        Some(pub_key) => Some(PublicKey::from_hex(&pub_key).map_err(|_| {
+            ApiError::BadRequest("Invalid request param: `pub_key`".into())
+        })?),
+        None => None,
+    };
+
+    Ok(StatsResponse {
+        buckets: schema.daily_stats(from, to, submitter.as_ref()),
+        total_timestamps: schema.history().len(),
+        total_submitters: schema.total_submitters().get().unwrap_or(0),
+    })
+}
+
+// Bounds of a range query over timestamps, either by time (UNIX time) or by block height.
+// Both ranges include the start and exclude the end.
+struct TimestampRange {
+    from: Option<u64>,
+    to: Option<u64>,
+    from_height: Option<u64>,
+    to_height: Option<u64>,
+}
+
+impl TimestampRange {
+    fn from_request(req: &Request) -> Result<Self, ApiError> {
+        let range = TimestampRange {
+            from: query_param::<u64>(req, "from")?,
+            to: query_param::<u64>(req, "to")?,
+            from_height: query_param::<u64>(req, "from_height")?,
+            to_height: query_param::<u64>(req, "to_height")?,
+        };
+        if range.by_time() && range.by_height() {
+            return Err(ApiError::BadRequest("Use either a time or a height range".into()));
+        }
+        Ok(range)
+    }
+
+    fn by_time(&self) -> bool {
+        self.from.is_some() || self.to.is_some()
+    }
+
+    fn by_height(&self) -> bool {
+        self.from_height.is_some() || self.to_height.is_some()
+    }
+
+    fn is_unbounded(&self) -> bool {
+        !self.by_time() && !self.by_height()
+    }
+
+    // Walks an index for a page starting at `cursor`, the `next` of the previous page. An unbounded
+    // range walks all timestamps by height. A cursor outside of the range is rejected, so it cannot
+    // reach past its bounds.
+    fn page<T: AsRef<Snapshot>>(
+        &self,
+        schema: &TimestampSchema<T>,
+        cursor: Option<String>,
+        limit: usize,
+    ) -> Result<TimestampsPage, ApiError> {
+        fn invalid_cursor<E>(_: E) -> ApiError {
+            ApiError::BadRequest("Invalid request param: `cursor`".into())
+        }
+
+        if self.by_time() {
+            let (from, to) = (self.from.unwrap_or(0), self.to.unwrap_or(u64::max_value()));
+            let cursor = match cursor {
+                Some(cursor) => {
+                    let key = Vec::<u8>::from_hex(&cursor).map_err(invalid_cursor)?;
+                    if key.len() != 8 + PUBLIC_KEY_LENGTH || key_time(&key) < from || key_time(&key) >= to {
+                        return Err(invalid_cursor(()));
+                    }
+                    Some(key)
+                }
+                None => None,
+            };
+            let (timestamps, next) = schema.timestamps_by_time(from, to, cursor, limit);
+            Ok(TimestampsPage { timestamps, next: next.map(encode_hex) })
+        } else {
+            let (from_height, to_height) = (
+                self.from_height.unwrap_or(0),
+                self.to_height.unwrap_or(u64::max_value()),
+            );
+            let cursor = match cursor {
+                Some(cursor) => {
+                    let position = cursor.parse::<u64>().map_err(invalid_cursor)?;
+                    if position < schema.history_position(from_height)
+                        || position > schema.history_position(to_height)
+                    {
+                        return Err(invalid_cursor(()));
+                    }
+                    Some(position)
+                }
+                None => None,
+            };
+            let (timestamps, next) = schema.timestamps_by_height(from_height, to_height, cursor, limit);
+            Ok(TimestampsPage { timestamps, next: next.map(|next| next.to_string()) })
+        }
+    }
+}
+
+// Reads the body of a batch, which is buffered, so no further than the limit; `None` when it is
+// larger.
+fn read_batch_body(req: &mut Request) -> Result<Option<String>, ApiError> {
+    let mut body = Vec::new();
+    req.body
+        .by_ref()
+        .take(MAX_BATCH_BODY_SIZE + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    if body.len() as u64 > MAX_BATCH_BODY_SIZE {
+        return Ok(None);
+    }
+    String::from_utf8(body).map(Some).map_err(|e| ApiError::BadRequest(e.to_string()))
+}
+
+// Splits a batch body into separate JSON values. A malformed line of NDJSON only fails its own item,
+// while a malformed JSON array fails the whole request.
+fn parse_batch(body: &str) -> Result<Vec<Result<serde_json::Value, String>>, ApiError> {
+    let body = body.trim();
+    if body.starts_with('[') {
+        let values: Vec<serde_json::Value> =
+            serde_json::from_str(body).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+        Ok(values.into_iter().map(Ok).collect())
+    } else {
+        Ok(body.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|e| e.to_string()))
+            .collect())
+    }
+}
+
+// A relayer registered in the genesis block
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RelayerConfig {
+    pub pub_key: PublicKey,
+    pub quota: u64,
+}
+
+// Service configuration written to the genesis block
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TimestampConfig {
+    pub relayers: Vec<RelayerConfig>,
+}
+
+// Exonum model relies on introducing various public services to interact with blockchain
+pub struct TimestampService {
+    config: TimestampConfig,
+    max_hash_size: u64,
+    notifier: Arc<CommitNotifier>,
+    pending: Arc<PendingTransactions>,
+    metrics: Arc<Metrics>,
+    anchoring: Option<Anchoring>,
+    snapshot: Option<ServiceSnapshot>,
+    snapshots: Option<Arc<SnapshotSchedule>>,
+}
+
+impl TimestampService {
+    pub fn new() -> Self {
+        TimestampService {
+            config: TimestampConfig::default(),
+            max_hash_size: DEFAULT_MAX_HASH_SIZE,
+            notifier: Arc::new(CommitNotifier::new()),
+            pending: Arc::new(PendingTransactions::new()),
+            metrics: Arc::new(Metrics::new()),
+            anchoring: None,
+            snapshot: None,
+            snapshots: None,
+        }
+    }
+
+    // Seeds the genesis block with the state of another network. The snapshot has to be verified
+    // with `ServiceSnapshot::verify` first.
+    pub fn with_snapshot(mut self, snapshot: ServiceSnapshot) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
+    // saves a snapshot of every `interval`-th block to `dir`, served by `/v1/snapshot?height=`
+    pub fn with_snapshots<P: AsRef<Path>>(mut self, interval: u64, dir: P) -> Self {
+        self.snapshots = Some(Arc::new(SnapshotSchedule::new(interval, dir)));
+        self
+    }
+
+    // anchors every `interval`-th block in `sink`
+    pub fn with_anchoring(mut self, interval: u64, sink: Box<AnchorSink>) -> Self {
+        self.anchoring = Some(Anchoring::new(interval, sink));
+        self
+    }
+
+    // registers a relayer in the genesis block
+    pub fn with_relayer(mut self, pub_key: &PublicKey, quota: u64) -> Self {
+        self.config.relayers.push(RelayerConfig { pub_key: *pub_key, quota });
+        self
+    }
+
+    // sets the largest body accepted by `/v1/hash`
+    pub fn with_max_hash_size(mut self, max_hash_size: u64) -> Self {
+        self.max_hash_size = max_hash_size;
+        self
+    }
+
+    // sets the number of `/v1/events` streams served at the same time
+    pub fn with_max_event_streams(mut self, max_streams: usize) -> Self {
+        self.notifier = Arc::new(CommitNotifier::with_max_streams(max_streams));
+        self
+    }
+}
+
+impl Default for TimestampService {
+    fn default() -> Self {
+        TimestampService::new()
+    }
+}
+
+impl Service for TimestampService {
+    // mandatory identifications
+    fn service_name(&self) -> &'static str {
+        "timestamp"
+    }
+
+    fn service_id(&self) -> u16 {
+        SERVICE_ID
+    }
+
+    fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<Transaction>, encoding::Error> {
+        let tx = TimestampServiceTransactions::tx_from_raw(raw)?;
+        Ok(tx.into())
+    }
+
+    fn state_hash(&self, snapshot: &Snapshot) -> Vec<Hash> {
+        let schema = TimestampSchema::new(snapshot);
+        schema.state_hash()
+    }
+
+    // restores a snapshot if one is given and registers relayers of the configuration in the
+    // genesis block
+    fn initialize(&self, fork: &mut Fork) -> serde_json::Value {
+        let mut schema = TimestampSchema::new(fork);
+        if let Some(ref snapshot) = self.snapshot {
+            schema.restore(snapshot);
+        }
+        for relayer in &self.config.relayers {
+            schema.put_relayer(Relayer::new(&relayer.pub_key, relayer.quota, 0));
+        }
+        serde_json::to_value(&self.config).unwrap()
+    }
+
+    // wake up event streams waiting for new timestamps, forget committed transactions, update
+    // metrics, anchor the block and save its snapshot if they are due
+    fn handle_commit(&self, ctx: &ServiceContext) {
+        let height = Schema::new(ctx.snapshot()).height();
+        self.notifier.notify(height.0);
+        for waited in self.pending.prune(ctx.snapshot()) {
+            self.metrics.observe_visible(waited);
+        }
+        self.metrics.count_failures(ctx.snapshot(), height);
+        if let Some(ref anchoring) = self.anchoring {
+            anchoring.handle_commit(ctx);
+        }
+        if let Some(ref snapshots) = self.snapshots {
+            snapshots.handle_commit(ctx);
+        }
+    }
+
+    // setup REST API
+    fn public_api_handler(&self, ctx: &ApiContext) -> Option<Box<Handler>> {
+        let mut router = Router::new();
+        let api = TimestampApi {
+            channel: ctx.node_channel().clone(),
+            blockchain: ctx.blockchain().clone(),
+            max_hash_size: self.max_hash_size,
+            notifier: self.notifier.clone(),
+            pending: self.pending.clone(),
+            metrics: self.metrics.clone(),
+        };
+        api.wire(&mut router);
+        Some(Box::new(router))
+    }
+
+    // setup the operator API
+    fn private_api_handler(&self, ctx: &ApiContext) -> Option<Box<Handler>> {
+        let mut router = Router::new();
+        let api = AdminApi {
+            channel: ctx.node_channel().clone(),
+            blockchain: ctx.blockchain().clone(),
+            pending: self.pending.clone(),
+            service_keys: (*ctx.public_key(), ctx.secret_key().clone()),
+            max_hash_size: self.max_hash_size,
+            anchor_interval: self.anchoring.as_ref().map(|anchoring| anchoring.interval()),
+            snapshots: self.snapshots.clone(),
+        };
+        api.wire(&mut router);
+        Some(Box::new(router))
+    }
+}
+
+// Creates the service for nodes configured and launched with `exonum::helpers::fabric::NodeBuilder`
+pub struct ServiceFactory;
+
+impl fabric::ServiceFactory for ServiceFactory {
+    fn command(&mut self, command: fabric::CommandName) -> Option<Box<fabric::CommandExtension>> {
+        if command == fabric::Run::name() {
+            Some(Box::new(RunArgs))
+        } else {
+            None
+        }
+    }
+
+    // A snapshot which fails verification stops the node before it creates the genesis block.
+    fn make_service(&mut self, context: &Context) -> Box<Service> {
+        let mut service = TimestampService::new();
+        service.anchoring = Anchoring::from_context(context);
+        service.snapshots = SnapshotSchedule::from_context(context).map(Arc::new);
+        match snapshot::restored(context) {
+            Ok(Some(snapshot)) => service = service.with_snapshot(snapshot),
+            Ok(None) => {}
+            Err(e) => panic!("Cannot restore the snapshot: {}", e),
+        }
+        Box::new(service)
+    }
+}
+
+// Arguments the service adds to `timestamping-node run`: a factory extends a command once, so
+// this joins those of `AnchoringArgs` and `snapshot::snapshot_args`.
+struct RunArgs;
+
+impl CommandExtension for RunArgs {
+    fn args(&self) -> Vec<fabric::Argument> {
+        let mut args = AnchoringArgs.args();
+        args.extend(snapshot::snapshot_args());
+        args
+    }
+
+    fn execute(&self, context: Context) -> Result<Context, failure::Error> {
+        AnchoringArgs.execute(context)
     }
 }
@@ -0,0 +1,214 @@
+// Metrics of the service in the Prometheus text format, served at `/metrics`.
+//
+// Counters and histograms live in memory of the node and start from zero on restart; gauges of
+// index sizes are read from the database on every scrape.
+
+use exonum::blockchain::{Schema, TransactionErrorType};
+use exonum::helpers::Height;
+use exonum::storage::Snapshot;
+
+use iron::prelude::*;
+use iron::Handler;
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::{ErrorCode, TimestampSchema, SERVICE_ID};
+
+// upper bounds of histogram buckets in seconds
+const LATENCY_BUCKETS: &[f64] = &[0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+const VISIBLE_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+struct Histogram {
+    buckets: &'static [f64],
+    // observations in each bucket, not cumulative
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Self {
+        Histogram {
+            buckets,
+            counts: vec![0; buckets.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9;
+        if let Some(idx) = self.buckets.iter().position(|&bound| seconds <= bound) {
+            self.counts[idx] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in self.buckets.iter().zip(&self.counts) {
+            cumulative += count;
+            writeln!(out, "{}_bucket{{{}le=\"{}\"}} {}", name, labels, bound, cumulative).unwrap();
+        }
+        writeln!(out, "{}_bucket{{{}le=\"+Inf\"}} {}", name, labels, self.count).unwrap();
+        let labels = match labels.trim_end_matches(',') {
+            "" => String::new(),
+            labels => format!("{{{}}}", labels),
+        };
+        writeln!(out, "{}_sum{} {}", name, labels, self.sum).unwrap();
+        writeln!(out, "{}_count{} {}", name, labels, self.count).unwrap();
+    }
+}
+
+struct Counters {
+    submitted: u64,
+    accepted: u64,
+    rejected: BTreeMap<&'static str, u64>,
+    routes: BTreeMap<&'static str, Histogram>,
+    visible: Histogram,
+}
+
+// Metrics shared by the API handlers and the service
+pub struct Metrics {
+    counters: Mutex<Counters>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics {
+            counters: Mutex::new(Counters {
+                submitted: 0,
+                accepted: 0,
+                rejected: BTreeMap::new(),
+                routes: BTreeMap::new(),
+                visible: Histogram::new(VISIBLE_BUCKETS),
+            }),
+        }
+    }
+
+    // a transaction reached the API
+    pub fn submitted(&self) {
+        self.counters.lock().unwrap().submitted += 1;
+    }
+
+    // a transaction passed checks of the API and was passed to the node
+    pub fn accepted(&self) {
+        self.counters.lock().unwrap().accepted += 1;
+    }
+
+    // a transaction was refused by the API or failed on execution
+    pub fn rejected(&self, reason: &'static str) {
+        *self.counters.lock().unwrap().rejected.entry(reason).or_insert(0) += 1;
+    }
+
+    pub fn observe_route(&self, route: &'static str, duration: Duration) {
+        self.counters
+            .lock()
+            .unwrap()
+            .routes
+            .entry(route)
+            .or_insert_with(|| Histogram::new(LATENCY_BUCKETS))
+            .observe(duration);
+    }
+
+    // time from accepting a timestamp to it becoming visible in a committed block
+    pub fn observe_visible(&self, duration: Duration) {
+        self.counters.lock().unwrap().visible.observe(duration);
+    }
+
+    // counts transactions of the service which failed on execution in the block at `height`
+    pub fn count_failures(&self, snapshot: &Snapshot, height: Height) {
+        let schema = Schema::new(snapshot);
+        let results = schema.transaction_results();
+        for tx_hash in schema.block_txs(height).iter() {
+            let ours = schema.transactions().get(&tx_hash).map_or(false, |raw| raw.service_id() == SERVICE_ID);
+            if !ours {
+                continue;
+            }
+            if let Some(Err(e)) = results.get(&tx_hash) {
+                self.rejected(match e.error_type() {
+                    TransactionErrorType::Code(code) => ErrorCode::name(code),
+                    TransactionErrorType::Panic => "panic",
+                });
+            }
+        }
+    }
+
+    // wraps a handler of the route to measure its latency
+    pub fn timed<H: Handler>(metrics: &Arc<Metrics>, route: &'static str, handler: H) -> Timed<H> {
+        Timed {
+            metrics: metrics.clone(),
+            route,
+            handler,
+        }
+    }
+
+    pub fn render(&self, snapshot: &Snapshot) -> String {
+        let mut out = String::new();
+        {
+            let counters = self.counters.lock().unwrap();
+
+            out.push_str("# HELP timestamp_transactions_submitted_total Transactions received by the API.\n");
+            out.push_str("# TYPE timestamp_transactions_submitted_total counter\n");
+            writeln!(out, "timestamp_transactions_submitted_total {}", counters.submitted).unwrap();
+
+            out.push_str("# HELP timestamp_transactions_accepted_total Transactions passed to the node.\n");
+            out.push_str("# TYPE timestamp_transactions_accepted_total counter\n");
+            writeln!(out, "timestamp_transactions_accepted_total {}", counters.accepted).unwrap();
+
+            out.push_str(
+                "# HELP timestamp_transactions_rejected_total Transactions refused by the API or failed on execution.\n",
+            );
+            out.push_str("# TYPE timestamp_transactions_rejected_total counter\n");
+            for (reason, count) in &counters.rejected {
+                writeln!(out, "timestamp_transactions_rejected_total{{reason=\"{}\"}} {}", reason, count).unwrap();
+            }
+
+            out.push_str("# HELP timestamp_http_request_duration_seconds Latency of REST handlers.\n");
+            out.push_str("# TYPE timestamp_http_request_duration_seconds histogram\n");
+            for (route, histogram) in &counters.routes {
+                let labels = format!("route=\"{}\",", route);
+                histogram.render(&mut out, "timestamp_http_request_duration_seconds", &labels);
+            }
+
+            out.push_str(
+                "# HELP timestamp_commit_visible_seconds Time from accepting a timestamp to its commit.\n",
+            );
+            out.push_str("# TYPE timestamp_commit_visible_seconds histogram\n");
+            counters.visible.render(&mut out, "timestamp_commit_visible_seconds", "");
+        }
+
+        out.push_str("# HELP timestamp_index_entries Entries in indexes of the service.\n");
+        out.push_str("# TYPE timestamp_index_entries gauge\n");
+        for (index, size) in TimestampSchema::new(snapshot).index_sizes() {
+            writeln!(out, "timestamp_index_entries{{index=\"{}\"}} {}", index, size).unwrap();
+        }
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+// A handler measuring the latency of another one
+pub struct Timed<H> {
+    metrics: Arc<Metrics>,
+    route: &'static str,
+    handler: H,
+}
+
+impl<H: Handler> Handler for Timed<H> {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let start = Instant::now();
+        let response = self.handler.handle(req);
+        self.metrics.observe_route(self.route, start.elapsed());
+        response
+    }
+}
@@ -0,0 +1,274 @@
+// Content negotiation of the v1 API.
+//
+// Besides JSON, read endpoints answer in CBOR for `Accept: application/cbor`, and endpoints
+// returning values stored in the database answer in the native binary encoding of Exonum for
+// `Accept: application/octet-stream`. `/v1/submit` takes bodies in the same encodings by their
+// `Content-Type`; a binary body is the signed message itself. Errors are always JSON.
+//
+// CBOR carries the same document as JSON, except that hashes, keys and signatures, which JSON
+// writes as 64 or 128 hex digits, are CBOR byte strings of half the size. `to_cbor` and
+// `from_cbor` convert between the two. A list in the binary encoding is a sequence of values, each
+// preceded by its length as a little-endian u32; the cursor of a page goes to `X-Next-Cursor`.
+
+use exonum::blockchain::TransactionSet;
+use exonum::crypto::{HASH_SIZE, SIGNATURE_LENGTH};
+use exonum::encoding::serialize::{encode_hex, FromHex};
+use exonum::messages::{MessageBuffer, RawMessage, HEADER_LENGTH};
+use exonum::storage::StorageValue;
+
+use iron::headers::ContentType;
+use iron::modifiers::Header;
+use iron::prelude::*;
+use iron::status::Status;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_cbor::{self, ObjectKey};
+use serde_json::{self, Number};
+
+use std::collections::BTreeMap;
+
+use super::{TimestampServiceTransactions, SERVICE_ID};
+
+pub const CBOR: &str = "application/cbor";
+pub const BINARY: &str = "application/octet-stream";
+
+// header with the cursor of the next page of a binary list
+pub const NEXT_CURSOR: &str = "X-Next-Cursor";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Json,
+    Cbor,
+    // `StorageValue` bytes of a value or a raw message
+    Binary,
+}
+
+impl Encoding {
+    pub fn content_type(&self) -> &'static str {
+        match *self {
+            Encoding::Json => "application/json",
+            Encoding::Cbor => CBOR,
+            Encoding::Binary => BINARY,
+        }
+    }
+
+    fn from_media_type(media_type: &str) -> Option<Encoding> {
+        match media_type {
+            "application/json" => Some(Encoding::Json),
+            CBOR => Some(Encoding::Cbor),
+            BINARY => Some(Encoding::Binary),
+            _ => None,
+        }
+    }
+
+    // The first encoding of `Accept` which is supported, JSON if there is none. Quality values are
+    // not weighed, so clients list the preferred encoding first.
+    pub fn accepted(req: &Request) -> Encoding {
+        media_types(req, "Accept")
+            .iter()
+            .filter_map(|media_type| Encoding::from_media_type(media_type))
+            .next()
+            .unwrap_or(Encoding::Json)
+    }
+
+    // encoding of a request body by its `Content-Type`, JSON if it is not given or unknown
+    pub fn of_body(req: &Request) -> Encoding {
+        media_types(req, "Content-Type")
+            .first()
+            .and_then(|media_type| Encoding::from_media_type(media_type))
+            .unwrap_or(Encoding::Json)
+    }
+}
+
+// media types of a header without their params, e.g. `application/cbor` of `application/cbor; q=1`
+fn media_types(req: &Request, header: &str) -> Vec<String> {
+    req.headers
+        .get_raw(header)
+        .unwrap_or(&[])
+        .iter()
+        .flat_map(|value| {
+            String::from_utf8_lossy(value)
+                .split(',')
+                .map(|media_type| media_type.split(';').next().unwrap().trim().to_lowercase())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+// Answers with a document in the encoding the client accepts. Only stored values and lists of
+// them have a binary encoding, so it is not acceptable here.
+pub fn respond<T: Serialize>(req: &Request, value: &T) -> IronResult<Response> {
+    match Encoding::accepted(req) {
+        Encoding::Binary => Ok(Response::with((
+            Status::NotAcceptable,
+            Header(ContentType::json()),
+            serde_json::to_string_pretty("Binary encoding is available for stored values only").unwrap(),
+        ))),
+        encoding => Ok(document(encoding, value)),
+    }
+}
+
+// Answers with a stored value in the encoding the client accepts
+pub fn respond_stored<T: Serialize + StorageValue>(req: &Request, value: T) -> IronResult<Response> {
+    match Encoding::accepted(req) {
+        Encoding::Binary => Ok(Response::with((
+            Status::Ok,
+            Header(ContentType(BINARY.parse().unwrap())),
+            value.into_bytes(),
+        ))),
+        encoding => Ok(document(encoding, &value)),
+    }
+}
+
+// Answers with stored values in the encoding the client accepts; `next` is the cursor of the next
+// page, which JSON and CBOR carry in `document` itself
+pub fn respond_list<T, D>(req: &Request, values: &[T], next: Option<&str>, document: &D) -> IronResult<Response>
+where
+    T: StorageValue + Clone,
+    D: Serialize,
+{
+    match Encoding::accepted(req) {
+        Encoding::Binary => {
+            let mut body = Vec::new();
+            for value in values {
+                let bytes = value.clone().into_bytes();
+                body.extend_from_slice(&u32_bytes(bytes.len() as u32));
+                body.extend_from_slice(&bytes);
+            }
+            let mut response = Response::with((Status::Ok, Header(ContentType(BINARY.parse().unwrap())), body));
+            if let Some(next) = next {
+                response.headers.set_raw(NEXT_CURSOR, vec![next.as_bytes().to_vec()]);
+            }
+            Ok(response)
+        }
+        encoding => Ok(self::document(encoding, document)),
+    }
+}
+
+// splits a binary list into the bytes of its values
+pub fn split_list(mut body: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    let mut values = Vec::new();
+    while !body.is_empty() {
+        if body.len() < 4 {
+            return Err("Truncated length".into());
+        }
+        let len = body[..4].iter().rev().fold(0, |len, &byte| (len << 8) | byte as usize);
+        if body.len() < 4 + len {
+            return Err("Truncated value".into());
+        }
+        values.push(body[4..4 + len].to_vec());
+        body = &body[4 + len..];
+    }
+    Ok(values)
+}
+
+fn u32_bytes(value: u32) -> [u8; 4] {
+    [value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]
+}
+
+// CBOR of a value, with hashes, keys and signatures as byte strings
+pub fn to_cbor<T: Serialize>(value: &T) -> Vec<u8> {
+    let value = serde_json::to_value(value).unwrap();
+    serde_cbor::to_vec(&json_to_cbor(value)).unwrap()
+}
+
+// reads a value written by `to_cbor`; hex strings are accepted in place of byte strings
+pub fn from_cbor<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    let value: serde_cbor::Value = serde_cbor::from_slice(bytes).map_err(|e| e.to_string())?;
+    serde_json::from_value(cbor_to_json(value)?).map_err(|e| e.to_string())
+}
+
+// hex strings of hashes, keys and signatures, which are the only strings of these lengths
+fn binary_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() != 2 * HASH_SIZE && s.len() != 2 * SIGNATURE_LENGTH {
+        return None;
+    }
+    if !s.bytes().all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte)) {
+        return None;
+    }
+    Vec::<u8>::from_hex(s).ok()
+}
+
+fn json_to_cbor(value: serde_json::Value) -> serde_cbor::Value {
+    match value {
+        serde_json::Value::Null => serde_cbor::Value::Null,
+        serde_json::Value::Bool(value) => serde_cbor::Value::Bool(value),
+        serde_json::Value::Number(number) => match (number.as_u64(), number.as_i64()) {
+            (Some(value), _) => serde_cbor::Value::U64(value),
+            (None, Some(value)) => serde_cbor::Value::I64(value),
+            _ => serde_cbor::Value::F64(number.as_f64().unwrap()),
+        },
+        serde_json::Value::String(s) => match binary_hex(&s) {
+            Some(bytes) => serde_cbor::Value::Bytes(bytes),
+            None => serde_cbor::Value::String(s),
+        },
+        serde_json::Value::Array(values) => serde_cbor::Value::Array(values.into_iter().map(json_to_cbor).collect()),
+        serde_json::Value::Object(fields) => serde_cbor::Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (ObjectKey::String(key), json_to_cbor(value)))
+                .collect::<BTreeMap<_, _>>(),
+        ),
+    }
+}
+
+fn cbor_to_json(value: serde_cbor::Value) -> Result<serde_json::Value, String> {
+    Ok(match value {
+        serde_cbor::Value::Null => serde_json::Value::Null,
+        serde_cbor::Value::Bool(value) => serde_json::Value::Bool(value),
+        serde_cbor::Value::U64(value) => serde_json::Value::Number(value.into()),
+        serde_cbor::Value::I64(value) => serde_json::Value::Number(value.into()),
+        serde_cbor::Value::F64(value) => {
+            serde_json::Value::Number(Number::from_f64(value).ok_or_else(|| "Invalid number".to_owned())?)
+        }
+        serde_cbor::Value::Bytes(bytes) => serde_json::Value::String(encode_hex(&bytes)),
+        serde_cbor::Value::String(s) => serde_json::Value::String(s),
+        serde_cbor::Value::Array(values) => {
+            serde_json::Value::Array(values.into_iter().map(cbor_to_json).collect::<Result<_, _>>()?)
+        }
+        serde_cbor::Value::Object(fields) => {
+            let mut object = serde_json::Map::new();
+            for (key, value) in fields {
+                let key = match key {
+                    ObjectKey::String(key) => key,
+                    _ => return Err("Map keys must be strings".into()),
+                };
+                object.insert(key, cbor_to_json(value)?);
+            }
+            serde_json::Value::Object(object)
+        }
+    })
+}
+
+fn document<T: Serialize>(encoding: Encoding, value: &T) -> Response {
+    let body = match encoding {
+        // through `Value` to keep the output of `Api::ok_response`
+        Encoding::Json => serde_json::to_vec_pretty(&serde_json::to_value(value).unwrap()).unwrap(),
+        Encoding::Cbor => to_cbor(value),
+        Encoding::Binary => unreachable!(),
+    };
+    Response::with((
+        Status::Ok,
+        Header(ContentType(encoding.content_type().parse().unwrap())),
+        body,
+    ))
+}
+
+// Reads a transaction of the service from a body in the given encoding
+pub fn decode_transaction(encoding: Encoding, body: &[u8]) -> Result<TimestampServiceTransactions, String> {
+    match encoding {
+        Encoding::Json => serde_json::from_slice(body).map_err(|e| e.to_string()),
+        Encoding::Cbor => from_cbor(body),
+        Encoding::Binary => {
+            if body.len() < HEADER_LENGTH + SIGNATURE_LENGTH {
+                return Err("Message is too short".into());
+            }
+            let raw = RawMessage::new(MessageBuffer::from_vec(body.to_vec()));
+            if raw.service_id() != SERVICE_ID {
+                return Err(format!("Message of unknown service {}", raw.service_id()));
+            }
+            TimestampServiceTransactions::tx_from_raw(raw).map_err(|e| e.to_string())
+        }
+    }
+}
@@ -0,0 +1,1034 @@
+// OpenAPI 3 description of the public REST API, served at `/v1/openapi.json`.
+//
+// `ROUTES` is the route table of the public API: `TimestampApi::wire` registers exactly these
+// routes, in the router's path syntax, with the handlers their names select, and the document is
+// built from it and from the schemas of the JSON types in `schemas`. A route without a handler
+// stops the node when the API is wired.
+
+use serde_json::{Map, Value};
+
+use super::{MAX_BATCH_SIZE, MAX_PAGE_SIZE, MAX_TIME_DRIFT, SERVICE_ID};
+
+pub const OPENAPI_VERSION: &str = "3.0.0";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Method::Get => "get",
+            Method::Post => "post",
+        }
+    }
+}
+
+// Values a parameter takes
+#[derive(Clone, Copy)]
+pub enum Kind {
+    Integer,
+    // 64 hexadecimal digits: a hash or a public key
+    Hex,
+    Text,
+    OneOf(&'static [&'static str]),
+}
+
+// A path parameter if the path has `:name` in it, a query parameter otherwise
+pub struct Param {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub description: &'static str,
+}
+
+// A request or response body: a JSON value of a schema, the value in JSON or CBOR, the value in
+// these or the binary encoding, the value in an envelope of v2 or content of another type. A list
+// of stored values is `Stored` as well. See `negotiation`.
+#[derive(Clone, Copy)]
+pub enum Body {
+    Json(&'static str),
+    Negotiated(&'static str),
+    Stored(&'static str),
+    Envelope(&'static str),
+    Raw(&'static str),
+}
+
+pub struct Route {
+    pub method: Method,
+    pub path: &'static str,
+    // operation id, the same as the route's name in the router
+    pub name: &'static str,
+    pub summary: &'static str,
+    pub params: &'static [Param],
+    pub body: Option<Body>,
+    pub response: Body,
+    // error statuses besides the common `400`
+    pub errors: &'static [u16],
+}
+
+const PUB_KEY: Param = Param {
+    name: "pub_key",
+    kind: Kind::Hex,
+    description: "Public key of an author",
+};
+
+const CONTENT_HASH: Param = Param {
+    name: "hash",
+    kind: Kind::Hex,
+    description: "SHA-256 hash of a document",
+};
+
+// params of range queries over timestamps
+const RANGE_PARAMS: &[Param] = &[
+    Param {
+        name: "from",
+        kind: Kind::Integer,
+        description: "Start of a time range, UNIX time, inclusive",
+    },
+    Param {
+        name: "to",
+        kind: Kind::Integer,
+        description: "End of a time range, UNIX time, exclusive",
+    },
+    Param {
+        name: "from_height",
+        kind: Kind::Integer,
+        description: "Start of a block range, inclusive",
+    },
+    Param {
+        name: "to_height",
+        kind: Kind::Integer,
+        description: "End of a block range, exclusive",
+    },
+    Param {
+        name: "limit",
+        kind: Kind::Integer,
+        description: "Timestamps in a page",
+    },
+    Param {
+        name: "cursor",
+        kind: Kind::Text,
+        description: "`next` of the previous page",
+    },
+];
+
+// params of `stats`
+const STATS_PARAMS: &[Param] = &[
+    Param {
+        name: "bucket",
+        kind: Kind::OneOf(&["day"]),
+        description: "Length of a bucket",
+    },
+    Param {
+        name: "from",
+        kind: Kind::Integer,
+        description: "Start of the range, UNIX time, inclusive",
+    },
+    Param {
+        name: "to",
+        kind: Kind::Integer,
+        description: "End of the range, UNIX time, exclusive",
+    },
+    Param {
+        name: "pub_key",
+        kind: Kind::Hex,
+        description: "Count only timestamps sent by this key",
+    },
+];
+
+const HEIGHT: Param = Param {
+    name: "height",
+    kind: Kind::Integer,
+    description: "Block height",
+};
+
+pub const ROUTES: &[Route] = &[
+    Route {
+        method: Method::Get,
+        path: "/v1/timestamp/:pub_key",
+        name: "timestamp",
+        summary: "Timestamp of a key",
+        params: &[PUB_KEY],
+        body: None,
+        response: Body::Stored("Timestamp"),
+        errors: &[404],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v1/timestamps",
+        name: "timestamps",
+        summary: "All timestamps, or a page of a time or a block range",
+        params: RANGE_PARAMS,
+        body: None,
+        response: Body::Stored("TimestampList"),
+        errors: &[],
+    },
+    Route {
+        method: Method::Post,
+        path: "/v1/submit",
+        name: "submit",
+        summary: "Submit a signed transaction of the service",
+        params: &[],
+        body: Some(Body::Stored("Transaction")),
+        response: Body::Stored("TimestampResponse"),
+        errors: &[500],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v1/block_stats/:id",
+        name: "block_stats",
+        summary: "Description of a block",
+        params: &[
+            Param {
+                name: "id",
+                kind: Kind::Integer,
+                description: "Height of the block",
+            },
+        ],
+        body: None,
+        response: Body::Negotiated("BlockStats"),
+        errors: &[],
+    },
+    Route {
+        method: Method::Post,
+        path: "/v1/hash",
+        name: "hash",
+        summary: "SHA-256 hash of an uploaded document",
+        params: &[],
+        body: Some(Body::Raw("application/octet-stream")),
+        response: Body::Json("HashResponse"),
+        errors: &[413],
+    },
+    Route {
+        method: Method::Post,
+        path: "/v1/prepare",
+        name: "prepare",
+        summary: "Bytes of a timestamp transaction for an external signer",
+        params: &[],
+        body: Some(Body::Json("PrepareRequest")),
+        response: Body::Json("PrepareResponse"),
+        errors: &[],
+    },
+    Route {
+        method: Method::Post,
+        path: "/v1/submit_signed",
+        name: "submit_signed",
+        summary: "Submit a timestamp signed by an external signer",
+        params: &[],
+        body: Some(Body::Json("SignedRequest")),
+        response: Body::Json("TimestampResponse"),
+        errors: &[500],
+    },
+    Route {
+        method: Method::Post,
+        path: "/v1/submit_batch",
+        name: "submit_batch",
+        summary: "Submit many transactions as a JSON array or newline-delimited JSON",
+        params: &[],
+        body: Some(Body::Json("Batch")),
+        response: Body::Json("BatchResponse"),
+        errors: &[413],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v1/events",
+        name: "events",
+        summary: "Server-Sent Events of committed timestamps",
+        params: &[
+            Param {
+                name: "from_height",
+                kind: Kind::Integer,
+                description: "Height to replay timestamps from, `Last-Event-ID` if not given",
+            },
+            Param {
+                name: "limit",
+                kind: Kind::Integer,
+                description: "Events to send before closing the stream",
+            },
+        ],
+        body: None,
+        response: Body::Raw("text/event-stream"),
+        errors: &[503],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v1/transaction/:tx_hash",
+        name: "transaction_status",
+        summary: "Whether a transaction is committed",
+        params: &[
+            Param {
+                name: "tx_hash",
+                kind: Kind::Hex,
+                description: "Hash of the transaction",
+            },
+        ],
+        body: None,
+        response: Body::Negotiated("TransactionStatus"),
+        errors: &[],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v1/content/:hash",
+        name: "content",
+        summary: "Timestamp of a content hash",
+        params: &[CONTENT_HASH],
+        body: None,
+        response: Body::Negotiated("TimestampInfo"),
+        errors: &[404],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v1/reveal/:pub_key",
+        name: "reveal",
+        summary: "Revealed document of a key",
+        params: &[PUB_KEY],
+        body: None,
+        response: Body::Stored("Reveal"),
+        errors: &[404],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v1/revealed/:hash",
+        name: "revealed",
+        summary: "Reveal of a document",
+        params: &[CONTENT_HASH],
+        body: None,
+        response: Body::Stored("Reveal"),
+        errors: &[404],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v1/relayer/:pub_key",
+        name: "relayer",
+        summary: "Quota of a relayer",
+        params: &[
+            Param {
+                name: "pub_key",
+                kind: Kind::Hex,
+                description: "Public key of the relayer",
+            },
+        ],
+        body: None,
+        response: Body::Stored("Relayer"),
+        errors: &[404],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v1/anchors",
+        name: "anchors",
+        summary: "External anchors ordered by height",
+        params: &[],
+        body: None,
+        response: Body::Stored("AnchorList"),
+        errors: &[],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v1/anchor/:height",
+        name: "anchor",
+        summary: "First anchor at or above a height",
+        params: &[
+            Param {
+                name: "height",
+                kind: Kind::Integer,
+                description: "Block height, e.g. of a timestamp",
+            },
+        ],
+        body: None,
+        response: Body::Stored("Anchor"),
+        errors: &[404],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v1/anchor/proof/:tx_hash",
+        name: "anchor_proof",
+        summary: "Proof linking a committed transaction to the first anchor after it",
+        params: &[
+            Param {
+                name: "tx_hash",
+                kind: Kind::Hex,
+                description: "Hash of the transaction, e.g. of a timestamp's record",
+            },
+        ],
+        body: None,
+        response: Body::Negotiated("AnchorProof"),
+        errors: &[404],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v1/stats",
+        name: "stats",
+        summary: "Timestamps and submitters per bucket of time",
+        params: STATS_PARAMS,
+        body: None,
+        response: Body::Negotiated("StatsResponse"),
+        errors: &[],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v1/export",
+        name: "export",
+        summary: "Stream of timestamps for audits",
+        params: &[
+            Param {
+                name: "format",
+                kind: Kind::OneOf(&["csv", "ndjson"]),
+                description: "Format of the records, `csv` if not given",
+            },
+            Param {
+                name: "pub_key",
+                kind: Kind::Hex,
+                description: "Export only timestamps of this key; may be repeated",
+            },
+            Param {
+                name: "from",
+                kind: Kind::Integer,
+                description: "Start of a time range, UNIX time, inclusive",
+            },
+            Param {
+                name: "to",
+                kind: Kind::Integer,
+                description: "End of a time range, UNIX time, exclusive",
+            },
+        ],
+        body: None,
+        response: Body::Raw("text/csv"),
+        errors: &[],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v1/openapi.json",
+        name: "openapi",
+        summary: "This document",
+        params: &[],
+        body: None,
+        response: Body::Json("OpenApi"),
+        errors: &[],
+    },
+    Route {
+        method: Method::Get,
+        path: "/metrics",
+        name: "metrics",
+        summary: "Metrics in the Prometheus text format",
+        params: &[],
+        body: None,
+        response: Body::Raw("text/plain; version=0.0.4"),
+        errors: &[],
+    },
+    Route {
+        method: Method::Get,
+        path: "/explorer",
+        name: "explorer",
+        summary: "Web explorer",
+        params: &[],
+        body: None,
+        response: Body::Raw("text/html"),
+        errors: &[],
+    },
+    Route {
+        method: Method::Get,
+        path: "/explorer/:file",
+        name: "explorer_asset",
+        summary: "A file of the web explorer",
+        params: &[
+            Param {
+                name: "file",
+                kind: Kind::Text,
+                description: "Name of the file",
+            },
+        ],
+        body: None,
+        response: Body::Raw("*/*"),
+        errors: &[404],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v2/timestamps",
+        name: "v2_timestamps",
+        summary: "A page of all timestamps in the order of commit, or of a time or a block range",
+        params: RANGE_PARAMS,
+        body: None,
+        response: Body::Envelope("TimestampArray"),
+        errors: &[],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v2/timestamps/:pub_key",
+        name: "v2_timestamp",
+        summary: "Timestamp of a key",
+        params: &[PUB_KEY],
+        body: None,
+        response: Body::Envelope("Timestamp"),
+        errors: &[404],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v2/content/:hash",
+        name: "v2_content",
+        summary: "Timestamp of a content hash",
+        params: &[CONTENT_HASH],
+        body: None,
+        response: Body::Envelope("TimestampInfo"),
+        errors: &[404],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v2/blocks/:height",
+        name: "v2_block",
+        summary: "A block with its precommits and transaction hashes",
+        params: &[HEIGHT],
+        body: None,
+        response: Body::Envelope("BlockInfo"),
+        errors: &[404],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v2/transactions/:tx_hash",
+        name: "v2_transaction",
+        summary: "Whether a transaction is committed",
+        params: &[
+            Param {
+                name: "tx_hash",
+                kind: Kind::Hex,
+                description: "Hash of the transaction",
+            },
+        ],
+        body: None,
+        response: Body::Envelope("TransactionStatus"),
+        errors: &[],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v2/reveals/:pub_key",
+        name: "v2_reveal",
+        summary: "Revealed document of a key",
+        params: &[PUB_KEY],
+        body: None,
+        response: Body::Envelope("Reveal"),
+        errors: &[404],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v2/revealed/:hash",
+        name: "v2_revealed",
+        summary: "Reveal of a document",
+        params: &[CONTENT_HASH],
+        body: None,
+        response: Body::Envelope("Reveal"),
+        errors: &[404],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v2/relayers/:pub_key",
+        name: "v2_relayer",
+        summary: "Quota of a relayer",
+        params: &[
+            Param {
+                name: "pub_key",
+                kind: Kind::Hex,
+                description: "Public key of the relayer",
+            },
+        ],
+        body: None,
+        response: Body::Envelope("Relayer"),
+        errors: &[404],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v2/anchors",
+        name: "v2_anchors",
+        summary: "A page of external anchors ordered by height",
+        params: &[
+            Param {
+                name: "limit",
+                kind: Kind::Integer,
+                description: "Anchors in a page",
+            },
+            Param {
+                name: "cursor",
+                kind: Kind::Text,
+                description: "`next` of the previous page",
+            },
+        ],
+        body: None,
+        response: Body::Envelope("AnchorList"),
+        errors: &[],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v2/anchors/:height",
+        name: "v2_anchor",
+        summary: "First anchor at or above a height",
+        params: &[HEIGHT],
+        body: None,
+        response: Body::Envelope("Anchor"),
+        errors: &[404],
+    },
+    Route {
+        method: Method::Get,
+        path: "/v2/stats",
+        name: "v2_stats",
+        summary: "Timestamps and submitters per bucket of time",
+        params: STATS_PARAMS,
+        body: None,
+        response: Body::Envelope("StatsResponse"),
+        errors: &[],
+    },
+    Route {
+        method: Method::Post,
+        path: "/v2/submit",
+        name: "v2_submit",
+        summary: "Submit a signed transaction of the service",
+        params: &[],
+        body: Some(Body::Json("Transaction")),
+        response: Body::Envelope("TimestampResponse"),
+        errors: &[500],
+    },
+    Route {
+        method: Method::Post,
+        path: "/v2/submit_batch",
+        name: "v2_submit_batch",
+        summary: "Submit many transactions as a JSON array or newline-delimited JSON",
+        params: &[],
+        body: Some(Body::Json("Batch")),
+        response: Body::Envelope("BatchResponse"),
+        errors: &[413, 500],
+    },
+    Route {
+        method: Method::Post,
+        path: "/v2/prepare",
+        name: "v2_prepare",
+        summary: "Bytes of a timestamp transaction for an external signer",
+        params: &[],
+        body: Some(Body::Json("PrepareRequest")),
+        response: Body::Envelope("PrepareResponse"),
+        errors: &[],
+    },
+    Route {
+        method: Method::Post,
+        path: "/v2/submit_signed",
+        name: "v2_submit_signed",
+        summary: "Submit a timestamp signed by an external signer",
+        params: &[],
+        body: Some(Body::Json("SignedRequest")),
+        response: Body::Envelope("TimestampResponse"),
+        errors: &[500],
+    },
+];
+
+// Builds the document. Paths are relative to the mount point of the service API.
+pub fn spec() -> Value {
+    let mut paths = Map::new();
+    for route in ROUTES {
+        let path = paths
+            .entry(template(route.path))
+            .or_insert_with(|| Value::Object(Map::new()));
+        path.as_object_mut()
+            .unwrap()
+            .insert(route.method.as_str().to_owned(), operation(route));
+    }
+
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": "Timestamping service",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": "/api/services/timestamp" }],
+        "paths": paths,
+        "components": { "schemas": schemas() },
+    })
+}
+
+// `/v1/timestamp/:pub_key` becomes `/v1/timestamp/{pub_key}`
+pub fn template(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) => format!("{{{}}}", name),
+            None => segment.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn operation(route: &Route) -> Value {
+    let params: Vec<Value> = route
+        .params
+        .iter()
+        .map(|param| {
+            let in_path = route.path.split('/').any(|segment| segment == format!(":{}", param.name));
+            json!({
+                "name": param.name,
+                "in": if in_path { "path" } else { "query" },
+                "required": in_path,
+                "description": param.description,
+                "schema": param_schema(param.kind),
+            })
+        })
+        .collect();
+
+    let mut responses = Map::new();
+    responses.insert(
+        "200".into(),
+        json!({ "description": "Success", "content": content(route.response) }),
+    );
+    // v2 reports errors in envelopes as well
+    let error = match route.response {
+        Body::Envelope(_) => Body::Envelope("Error"),
+        _ => Body::Json("Error"),
+    };
+    // documents other than stored values have no binary encoding
+    let not_acceptable: &[u16] = match route.response {
+        Body::Negotiated(_) => &[406],
+        _ => &[],
+    };
+    for &status in [400].iter().chain(route.errors).chain(not_acceptable) {
+        responses.insert(
+            status.to_string(),
+            json!({ "description": error_description(status), "content": content(error) }),
+        );
+    }
+
+    let mut operation = json!({
+        "operationId": route.name,
+        "summary": route.summary,
+        "parameters": params,
+        "responses": responses,
+    });
+    if let Some(body) = route.body {
+        operation["requestBody"] = json!({ "required": true, "content": content(body) });
+    }
+    operation
+}
+
+fn param_schema(kind: Kind) -> Value {
+    match kind {
+        Kind::Integer => json!({ "type": "integer", "format": "int64", "minimum": 0 }),
+        Kind::Hex => schema_ref("Hash"),
+        Kind::Text => json!({ "type": "string" }),
+        Kind::OneOf(values) => json!({ "type": "string", "enum": values }),
+    }
+}
+
+fn content(body: Body) -> Value {
+    match body {
+        Body::Json(schema) => json!({ "application/json": { "schema": schema_ref(schema) } }),
+        Body::Negotiated(schema) => json!({
+            "application/json": { "schema": schema_ref(schema) },
+            "application/cbor": { "schema": schema_ref(schema) },
+        }),
+        Body::Stored(schema) => json!({
+            "application/json": { "schema": schema_ref(schema) },
+            "application/cbor": { "schema": schema_ref(schema) },
+            "application/octet-stream": {
+                "schema": { "type": "string", "format": "binary" },
+            },
+        }),
+        Body::Envelope("Error") => json!({ "application/json": { "schema": schema_ref("Envelope") } }),
+        Body::Envelope(schema) => json!({
+            "application/json": {
+                "schema": {
+                    "allOf": [
+                        schema_ref("Envelope"),
+                        { "properties": { "data": schema_ref(schema) } },
+                    ],
+                },
+            },
+        }),
+        Body::Raw(content_type) => {
+            let mut content = Map::new();
+            content.insert(content_type.to_owned(), json!({ "schema": { "type": "string" } }));
+            Value::Object(content)
+        }
+    }
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn error_description(status: u16) -> &'static str {
+    match status {
+        400 => "Invalid request",
+        404 => "Not found",
+        406 => "Binary encoding is not available",
+        413 => "Request body too large",
+        503 => "Too many open event streams",
+        _ => "The node cannot accept the transaction",
+    }
+}
+
+// Schemas of the JSON types. Exonum writes `u64` fields of stored structures and transactions as
+// decimal strings, see `Uint64`; other integers are JSON numbers.
+fn schemas() -> Value {
+    json!({
+        "Hash": {
+            "type": "string",
+            "pattern": "^[0-9a-f]{64}$",
+            "description": "32 bytes in hex: a SHA-256 hash or an Ed25519 public key",
+        },
+        "Signature": {
+            "type": "string",
+            "pattern": "^[0-9a-f]{128}$",
+            "description": "Ed25519 signature in hex",
+        },
+        "Uint64": {
+            "type": "string",
+            "pattern": "^[0-9]+$",
+            "description": "64-bit unsigned integer as a decimal string",
+        },
+        "Error": {
+            "type": "string",
+            "description": "Reason of the failure",
+        },
+        "Timestamp": {
+            "type": "object",
+            "required": ["pub_key", "content", "time"],
+            "properties": {
+                "pub_key": schema_ref("Hash"),
+                "content": schema_ref("Hash"),
+                "time": schema_ref("Uint64"),
+            },
+        },
+        "TimestampInfo": {
+            "type": "object",
+            "required": ["timestamp", "height", "tx_hash"],
+            "properties": {
+                "timestamp": schema_ref("Timestamp"),
+                "height": { "type": "integer", "format": "int64" },
+                "tx_hash": schema_ref("Hash"),
+                "owner": schema_ref("Hash"),
+            },
+        },
+        "TimestampsPage": {
+            "type": "object",
+            "required": ["timestamps", "next"],
+            "properties": {
+                "timestamps": {
+                    "type": "array",
+                    "items": schema_ref("Timestamp"),
+                    "maxItems": MAX_PAGE_SIZE,
+                },
+                "next": { "type": "string", "nullable": true },
+            },
+        },
+        "TimestampList": {
+            "description": "An array without a range, a page with one",
+            "oneOf": [
+                { "type": "array", "items": schema_ref("Timestamp") },
+                schema_ref("TimestampsPage"),
+            ],
+        },
+        "TimestampResponse": {
+            "type": "object",
+            "required": ["tx_hash"],
+            "properties": { "tx_hash": schema_ref("Hash") },
+        },
+        "Transaction": {
+            "type": "object",
+            "description": "A signed message; `message_id` is 0 for TxTimestamp, 1 for TxReveal, \
+                            2 for TxRelayed, 3 for TxAnchor, 4 for TxSetQuota and 5 for TxOwned",
+            "required": ["body", "protocol_version", "service_id", "message_id", "signature"],
+            "properties": {
+                "body": { "type": "object" },
+                "protocol_version": { "type": "integer" },
+                "service_id": { "type": "integer", "enum": [SERVICE_ID] },
+                "message_id": { "type": "integer", "minimum": 0, "maximum": 5 },
+                "signature": schema_ref("Signature"),
+            },
+        },
+        "Batch": {
+            "type": "array",
+            "description": "Also accepted as newline-delimited JSON with a transaction per line",
+            "items": schema_ref("Transaction"),
+            "maxItems": MAX_BATCH_SIZE,
+        },
+        "BatchItemResponse": {
+            "type": "object",
+            "description": "Either the hash of an accepted transaction or the reason it was rejected",
+            "properties": {
+                "tx_hash": schema_ref("Hash"),
+                "error": { "type": "string" },
+            },
+        },
+        "BatchResponse": {
+            "type": "array",
+            "items": schema_ref("BatchItemResponse"),
+        },
+        "BlockStats": {
+            "type": "string",
+            "description": "Human-readable description of the block",
+        },
+        "HashResponse": {
+            "type": "object",
+            "required": ["hash", "size"],
+            "properties": {
+                "hash": schema_ref("Hash"),
+                "size": { "type": "integer", "format": "int64" },
+            },
+        },
+        "PrepareRequest": {
+            "type": "object",
+            "required": ["from", "content", "time"],
+            "properties": {
+                "from": schema_ref("Hash"),
+                "content": schema_ref("Hash"),
+                "time": {
+                    "type": "integer",
+                    "format": "int64",
+                    "description": format!("UNIX time, within {} seconds of the node's clock", MAX_TIME_DRIFT),
+                },
+            },
+        },
+        "PrepareResponse": {
+            "type": "object",
+            "required": ["message", "template"],
+            "properties": {
+                "message": {
+                    "type": "string",
+                    "description": "Hex of the bytes to sign",
+                },
+                "template": {
+                    "type": "object",
+                    "description": "The transaction without a signature",
+                },
+            },
+        },
+        "SignedRequest": {
+            "type": "object",
+            "required": ["from", "content", "time", "signature"],
+            "properties": {
+                "from": schema_ref("Hash"),
+                "content": schema_ref("Hash"),
+                "time": { "type": "integer", "format": "int64" },
+                "signature": schema_ref("Signature"),
+            },
+        },
+        "TransactionStatus": {
+            "type": "object",
+            "required": ["committed", "height"],
+            "properties": {
+                "committed": { "type": "boolean" },
+                "height": { "type": "integer", "format": "int64", "nullable": true },
+            },
+        },
+        "Reveal": {
+            "type": "object",
+            "required": ["pub_key", "document", "salt", "tx_hash", "height"],
+            "properties": {
+                "pub_key": schema_ref("Hash"),
+                "document": schema_ref("Hash"),
+                "salt": { "type": "string", "description": "Salt of the commitment in hex" },
+                "tx_hash": schema_ref("Hash"),
+                "height": schema_ref("Uint64"),
+            },
+        },
+        "Relayer": {
+            "type": "object",
+            "required": ["pub_key", "quota", "used"],
+            "properties": {
+                "pub_key": schema_ref("Hash"),
+                "quota": schema_ref("Uint64"),
+                "used": schema_ref("Uint64"),
+            },
+        },
+        "Anchor": {
+            "type": "object",
+            "required": ["height", "block_hash", "reference", "tx_hash"],
+            "properties": {
+                "height": schema_ref("Uint64"),
+                "block_hash": schema_ref("Hash"),
+                "reference": { "type": "string" },
+                "tx_hash": schema_ref("Hash"),
+            },
+        },
+        "AnchorProof": {
+            "type": "object",
+            "description": "The transaction in its block and headers linked by `prev_hash` up to the anchored block",
+            "required": ["tx_hash", "tx_proof", "blocks", "anchor"],
+            "properties": {
+                "tx_hash": schema_ref("Hash"),
+                "tx_proof": { "type": "object", "description": "Merkle proof in the transactions of the first block" },
+                "blocks": { "type": "array", "items": { "type": "object" } },
+                "anchor": schema_ref("Anchor"),
+            },
+        },
+        "AnchorList": {
+            "type": "array",
+            "items": schema_ref("Anchor"),
+        },
+        "StatsBucket": {
+            "type": "object",
+            "required": ["start", "timestamps", "submitters"],
+            "properties": {
+                "start": { "type": "integer", "format": "int64" },
+                "timestamps": { "type": "integer", "format": "int64" },
+                "submitters": { "type": "integer", "format": "int64" },
+            },
+        },
+        "StatsResponse": {
+            "type": "object",
+            "required": ["buckets", "total_timestamps", "total_submitters"],
+            "properties": {
+                "buckets": { "type": "array", "items": schema_ref("StatsBucket") },
+                "total_timestamps": { "type": "integer", "format": "int64" },
+                "total_submitters": { "type": "integer", "format": "int64" },
+            },
+        },
+        "TimestampArray": {
+            "type": "array",
+            "items": schema_ref("Timestamp"),
+        },
+        "BlockInfo": {
+            "type": "object",
+            "required": ["block", "precommits", "txs"],
+            "properties": {
+                "block": { "type": "object" },
+                "precommits": { "type": "array", "items": { "type": "object" } },
+                "txs": { "type": "array", "items": schema_ref("Hash") },
+            },
+        },
+        "Envelope": {
+            "type": "object",
+            "description": "Response of v2; either `data` or `error` is set",
+            "required": ["data", "error", "meta"],
+            "properties": {
+                "data": { "nullable": true },
+                "error": {
+                    "type": "object",
+                    "nullable": true,
+                    "required": ["code", "message"],
+                    "properties": {
+                        "code": { "type": "string", "enum": ["bad_request", "not_found", "payload_too_large", "internal"] },
+                        "message": { "type": "string" },
+                    },
+                },
+                "meta": {
+                    "type": "object",
+                    "required": ["height"],
+                    "properties": {
+                        "height": {
+                            "type": "integer",
+                            "format": "int64",
+                            "description": "Height of the latest block the data was read at",
+                        },
+                        "pagination": {
+                            "type": "object",
+                            "required": ["limit", "next"],
+                            "properties": {
+                                "limit": { "type": "integer" },
+                                "next": { "type": "string", "nullable": true },
+                            },
+                        },
+                    },
+                },
+            },
+        },
+        "OpenApi": {
+            "type": "object",
+            "description": "An OpenAPI 3 document",
+        },
+    })
+}
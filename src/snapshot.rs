@@ -0,0 +1,424 @@
+// Portable snapshots of the service state for disaster recovery and analytics.
+//
+// A snapshot holds the content of every table of `TimestampSchema::state_hash` at the latest
+// block, the block with precommits of the validators and the entries of the table the block's
+// state hash is the root of, which map every table of the blockchain to its root. `verify`
+// recomputes the roots from the content and the state hash from the entries, so any change to a
+// snapshot is detected: timestamps with their commit records, submitters and owners, reveals,
+// relayers and anchors are all tables of the state hash. Only the order of timestamps, which
+// rebuilds the commit history, is checked for consistency alone.
+//
+// Nodes keep only the latest state, so a snapshot at an earlier height exists only if it was taken
+// on commit of that block: with a `SnapshotSchedule` a node saves one every `interval` blocks.
+//
+// A verified snapshot can seed a fresh network through the genesis block, see
+// `TimestampService::with_snapshot` and `timestamping-node run --restore-snapshot`. Every
+// validator of the new network has to restore the same snapshot. Heights and transaction hashes
+// keep referring to the original network.
+
+use exonum::blockchain::{Blockchain, BlockProof, Schema, ServiceContext};
+use exonum::crypto::{CryptoHash, Hash, PublicKey};
+use exonum::encoding::serialize::FromHex;
+use exonum::helpers::fabric::{Argument, Context};
+use exonum::messages::Message;
+use exonum::storage::{Database, MemoryDB, ProofListIndex, ProofMapIndex, Snapshot};
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::{Anchor, Relayer, Reveal, Timestamp, TimestampRecord, TimestampSchema, SERVICE_ID};
+
+// version of the snapshot format
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+// arguments of `timestamping-node run`, see `snapshot_args`
+pub const SNAPSHOT_DIR: &str = "SNAPSHOT_DIR";
+pub const SNAPSHOT_INTERVAL: &str = "SNAPSHOT_INTERVAL";
+pub const RESTORE_SNAPSHOT: &str = "RESTORE_SNAPSHOT";
+pub const SNAPSHOT_VALIDATORS: &str = "SNAPSHOT_VALIDATORS";
+
+// snapshot schedule used when only a directory is given
+pub const DEFAULT_SNAPSHOT_INTERVAL: u64 = 10_000;
+
+// tables of `TimestampSchema::state_hash` in their order
+const TIMESTAMPS_TABLE: usize = 0;
+const REVEALS_TABLE: usize = 1;
+const RELAYERS_TABLE: usize = 2;
+const ANCHORS_TABLE: usize = 3;
+const RECORDS_TABLE: usize = 4;
+const SUBMITTERS_TABLE: usize = 5;
+const OWNERS_TABLE: usize = 6;
+const TABLES: usize = 7;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Version(u32),
+    // the block is not signed by a majority of the given validators
+    Precommits(String),
+    // the table roots do not lead to the block's state hash
+    Proof(String),
+    // the content does not match the proven roots or is inconsistent
+    Content(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SnapshotError::Version(version) => write!(f, "Unsupported snapshot version {}", version),
+            SnapshotError::Precommits(ref e) => write!(f, "Invalid precommits: {}", e),
+            SnapshotError::Proof(ref e) => write!(f, "Invalid proof: {}", e),
+            SnapshotError::Content(ref e) => write!(f, "Invalid content: {}", e),
+        }
+    }
+}
+
+impl Error for SnapshotError {
+    fn description(&self) -> &str {
+        match *self {
+            SnapshotError::Version(_) => "unsupported snapshot version",
+            SnapshotError::Precommits(_) => "invalid precommits",
+            SnapshotError::Proof(_) => "invalid proof",
+            SnapshotError::Content(_) => "invalid content",
+        }
+    }
+}
+
+// A timestamp with its commit record and the key which sent it, in the order of the commit history
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub timestamp: Timestamp,
+    pub record: TimestampRecord,
+    pub submitter: PublicKey,
+    // the owner of a document key timestamped with TxOwned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<PublicKey>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ServiceSnapshot {
+    pub version: u32,
+    pub block_proof: BlockProof,
+    // roots of all tables of the blockchain by their keys, see `Blockchain::service_table_unique_key`
+    pub state_tables: Vec<(Hash, Hash)>,
+    // consensus keys of the validators at the time of the snapshot; `verify` has to be given keys
+    // from a trusted source, these only help to find them
+    pub validators: Vec<PublicKey>,
+    pub timestamps: Vec<SnapshotEntry>,
+    pub reveals: Vec<Reveal>,
+    pub relayers: Vec<Relayer>,
+    pub anchors: Vec<Anchor>,
+}
+
+impl ServiceSnapshot {
+    // takes a snapshot of the state after the latest block
+    pub fn take(snapshot: &Snapshot) -> Self {
+        let core = Schema::new(snapshot);
+        let schema = TimestampSchema::new(snapshot);
+        let height = core.height();
+
+        let records = schema.records();
+        let timestamps = schema
+            .history()
+            .iter()
+            .map(|pub_key| {
+                SnapshotEntry {
+                    record: records.get(&pub_key).unwrap(),
+                    submitter: schema.submitter(&pub_key).unwrap(),
+                    owner: schema.owner(&pub_key),
+                    timestamp: schema.timestamp(&pub_key).unwrap(),
+                }
+            })
+            .collect();
+
+        ServiceSnapshot {
+            version: SNAPSHOT_VERSION,
+            block_proof: core.block_and_precommits(height).unwrap(),
+            state_tables: core.state_hash_aggregator().iter().collect(),
+            validators: core.actual_configuration()
+                .validator_keys
+                .iter()
+                .map(|keys| keys.consensus_key)
+                .collect(),
+            timestamps,
+            reveals: schema.reveals().values().collect(),
+            relayers: schema.relayers().values().collect(),
+            anchors: schema.anchors().iter().collect(),
+        }
+    }
+
+    pub fn height(&self) -> u64 {
+        self.block_proof.block.height().0
+    }
+
+    // checks the snapshot against consensus keys of the validators of the original network
+    pub fn verify(&self, validators: &[PublicKey]) -> Result<(), SnapshotError> {
+        if self.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::Version(self.version));
+        }
+        self.verify_precommits(validators)?;
+
+        if self.state_root() != *self.block_proof.block.state_hash() {
+            return Err(SnapshotError::Proof("table roots do not lead to the state hash".into()));
+        }
+        for (table, root) in self.content_roots().into_iter().enumerate() {
+            let key = Blockchain::service_table_unique_key(SERVICE_ID, table);
+            let proven = self.state_tables.iter().find(|&&(k, _)| k == key).map(|&(_, v)| v);
+            if proven != Some(root) {
+                return Err(SnapshotError::Content(format!("table {} does not match its root", table)));
+            }
+        }
+        self.verify_records()
+    }
+
+    fn verify_precommits(&self, validators: &[PublicKey]) -> Result<(), SnapshotError> {
+        let block = &self.block_proof.block;
+        let block_hash = block.hash();
+        let mut signed = HashSet::new();
+        for precommit in &self.block_proof.precommits {
+            let validator = validators
+                .get(precommit.validator().0 as usize)
+                .ok_or_else(|| SnapshotError::Precommits("unknown validator".into()))?;
+            if precommit.block_hash() != &block_hash || precommit.height() != block.height() {
+                return Err(SnapshotError::Precommits("precommit of another block".into()));
+            }
+            if !precommit.verify_signature(validator) {
+                return Err(SnapshotError::Precommits("invalid signature".into()));
+            }
+            signed.insert(precommit.validator().0);
+        }
+        // Byzantine majority as in the consensus
+        if signed.len() * 3 <= validators.len() * 2 {
+            return Err(SnapshotError::Precommits(format!(
+                "{} of {} validators signed",
+                signed.len(),
+                validators.len()
+            )));
+        }
+        Ok(())
+    }
+
+    // the state hash rebuilt from the table roots
+    fn state_root(&self) -> Hash {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        let mut index = ProofMapIndex::new("state_tables", &mut fork);
+        for &(key, root) in &self.state_tables {
+            index.put(&key, root);
+        }
+        index.root_hash()
+    }
+
+    // roots of the tables rebuilt from the content
+    fn content_roots(&self) -> Vec<Hash> {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        let mut roots = vec![Hash::zero(); TABLES];
+        {
+            let mut index = ProofMapIndex::new("timestamps", &mut fork);
+            for entry in &self.timestamps {
+                index.put(entry.timestamp.pub_key(), entry.timestamp.clone());
+            }
+            roots[TIMESTAMPS_TABLE] = index.root_hash();
+        }
+        {
+            let mut index = ProofMapIndex::new("reveals", &mut fork);
+            for reveal in &self.reveals {
+                index.put(reveal.pub_key(), reveal.clone());
+            }
+            roots[REVEALS_TABLE] = index.root_hash();
+        }
+        {
+            let mut index = ProofMapIndex::new("relayers", &mut fork);
+            for relayer in &self.relayers {
+                index.put(relayer.pub_key(), relayer.clone());
+            }
+            roots[RELAYERS_TABLE] = index.root_hash();
+        }
+        {
+            let mut index = ProofListIndex::new("anchors", &mut fork);
+            index.extend(self.anchors.iter().cloned());
+            roots[ANCHORS_TABLE] = index.root_hash();
+        }
+        {
+            let mut index = ProofMapIndex::new("records", &mut fork);
+            for entry in &self.timestamps {
+                index.put(entry.timestamp.pub_key(), entry.record.clone());
+            }
+            roots[RECORDS_TABLE] = index.root_hash();
+        }
+        {
+            let mut index = ProofMapIndex::new("submitters", &mut fork);
+            for entry in &self.timestamps {
+                index.put(entry.timestamp.pub_key(), entry.submitter);
+            }
+            roots[SUBMITTERS_TABLE] = index.root_hash();
+        }
+        {
+            let mut index = ProofMapIndex::new("owners", &mut fork);
+            for entry in &self.timestamps {
+                if let Some(owner) = entry.owner {
+                    index.put(entry.timestamp.pub_key(), owner);
+                }
+            }
+            roots[OWNERS_TABLE] = index.root_hash();
+        }
+        roots
+    }
+
+    // the order of timestamps is not proven, but has to be the order of their proven records
+    fn verify_records(&self) -> Result<(), SnapshotError> {
+        let mut keys = HashSet::new();
+        let mut last_height = 0;
+        for entry in &self.timestamps {
+            if !keys.insert(*entry.timestamp.pub_key()) {
+                return Err(SnapshotError::Content("duplicate timestamp".into()));
+            }
+            let height = entry.record.height();
+            if height < last_height || height > self.height() {
+                return Err(SnapshotError::Content("records out of order".into()));
+            }
+            last_height = height;
+        }
+        let revealed_keys = self.reveals.iter().all(|reveal| keys.contains(reveal.pub_key()));
+        if !revealed_keys {
+            return Err(SnapshotError::Content("reveal of a missing timestamp".into()));
+        }
+        Ok(())
+    }
+}
+
+// Snapshots saved on commit of every `interval`-th block as `snapshot_<height>.json` in a
+// directory. A snapshot holds the whole state, so taking it delays the node like a slow
+// `AnchorSink` does.
+pub struct SnapshotSchedule {
+    interval: u64,
+    dir: PathBuf,
+}
+
+impl SnapshotSchedule {
+    pub fn new<P: AsRef<Path>>(interval: u64, dir: P) -> Self {
+        assert!(interval > 0, "Snapshot interval must be positive");
+        SnapshotSchedule {
+            interval,
+            dir: dir.as_ref().to_owned(),
+        }
+    }
+
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    // saves a snapshot of the committed block if it is due
+    pub fn handle_commit(&self, ctx: &ServiceContext) {
+        let height = Schema::new(ctx.snapshot()).height().0;
+        if height == 0 || height % self.interval != 0 {
+            return;
+        }
+        // the next scheduled block is saved anyway, so a failure only widens the gap
+        if let Err(e) = self.save(&ServiceSnapshot::take(ctx.snapshot())) {
+            error!("Failed to save the snapshot of block {}: {}", height, e);
+        }
+    }
+
+    // the snapshot saved at `height`, if any
+    pub fn load(&self, height: u64) -> io::Result<Option<ServiceSnapshot>> {
+        let file = match File::open(self.path(height)) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        ::serde_json::from_reader(BufReader::new(file))
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    // written next to its place and renamed, so a crash never leaves a partial snapshot
+    fn save(&self, snapshot: &ServiceSnapshot) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(snapshot.height());
+        let partial = path.with_extension("part");
+        {
+            let mut file = BufWriter::new(File::create(&partial)?);
+            ::serde_json::to_writer(&mut file, snapshot)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            file.flush()?;
+            file.get_ref().sync_all()?;
+        }
+        fs::rename(partial, path)
+    }
+
+    fn path(&self, height: u64) -> PathBuf {
+        self.dir.join(format!("snapshot_{}.json", height))
+    }
+
+    // the schedule given to `timestamping-node run`, if the node saves snapshots
+    pub fn from_context(context: &Context) -> Option<Self> {
+        let dir = context.arg::<String>(SNAPSHOT_DIR).ok()?;
+        let interval = context.arg::<u64>(SNAPSHOT_INTERVAL).unwrap_or(DEFAULT_SNAPSHOT_INTERVAL);
+        Some(SnapshotSchedule::new(interval, dir))
+    }
+}
+
+// Reads and verifies the snapshot given to `timestamping-node run --restore-snapshot`. It is
+// checked against the consensus keys of the original validators given with
+// `--snapshot-validators`, never against the keys the snapshot lists itself.
+pub fn restored(context: &Context) -> Result<Option<ServiceSnapshot>, String> {
+    let path = match context.arg::<String>(RESTORE_SNAPSHOT) {
+        Ok(path) => path,
+        Err(_) => return Ok(None),
+    };
+    let validators = context
+        .arg_multiple::<String>(SNAPSHOT_VALIDATORS)
+        .map_err(|_| "`--snapshot-validators` are required to restore a snapshot".to_owned())?
+        .iter()
+        .map(|key| PublicKey::from_hex(key).map_err(|_| format!("Invalid validator key `{}`", key)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let file = File::open(&path).map_err(|e| format!("{}: {}", path, e))?;
+    let snapshot: ServiceSnapshot = ::serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("{}: {}", path, e))?;
+    snapshot.verify(&validators).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(Some(snapshot))
+}
+
+// arguments of `timestamping-node run` for saving and restoring snapshots
+pub fn snapshot_args() -> Vec<Argument> {
+    vec![
+        Argument::new_named(
+            SNAPSHOT_DIR,
+            false,
+            "Saves snapshots of the state to this directory.",
+            None,
+            "snapshot-dir",
+            false,
+        ),
+        Argument::new_named(
+            SNAPSHOT_INTERVAL,
+            false,
+            "Saves a snapshot every N-th block, 10000 by default.",
+            None,
+            "snapshot-interval",
+            false,
+        ),
+        Argument::new_named(
+            RESTORE_SNAPSHOT,
+            false,
+            "Seeds the genesis block with this snapshot on the first run.",
+            None,
+            "restore-snapshot",
+            false,
+        ),
+        Argument::new_named(
+            SNAPSHOT_VALIDATORS,
+            false,
+            "Consensus keys of the validators which signed the restored snapshot.",
+            None,
+            "snapshot-validators",
+            true,
+        ),
+    ]
+}
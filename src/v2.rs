@@ -0,0 +1,373 @@
+// Version 2 of the public REST API, served next to v1 under `/v2`.
+//
+// Every response is an `Envelope`: `data` on success, `error` on failure, and `meta` with the
+// height of the latest block the data was read at and, for lists, the pagination. Failures have
+// proper HTTP statuses and lists are always paged. Streams and uploads (`/v1/events`,
+// `/v1/export`, `/v1/hash`) are not JSON documents and stay in v1 only.
+
+use bodyparser;
+
+use exonum::api::ApiError;
+use exonum::blockchain::Schema;
+use exonum::crypto::{Hash, PublicKey};
+use exonum::encoding::serialize::FromHex;
+use exonum::explorer::BlockInfo;
+use exonum::helpers::Height;
+use exonum::storage::Snapshot;
+
+use iron::headers::ContentType;
+use iron::modifiers::Header;
+use iron::prelude::*;
+use iron::status::Status;
+use iron::Handler;
+
+use serde::Serialize;
+use serde_json::{self, Value};
+
+use super::metrics::{Metrics, Timed};
+use super::{page_limit, query_param, read_batch_body, read_stats, Anchor, PrepareRequest, PrepareResponse,
+            SignedRequest, TimestampApi, TimestampRange, TimestampResponse, TimestampSchema,
+            TimestampServiceTransactions, TransactionStatus, TxTimestamp, MAX_BATCH_BODY_SIZE};
+
+#[derive(Serialize, Deserialize)]
+pub struct ErrorBody {
+    // `bad_request`, `not_found`, `payload_too_large` or `internal`
+    pub code: String,
+    pub message: String,
+}
+
+// A list is continued by repeating the request with `cursor` set to `next`
+#[derive(Serialize, Deserialize)]
+pub struct Pagination {
+    pub limit: usize,
+    pub next: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Meta {
+    pub height: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<Pagination>,
+}
+
+// Body of every v2 response; exactly one of `data` and `error` is set
+#[derive(Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub data: Option<T>,
+    pub error: Option<ErrorBody>,
+    pub meta: Meta,
+}
+
+// What a handler answers with on success
+struct Reply {
+    data: Value,
+    pagination: Option<Pagination>,
+}
+
+impl Reply {
+    fn new<T: Serialize>(data: T) -> Self {
+        Reply {
+            data: serde_json::to_value(data).unwrap(),
+            pagination: None,
+        }
+    }
+
+    fn paged<T: Serialize>(data: T, pagination: Pagination) -> Self {
+        Reply {
+            pagination: Some(pagination),
+            ..Reply::new(data)
+        }
+    }
+}
+
+// What a handler answers with on failure
+struct Failure {
+    status: Status,
+    code: &'static str,
+    message: String,
+}
+
+impl Failure {
+    fn bad_request<S: Into<String>>(message: S) -> Self {
+        Failure {
+            status: Status::BadRequest,
+            code: "bad_request",
+            message: message.into(),
+        }
+    }
+
+    fn not_found<S: Into<String>>(message: S) -> Self {
+        Failure {
+            status: Status::NotFound,
+            code: "not_found",
+            message: message.into(),
+        }
+    }
+
+    fn payload_too_large(limit: u64) -> Self {
+        Failure {
+            status: Status::PayloadTooLarge,
+            code: "payload_too_large",
+            message: format!("Body exceeds {} bytes", limit),
+        }
+    }
+}
+
+// errors of the helpers shared with v1
+impl From<ApiError> for Failure {
+    fn from(e: ApiError) -> Self {
+        match e {
+            ApiError::BadRequest(message) => Failure::bad_request(message),
+            e => Failure {
+                status: Status::InternalServerError,
+                code: "internal",
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+// A v2 handler; it reads everything from the given snapshot, the one `meta` is taken from
+type Action = fn(&TimestampApi, &mut Request, &Snapshot) -> Result<Reply, Failure>;
+
+// A route of v2: an action wrapped into an envelope
+struct Endpoint {
+    api: TimestampApi,
+    action: Action,
+}
+
+impl Handler for Endpoint {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let snapshot = self.api.blockchain.snapshot();
+        let meta = |pagination| Meta {
+            height: Schema::new(&snapshot).height().0,
+            pagination,
+        };
+        let (status, envelope) = match (self.action)(&self.api, req, &*snapshot) {
+            Ok(reply) => (
+                Status::Ok,
+                Envelope {
+                    data: Some(reply.data),
+                    error: None,
+                    meta: meta(reply.pagination),
+                },
+            ),
+            Err(failure) => (
+                failure.status,
+                Envelope {
+                    data: None,
+                    error: Some(ErrorBody {
+                        code: failure.code.into(),
+                        message: failure.message,
+                    }),
+                    meta: meta(None),
+                },
+            ),
+        };
+        Ok(Response::with((
+            status,
+            Header(ContentType::json()),
+            serde_json::to_string_pretty(&envelope).unwrap(),
+        )))
+    }
+}
+
+impl TimestampApi {
+    // The handler of a v2 route of `openapi::ROUTES` by its name, which is the name of its
+    // action as well.
+    pub fn handler_v2(&self, name: &'static str) -> Option<Box<Handler>> {
+        let action: Action = match name {
+            "v2_timestamps" => TimestampApi::v2_timestamps,
+            "v2_timestamp" => TimestampApi::v2_timestamp,
+            "v2_content" => TimestampApi::v2_content,
+            "v2_block" => TimestampApi::v2_block,
+            "v2_transaction" => TimestampApi::v2_transaction,
+            "v2_reveal" => TimestampApi::v2_reveal,
+            "v2_revealed" => TimestampApi::v2_revealed,
+            "v2_relayer" => TimestampApi::v2_relayer,
+            "v2_anchors" => TimestampApi::v2_anchors,
+            "v2_anchor" => TimestampApi::v2_anchor,
+            "v2_stats" => TimestampApi::v2_stats,
+            "v2_submit" => TimestampApi::v2_submit,
+            "v2_submit_batch" => TimestampApi::v2_submit_batch,
+            "v2_prepare" => TimestampApi::v2_prepare,
+            "v2_submit_signed" => TimestampApi::v2_submit_signed,
+            _ => return None,
+        };
+        Some(Box::new(self.v2(name, action)))
+    }
+
+    fn v2(&self, route: &'static str, action: Action) -> Timed<Endpoint> {
+        let endpoint = Endpoint {
+            api: self.clone(),
+            action,
+        };
+        Metrics::timed(&self.metrics, route, endpoint)
+    }
+
+    // Timestamps in the order of commit, or of a time or a block range as in `/v1/timestamps`
+    fn v2_timestamps(&self, req: &mut Request, snapshot: &Snapshot) -> Result<Reply, Failure> {
+        let range = TimestampRange::from_request(req)?;
+        let limit = page_limit(req)?;
+        let cursor = query_param::<String>(req, "cursor")?;
+        let page = range.page(&TimestampSchema::new(snapshot), cursor, limit)?;
+        Ok(Reply::paged(page.timestamps, Pagination { limit, next: page.next }))
+    }
+
+    fn v2_timestamp(&self, req: &mut Request, snapshot: &Snapshot) -> Result<Reply, Failure> {
+        let pub_key: PublicKey = hex_param(req, "pub_key")?;
+        TimestampSchema::new(snapshot)
+            .timestamp(&pub_key)
+            .map(Reply::new)
+            .ok_or_else(|| Failure::not_found("Timestamp not found"))
+    }
+
+    fn v2_content(&self, req: &mut Request, snapshot: &Snapshot) -> Result<Reply, Failure> {
+        let content: Hash = hex_param(req, "hash")?;
+        TimestampSchema::new(snapshot)
+            .info_by_content(&content)
+            .map(Reply::new)
+            .ok_or_else(|| Failure::not_found("Content not found"))
+    }
+
+    // The block with its precommits and transaction hashes
+    fn v2_block(&self, req: &mut Request, snapshot: &Snapshot) -> Result<Reply, Failure> {
+        let height = Height(height_param(req)?);
+        let schema = Schema::new(snapshot);
+        schema
+            .block_and_precommits(height)
+            .map(|proof| {
+                Reply::new(BlockInfo {
+                    block: proof.block,
+                    precommits: proof.precommits,
+                    txs: schema.block_txs(height).iter().collect(),
+                })
+            })
+            .ok_or_else(|| Failure::not_found(format!("Block {} not found", height)))
+    }
+
+    // Commit status of a transaction; unknown transactions are not committed rather than not found
+    fn v2_transaction(&self, req: &mut Request, snapshot: &Snapshot) -> Result<Reply, Failure> {
+        let tx_hash: Hash = hex_param(req, "tx_hash")?;
+        let location = Schema::new(snapshot).tx_location_by_tx_hash().get(&tx_hash);
+        Ok(Reply::new(TransactionStatus {
+            committed: location.is_some(),
+            height: location.map(|location| location.block_height().0),
+        }))
+    }
+
+    fn v2_reveal(&self, req: &mut Request, snapshot: &Snapshot) -> Result<Reply, Failure> {
+        let pub_key: PublicKey = hex_param(req, "pub_key")?;
+        TimestampSchema::new(snapshot)
+            .reveal(&pub_key)
+            .map(Reply::new)
+            .ok_or_else(|| Failure::not_found("Reveal not found"))
+    }
+
+    fn v2_revealed(&self, req: &mut Request, snapshot: &Snapshot) -> Result<Reply, Failure> {
+        let document: Hash = hex_param(req, "hash")?;
+        let schema = TimestampSchema::new(snapshot);
+        schema
+            .revealed_documents()
+            .get(&document)
+            .and_then(|pub_key| schema.reveal(&pub_key))
+            .map(Reply::new)
+            .ok_or_else(|| Failure::not_found("Document not revealed"))
+    }
+
+    fn v2_relayer(&self, req: &mut Request, snapshot: &Snapshot) -> Result<Reply, Failure> {
+        let pub_key: PublicKey = hex_param(req, "pub_key")?;
+        TimestampSchema::new(snapshot)
+            .relayer(&pub_key)
+            .map(Reply::new)
+            .ok_or_else(|| Failure::not_found("Relayer not found"))
+    }
+
+    // Anchors ordered by height; `cursor` is a position in the list
+    fn v2_anchors(&self, req: &mut Request, snapshot: &Snapshot) -> Result<Reply, Failure> {
+        let limit = page_limit(req)?;
+        let cursor = query_param::<u64>(req, "cursor")?.unwrap_or(0);
+        let schema = TimestampSchema::new(snapshot);
+        let anchors = schema.anchors();
+        let page: Vec<Anchor> = anchors.iter_from(cursor).take(limit).collect();
+        let next = cursor + page.len() as u64;
+        let next = if next < anchors.len() { Some(next.to_string()) } else { None };
+        Ok(Reply::paged(page, Pagination { limit, next }))
+    }
+
+    // The first anchor of a block at the height or above
+    fn v2_anchor(&self, req: &mut Request, snapshot: &Snapshot) -> Result<Reply, Failure> {
+        let height = height_param(req)?;
+        TimestampSchema::new(snapshot)
+            .anchor_covering(height)
+            .map(Reply::new)
+            .ok_or_else(|| Failure::not_found("Not anchored yet"))
+    }
+
+    fn v2_stats(&self, req: &mut Request, snapshot: &Snapshot) -> Result<Reply, Failure> {
+        let stats = read_stats(req, &TimestampSchema::new(snapshot))?;
+        Ok(Reply::new(stats))
+    }
+
+    fn v2_submit(&self, req: &mut Request, _: &Snapshot) -> Result<Reply, Failure> {
+        let transaction = match req.get::<bodyparser::Struct<TimestampServiceTransactions>>() {
+            Ok(Some(transaction)) => transaction,
+            Ok(None) => {
+                self.metrics.rejected("malformed");
+                return Err(Failure::bad_request("Empty request"));
+            }
+            Err(e) => {
+                self.metrics.rejected("malformed");
+                return Err(Failure::bad_request(e.to_string()));
+            }
+        };
+        let tx_hash = self.send(transaction)?;
+        Ok(Reply::new(TimestampResponse { tx_hash }))
+    }
+
+    // A hash or an error for each transaction of a JSON array or newline-delimited JSON, as in
+    // `/v1/submit_batch`
+    fn v2_submit_batch(&self, req: &mut Request, _: &Snapshot) -> Result<Reply, Failure> {
+        let body = read_batch_body(req)?
+            .ok_or_else(|| Failure::payload_too_large(MAX_BATCH_BODY_SIZE))?;
+        Ok(Reply::new(self.submit_items(&body)?))
+    }
+
+    fn v2_prepare(&self, req: &mut Request, _: &Snapshot) -> Result<Reply, Failure> {
+        match req.get::<bodyparser::Struct<PrepareRequest>>() {
+            Ok(Some(request)) => Ok(Reply::new(PrepareResponse::new(&request))),
+            Ok(None) => Err(Failure::bad_request("Empty request")),
+            Err(e) => Err(Failure::bad_request(e.to_string())),
+        }
+    }
+
+    fn v2_submit_signed(&self, req: &mut Request, _: &Snapshot) -> Result<Reply, Failure> {
+        let request = match req.get::<bodyparser::Struct<SignedRequest>>() {
+            Ok(Some(request)) => request,
+            Ok(None) => return Err(Failure::bad_request("Empty request")),
+            Err(e) => return Err(Failure::bad_request(e.to_string())),
+        };
+        let transaction = TxTimestamp::new_with_signature(
+            &request.from,
+            &request.content,
+            request.time,
+            &request.signature,
+        );
+        let tx_hash = self.send(TimestampServiceTransactions::TxTimestamp(transaction))?;
+        Ok(Reply::new(TimestampResponse { tx_hash }))
+    }
+}
+
+// Reads the last path segment as a hash or a public key
+fn hex_param<T: FromHex>(req: &Request, name: &str) -> Result<T, Failure> {
+    let path = req.url.path();
+    T::from_hex(path.last().unwrap()).map_err(|_| Failure::bad_request(format!("Invalid request param: `{}`", name)))
+}
+
+fn height_param(req: &Request) -> Result<u64, Failure> {
+    let path = req.url.path();
+    path.last()
+        .unwrap()
+        .parse()
+        .map_err(|_| Failure::bad_request("Invalid request param: `height`"))
+}
@@ -4,29 +4,162 @@ extern crate exonum_testkit;
 
 extern crate timestamping;
 
+extern crate serde_cbor;
+#[macro_use]
 extern crate serde_json;
 
+extern crate iron;
+extern crate iron_test;
+
+extern crate tempdir;
+
 use exonum::blockchain::Schema;
 use exonum::crypto;
 
-use exonum::crypto::{CryptoHash, gen_keypair, PublicKey};
-use exonum_testkit::{ApiKind, TestKitBuilder};
+use exonum::blockchain::Transaction;
+use exonum::crypto::{CryptoHash, gen_keypair, Hash, PublicKey, Signature, SIGNATURE_LENGTH};
+use exonum::encoding::serialize::FromHex;
+use exonum::messages::Message;
+use exonum::storage::StorageValue;
+
+use exonum_testkit::{ApiKind, TestKitApi, TestKitBuilder};
+
+use iron::Headers;
+use iron::headers::ContentType;
+use iron::status::Status;
+use iron_test::{request, response};
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tempdir::TempDir;
+
+use timestamping::admin::{ConfigResponse, PendingTransactions, QuotaRequest, ServiceStats};
+use timestamping::anchoring::{AnchorError, AnchorPayload, AnchorProof, AnchorSink, FileSink, MockLedger};
+use timestamping::client::{self, Client, ClientError, RawResponse, Receipt, Transport};
+use timestamping::journal::{Backoff, Journal, JournalEntry, Pending, LOST_AFTER};
+use timestamping::export::{ExportFilter, ExportFormat, ExportRecord};
+use timestamping::keystore::{Keystore, KeystoreError};
+use timestamping::negotiation::{self, Encoding};
+use timestamping::openapi;
+use timestamping::snapshot::{ServiceSnapshot, SnapshotError};
+use timestamping::{Anchor, BatchItemResponse, Envelope, HashResponse, PrepareRequest, PrepareResponse, Relayer,
+                   Reveal, SignedRequest, StatsResponse, Timestamp, TimestampInfo, TimestampRecord, TimestampResponse,
+                   TimestampService, TimestampServiceTransactions, TimestampsPage, TransactionStatus, TxOwned,
+                   TxRelayed, TxReveal, TxTimestamp, commitment, unix_time};
+
+// a binary of the crate built next to the test binary in `target/<profile>`
+fn target_exe(name: &str) -> PathBuf {
+    let mut path = env::current_exe().unwrap();
+    path.pop();
+    if path.ends_with("deps") {
+        path.pop();
+    }
+    path.join(format!("{}{}", name, env::consts::EXE_SUFFIX))
+}
+
+fn node_exe() -> PathBuf {
+    target_exe("timestamping-node")
+}
+
+fn ts_exe() -> PathBuf {
+    target_exe("ts")
+}
+
+// Sends a raw JSON body to a service endpoint, bypassing JSON serialization of the testkit helpers
+fn post_raw(api: &TestKitApi, endpoint: &str, body: &str) -> (Status, String) {
+    let url = format!("http://localhost:3000/api/services/timestamp/{}", endpoint);
+    let mut headers = Headers::new();
+    headers.set(ContentType::json());
+    let resp = match request::post(&url, headers, body, api.public_handler()) {
+        Ok(resp) => resp,
+        Err(e) => e.response,
+    };
+    let status = resp.status.unwrap();
+    (status, response::extract_body_to_string(resp))
+}
+
+// Reads a service endpoint as a raw string
+fn get_raw(api: &TestKitApi, endpoint: &str) -> (Status, String) {
+    let url = format!("http://localhost:3000/api/services/timestamp/{}", endpoint);
+    let resp = match request::get(&url, Headers::new(), api.public_handler()) {
+        Ok(resp) => resp,
+        Err(e) => e.response,
+    };
+    let status = resp.status.unwrap();
+    (status, response::extract_body_to_string(resp))
+}
+
+// Reads a service endpoint in an encoding given by `Accept`
+fn get_encoded(api: &TestKitApi, endpoint: &str, accept: &str) -> (Status, Vec<u8>) {
+    let url = format!("http://localhost:3000/api/services/timestamp/{}", endpoint);
+    let mut headers = Headers::new();
+    headers.set_raw("Accept", vec![accept.as_bytes().to_vec()]);
+    let resp = match request::get(&url, headers, api.public_handler()) {
+        Ok(resp) => resp,
+        Err(e) => e.response,
+    };
+    let status = resp.status.unwrap();
+    (status, response::extract_body_to_bytes(resp))
+}
+
+// Parses `data` lines of a Server-Sent Events body
+fn parse_events(body: &str) -> Vec<TimestampInfo> {
+    body.lines()
+        .filter(|line| line.starts_with("data: "))
+        .map(|line| serde_json::from_str(&line["data: ".len()..]).unwrap())
+        .collect()
+}
+
+// Client transport serving requests with the testkit's API handlers instead of HTTP
+struct TestKitTransport {
+    api: TestKitApi,
+}
+
+impl TestKitTransport {
+    fn raw(response: iron::IronResult<iron::Response>) -> RawResponse {
+        let resp = match response {
+            Ok(resp) => resp,
+            Err(e) => e.response,
+        };
+        let status = resp.status.unwrap().to_u16();
+        RawResponse { status, body: response::extract_body_to_bytes(resp) }
+    }
+}
+
+impl Transport for TestKitTransport {
+    fn get(&self, path: &str) -> Result<RawResponse, ClientError> {
+        let url = format!("http://localhost:3000/{}", path);
+        Ok(TestKitTransport::raw(request::get(&url, Headers::new(), self.api.public_handler())))
+    }
 
-use timestamping::{TimestampService, TxTimestamp};
+    fn post(&self, path: &str, body: &[u8]) -> Result<RawResponse, ClientError> {
+        let url = format!("http://localhost:3000/{}", path);
+        let mut headers = Headers::new();
+        headers.set(ContentType::json());
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        Ok(TestKitTransport::raw(request::post(&url, headers, &body, self.api.public_handler())))
+    }
+}
 
 #[test]
 fn test_submit_basic() {
     // Create testkit for network with four validators.
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Create few transactions.
     let keypair = gen_keypair();
-    let tx1 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Down To Earth"), &keypair.1);
-    let tx2 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Cry Over Spilt Milk"), &keypair.1);
-    let tx3 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Dropping Like Flies"), &keypair.1);
+    let tx1 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Down To Earth"), unix_time(), &keypair.1);
+    let tx2 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Cry Over Spilt Milk"), unix_time(), &keypair.1);
+    let tx3 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Dropping Like Flies"), unix_time(), &keypair.1);
 
     // Commit them into blockchain.
     testkit.create_block_with_transactions(
@@ -42,17 +175,35 @@ fn test_submit_basic() {
     assert!(schema.transactions().contains(&tx3.hash()));
 }
 
+#[test]
+fn test_example_transaction() {
+    // `test.json` is the documented layout of TxTimestamp
+    let example = include_str!("../test.json");
+    let tx: TxTimestamp = serde_json::from_str(example).unwrap();
+    assert!(tx.verify());
+    assert_eq!(tx.time(), 1_530_000_000);
+    assert_eq!(
+        tx.content(),
+        &Hash::from_hex("806f312994db8a3e92c5c20f35c6675bdb0693997f643d3d45e92eef8a5474c0").unwrap()
+    );
+
+    // the layout before `time` is not accepted
+    let mut old: serde_json::Value = serde_json::from_str(example).unwrap();
+    old["body"].as_object_mut().unwrap().remove("time");
+    assert!(serde_json::from_value::<TxTimestamp>(old).is_err());
+}
+
 #[test]
 fn test_submit_rest() {
     // Create testkit for network with four validators
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Create few transactions.
     let keypair = gen_keypair();
-    let tx1 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Down To Earth"), &keypair.1);
+    let tx1 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Down To Earth"), unix_time(), &keypair.1);
 
     // Check results with api
     let api = testkit.api();
@@ -71,12 +222,12 @@ fn test_get_timestamp_by_hash_rest() {
     // Create testkit for network with four validators
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Create few transactions.
     let keypair = gen_keypair();
-    let tx1 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Down To Earth"), &keypair.1);
+    let tx1 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Down To Earth"), unix_time(), &keypair.1);
 
     // Check results with api
     let api = testkit.api();
@@ -98,14 +249,14 @@ fn test_submit_get_block_by_id() {
     // Create testkit for network with four validators
     let mut testkit = TestKitBuilder::validator()
         .with_validators(4)
-        .with_service(TimestampService)
+        .with_service(TimestampService::new())
         .create();
 
     // Create few transactions.
     let keypair = gen_keypair();
-    let tx1 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Down To Earth"), &keypair.1);
-    let tx2 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Cry Over Spilt Milk"), &keypair.1);
-    let tx3 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Dropping Like Flies"), &keypair.1);
+    let tx1 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Down To Earth"), unix_time(), &keypair.1);
+    let tx2 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Cry Over Spilt Milk"), unix_time(), &keypair.1);
+    let tx3 = TxTimestamp::new(&keypair.0, &crypto::hash(b"Dropping Like Flies"), unix_time(), &keypair.1);
 
     // Check results with api
     let api = testkit.api();
@@ -122,4 +273,2126 @@ fn test_submit_get_block_by_id() {
     assert!(!res.as_str().unwrap().contains("not found"));
 }
 
+#[test]
+fn test_hash_upload_rest() {
+    let testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(TimestampService::new())
+        .create();
+
+    let api = testkit.api();
+
+    let (status, body) = post_raw(&api, "v1/hash", "Down To Earth");
+    let res: HashResponse = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(status, Status::Ok);
+    assert_eq!(res.hash, crypto::hash(b"Down To Earth"));
+    assert_eq!(res.size, 13);
+}
+
+#[test]
+fn test_hash_upload_too_large_rest() {
+    let testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(TimestampService::new().with_max_hash_size(4))
+        .create();
+
+    let api = testkit.api();
+
+    let (status, _) = post_raw(&api, "v1/hash", "Down To Earth");
+
+    assert_eq!(status, Status::PayloadTooLarge);
+}
+
+#[test]
+fn test_prepare_and_submit_signed_rest() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(TimestampService::new())
+        .create();
+
+    let api = testkit.api();
+
+    let keypair = gen_keypair();
+    let content = crypto::hash(b"Down To Earth");
+    let time = unix_time();
+    let prepare = PrepareRequest { from: keypair.0, content, time };
+
+    let prepared = api.post::<PrepareRequest, PrepareResponse>(
+        ApiKind::Service("timestamp"), "v1/prepare", &prepare);
+
+    // The external signer signs exactly the bytes it was given
+    let message = Vec::<u8>::from_hex(&prepared.message).unwrap();
+    let signature: Signature = crypto::sign(&message, &keypair.1);
+
+    let expected = TxTimestamp::new(&keypair.0, &content, time, &keypair.1);
+    assert_eq!(&signature, expected.raw().signature());
+    assert_eq!(prepared.template["service_id"], 13);
+    assert!(prepared.template.get("signature").is_none());
+
+    let signed = SignedRequest { from: keypair.0, content, time, signature };
+    let res = api.post::<SignedRequest, TimestampResponse>(
+        ApiKind::Service("timestamp"), "v1/submit_signed", &signed);
+    assert_eq!(res.tx_hash, expected.hash());
+
+    testkit.create_block();
+
+    let pk = PublicKey::to_hex(&keypair.0);
+    let res = api.get::<serde_json::Value>(ApiKind::Service("timestamp"), &format!("v1/timestamp/{}", &pk));
+
+    assert_eq!(&res.as_object().unwrap()["pub_key"], &pk);
+}
+
+#[test]
+fn test_submit_batch_rest() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(TimestampService::new())
+        .create();
+
+    let api = testkit.api();
+
+    let keypair1 = gen_keypair();
+    let keypair2 = gen_keypair();
+    let tx1 = TxTimestamp::new(&keypair1.0, &crypto::hash(b"Down To Earth"), unix_time(), &keypair1.1);
+    // Signed with a key other than `from`
+    let tx2 = TxTimestamp::new(&keypair2.0, &crypto::hash(b"Cry Over Spilt Milk"), unix_time(), &keypair1.1);
+
+    let batch = vec![
+        serde_json::to_value(&tx1).unwrap(),
+        serde_json::to_value(&tx2).unwrap(),
+        json!({ "foo": 1 }),
+    ];
+    let res = api.post::<Vec<serde_json::Value>, Vec<BatchItemResponse>>(
+        ApiKind::Service("timestamp"), "v1/submit_batch", &batch);
+
+    assert_eq!(res.len(), 3);
+    assert_eq!(res[0].tx_hash, Some(tx1.hash()));
+    assert!(res[1].tx_hash.is_none() && res[1].error.is_some());
+    assert!(res[2].tx_hash.is_none() && res[2].error.is_some());
+
+    testkit.create_block();
+
+    let res = api.get::<serde_json::Value>(ApiKind::Service("timestamp"), "v1/timestamps");
+    assert_eq!(res.as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_submit_batch_ndjson_rest() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(TimestampService::new())
+        .create();
+
+    let api = testkit.api();
+
+    let keypair1 = gen_keypair();
+    let keypair2 = gen_keypair();
+    let tx1 = TxTimestamp::new(&keypair1.0, &crypto::hash(b"Down To Earth"), unix_time(), &keypair1.1);
+    let tx2 = TxTimestamp::new(&keypair2.0, &crypto::hash(b"Cry Over Spilt Milk"), unix_time(), &keypair2.1);
+
+    let body = format!(
+        "{}\nnot a transaction\n{}\n",
+        serde_json::to_string(&tx1).unwrap(),
+        serde_json::to_string(&tx2).unwrap()
+    );
+    let (status, body) = post_raw(&api, "v1/submit_batch", &body);
+    let res: Vec<BatchItemResponse> = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(status, Status::Ok);
+    assert_eq!(res.len(), 3);
+    assert_eq!(res[0].tx_hash, Some(tx1.hash()));
+    assert!(res[1].error.is_some());
+    assert_eq!(res[2].tx_hash, Some(tx2.hash()));
+
+    testkit.create_block();
+
+    let res = api.get::<serde_json::Value>(ApiKind::Service("timestamp"), "v1/timestamps");
+    assert_eq!(res.as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_submit_batch_too_large() {
+    let testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new())
+        .create();
+    let api = testkit.api();
+
+    // The body is refused by its size before it is parsed
+    let keypair = gen_keypair();
+    let line = serde_json::to_string(&TxTimestamp::new(&keypair.0, &crypto::hash(b"Oceans"), unix_time(), &keypair.1))
+        .unwrap();
+    let count = timestamping::MAX_BATCH_BODY_SIZE as usize / (line.len() + 1) + 1;
+    let body = vec![line; count].join("\n");
+    let (status, _) = post_raw(&api, "v1/submit_batch", &body);
+    assert_eq!(status, Status::PayloadTooLarge);
+
+    // Nothing of it reached the pool
+    let stats: ServiceStats = api.get_private(ApiKind::Service("timestamp"), "v1/stats");
+    assert_eq!(stats.pending, 0);
+}
+
+#[test]
+fn test_events_resume_from_height_rest() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(TimestampService::new())
+        .create();
+
+    let keypair1 = gen_keypair();
+    let keypair2 = gen_keypair();
+    let keypair3 = gen_keypair();
+    let tx1 = TxTimestamp::new(&keypair1.0, &crypto::hash(b"Down To Earth"), unix_time(), &keypair1.1);
+    let tx2 = TxTimestamp::new(&keypair2.0, &crypto::hash(b"Cry Over Spilt Milk"), unix_time(), &keypair2.1);
+    let tx3 = TxTimestamp::new(&keypair3.0, &crypto::hash(b"Dropping Like Flies"), unix_time(), &keypair3.1);
+
+    testkit.create_block_with_transactions(txvec![tx1.clone(), tx2.clone()]);
+    testkit.create_block_with_transactions(txvec![tx3.clone()]);
+
+    let api = testkit.api();
+
+    let (status, body) = get_raw(&api, "v1/events?from_height=0&limit=3");
+    let events = parse_events(&body);
+
+    assert_eq!(status, Status::Ok);
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0].height, 1);
+    assert_eq!(events[2].height, 2);
+    assert_eq!(events[2].tx_hash, tx3.hash());
+
+    let (_, body) = get_raw(&api, "v1/events?from_height=2&limit=1");
+    let events = parse_events(&body);
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].timestamp.pub_key(), &keypair3.0);
+    assert!(body.contains("id: 2\n"));
+}
+
+#[test]
+fn test_events_live_rest() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new().with_max_event_streams(1))
+        .create();
+    let api = testkit.api();
+
+    // The stream waits for a block which is not committed yet. The only place for a stream may be
+    // taken by a probe below for a moment, so the request is repeated until it gets it.
+    let stream_api = testkit.api();
+    let stream = thread::spawn(move || loop {
+        let (status, body) = get_raw(&stream_api, "v1/events?from_height=1&limit=1");
+        if status != Status::ServiceUnavailable {
+            return (status, body);
+        }
+        thread::sleep(Duration::from_millis(10));
+    });
+
+    // Another stream is refused while the first one is open
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while get_raw(&api, "v1/events?limit=0").0 != Status::ServiceUnavailable {
+        assert!(Instant::now() < deadline, "The stream has not been opened");
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    // The commit wakes the stream up through `handle_commit`
+    let keypair = gen_keypair();
+    let tx = TxTimestamp::new(&keypair.0, &crypto::hash(b"Alive"), unix_time(), &keypair.1);
+    testkit.create_block_with_transactions(txvec![tx.clone()]);
+
+    let (status, body) = stream.join().unwrap();
+    let events = parse_events(&body);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].height, 1);
+    assert_eq!(events[0].tx_hash, tx.hash());
+
+    // The place of the closed stream is free again
+    let (status, _) = get_raw(&api, "v1/events?limit=0");
+    assert_eq!(status, Status::Ok);
+}
+
+#[test]
+fn test_timestamps_range_rest() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(TimestampService::new())
+        .create();
+
+    let keypair1 = gen_keypair();
+    let keypair2 = gen_keypair();
+    let keypair3 = gen_keypair();
+    let tx1 = TxTimestamp::new(&keypair1.0, &crypto::hash(b"Down To Earth"), unix_time(), &keypair1.1);
+    let tx2 = TxTimestamp::new(&keypair2.0, &crypto::hash(b"Cry Over Spilt Milk"), unix_time(), &keypair2.1);
+    let tx3 = TxTimestamp::new(&keypair3.0, &crypto::hash(b"Dropping Like Flies"), unix_time(), &keypair3.1);
+
+    testkit.create_block_with_transactions(txvec![tx1.clone(), tx2.clone()]);
+    testkit.create_block_with_transactions(txvec![tx3.clone()]);
+
+    let api = testkit.api();
+
+    // Paging through the time index
+    let page = api.get::<TimestampsPage>(ApiKind::Service("timestamp"), "v1/timestamps?from=0&limit=2");
+    assert_eq!(page.timestamps.len(), 2);
+    let next = page.next.unwrap();
+
+    let page = api.get::<TimestampsPage>(
+        ApiKind::Service("timestamp"),
+        &format!("v1/timestamps?from=0&limit=2&cursor={}", next),
+    );
+    assert_eq!(page.timestamps.len(), 1);
+    assert!(page.next.is_none());
+
+    // Nothing is timestamped in the past
+    let page = api.get::<TimestampsPage>(ApiKind::Service("timestamp"), "v1/timestamps?from=0&to=1");
+    assert!(page.timestamps.is_empty());
+
+    // Height range
+    let page = api.get::<TimestampsPage>(
+        ApiKind::Service("timestamp"),
+        "v1/timestamps?from_height=2&to_height=3",
+    );
+    assert_eq!(page.timestamps.len(), 1);
+    assert_eq!(page.timestamps[0].pub_key(), &keypair3.0);
+
+    // Plain listing is unchanged
+    let res = api.get::<serde_json::Value>(ApiKind::Service("timestamp"), "v1/timestamps");
+    assert_eq!(res.as_array().unwrap().len(), 3);
+}
+
+#[test]
+fn test_timestamp_time() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(TimestampService::new())
+        .create();
+    let api = testkit.api();
+
+    // The time of a timestamp is the one its author signed, not a clock of a validator
+    let keypair1 = gen_keypair();
+    let keypair2 = gen_keypair();
+    let time = unix_time() - 60;
+    let tx1 = TxTimestamp::new(&keypair1.0, &crypto::hash(b"Down To Earth"), time, &keypair1.1);
+    let tx2 = TxTimestamp::new(&keypair2.0, &crypto::hash(b"Oceans"), time + 1, &keypair2.1);
+    testkit.create_block_with_transactions(txvec![tx1.clone(), tx2.clone()]);
+
+    let res: Timestamp = api.get(ApiKind::Service("timestamp"), &format!("v1/timestamp/{}", keypair1.0.to_hex()));
+    assert_eq!(res.time(), time);
+    let page: TimestampsPage = api.get(
+        ApiKind::Service("timestamp"),
+        &format!("v1/timestamps?from={}&to={}", time, time + 1),
+    );
+    assert_eq!(page.timestamps.len(), 1);
+    assert_eq!(page.timestamps[0].pub_key(), &keypair1.0);
+
+    // Times too far from the node's clock are refused on submission
+    let keypair3 = gen_keypair();
+    let stale = TxTimestamp::new(
+        &keypair3.0,
+        &crypto::hash(b"Dropping Like Flies"),
+        unix_time() - 2 * timestamping::MAX_TIME_DRIFT,
+        &keypair3.1,
+    );
+    let (status, _) = post_raw(&api, "v1/submit", &serde_json::to_string(&stale).unwrap());
+    assert_eq!(status, Status::BadRequest);
+
+    // A cursor has to lie within the range it pages through
+    let page: TimestampsPage = api.get(
+        ApiKind::Service("timestamp"),
+        &format!("v1/timestamps?from={}&limit=1", time),
+    );
+    let next = page.next.unwrap();
+    let endpoint = format!("v1/timestamps?from={}&to={}&cursor={}", time, time + 1, next);
+    let (status, _) = get_raw(&api, &endpoint);
+    assert_eq!(status, Status::BadRequest);
+    let (status, _) = get_raw(&api, "v1/timestamps?from_height=2&cursor=0");
+    assert_eq!(status, Status::BadRequest);
+}
+
+#[test]
+fn test_rocksdb_restart() {
+    use exonum::blockchain::{Blockchain, GenesisConfig, Service, ValidatorKeys};
+    use exonum::helpers::{Height, ValidatorId};
+    use exonum::node::{ApiSender, EventsPoolCapacity, NodeChannel};
+    use exonum::storage::{Database, RocksDB, RocksDBOptions};
+    use std::collections::BTreeMap;
+    use std::iter;
+    use timestamping::TimestampSchema;
+
+    let dir = TempDir::new("timestamping").unwrap();
+    let channel = NodeChannel::new(&EventsPoolCapacity::default());
+    let consensus_keys = gen_keypair();
+    let service_keys = gen_keypair();
+
+    // The blockchain on the database in `dir`, as a node opens it on start
+    let open = || {
+        let mut options = RocksDBOptions::default();
+        options.create_if_missing(true);
+        let db: Box<dyn Database> = Box::new(RocksDB::open(dir.path(), &options).unwrap());
+        let services: Vec<Box<dyn Service>> = vec![Box::new(TimestampService::new())];
+        let mut blockchain = Blockchain::new(
+            db,
+            services,
+            service_keys.0,
+            service_keys.1.clone(),
+            ApiSender::new(channel.api_requests.0.clone()),
+        );
+        let keys = ValidatorKeys {
+            consensus_key: consensus_keys.0,
+            service_key: service_keys.0,
+        };
+        blockchain.initialize(GenesisConfig::new(vec![keys].into_iter())).unwrap();
+        blockchain
+    };
+
+    let keypairs: Vec<_> = (0..3).map(|_| gen_keypair()).collect();
+    let txs: Vec<TxTimestamp> = keypairs
+        .iter()
+        .enumerate()
+        .map(|(idx, keypair)| TxTimestamp::new(&keypair.0, &crypto::hash(&[idx as u8]), unix_time(), &keypair.1))
+        .collect();
+
+    let (height, state_hash, service_state_hash) = {
+        let mut blockchain = open();
+        let mut pool: BTreeMap<Hash, Box<dyn Transaction>> = BTreeMap::new();
+        for tx in &txs {
+            pool.insert(tx.hash(), Box::new(tx.clone()));
+            let height = blockchain.last_block().height().next();
+            let (block_hash, patch) = blockchain.create_patch(ValidatorId::zero(), height, &[tx.hash()], &pool);
+            blockchain.commit(&patch, block_hash, iter::empty()).unwrap();
+        }
+        let block = blockchain.last_block();
+        let service_state_hash = TimestampSchema::new(blockchain.snapshot()).state_hash();
+        (block.height(), *block.state_hash(), service_state_hash)
+    };
+    assert_eq!(height, Height(3));
+
+    // Open the same directory again, as a restarted node does; genesis is not created twice
+    let blockchain = open();
+    let block = blockchain.last_block();
+    assert_eq!(block.height(), height);
+    assert_eq!(block.state_hash(), &state_hash);
+
+    let schema = TimestampSchema::new(blockchain.snapshot());
+    assert_eq!(schema.state_hash(), service_state_hash);
+    for (keypair, tx) in keypairs.iter().zip(&txs) {
+        assert_eq!(schema.timestamp(&keypair.0).unwrap().content(), tx.content());
+        assert_eq!(schema.record(&keypair.0).unwrap().tx_hash(), &tx.hash());
+    }
+}
+
+#[test]
+fn test_client() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(TimestampService::new())
+        .create();
+
+    let client = Client::with_transport(TestKitTransport { api: testkit.api() });
+
+    let keypair = gen_keypair();
+    let content = crypto::hash(b"Down To Earth");
+    let tx1 = TxTimestamp::new(&keypair.0, &content, unix_time(), &keypair.1);
+
+    let tx_hash = client.submit(&tx1).unwrap();
+    assert_eq!(tx_hash, tx1.hash());
+    assert!(client.timestamp(&keypair.0).unwrap().is_none());
+
+    // Nothing is committed without a block
+    match client.wait_for_commit(&tx_hash, Duration::from_millis(10)) {
+        Err(ClientError::Timeout) => (),
+        _ => panic!("Expected a timeout"),
+    }
+
+    testkit.create_block();
+
+    assert_eq!(client.wait_for_commit(&tx_hash, Duration::from_secs(1)).unwrap(), 1);
+    assert_eq!(client.timestamp(&keypair.0).unwrap().unwrap().content(), &content);
+    assert_eq!(client.timestamps().unwrap().len(), 1);
+
+    match client.get::<serde_json::Value>("v1/transaction/not-a-hash") {
+        Err(ClientError::Api { status: 400, .. }) => (),
+        _ => panic!("Expected a bad request"),
+    }
+}
+
+#[test]
+fn test_content_lookup_rest() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(TimestampService::new())
+        .create();
+
+    let client = Client::with_transport(TestKitTransport { api: testkit.api() });
+
+    let owner = gen_keypair();
+    let content = crypto::hash(b"Down To Earth");
+    let keypair = client::document_keypair(&owner.1, &content);
+    let tx1 = TxTimestamp::new(&keypair.0, &content, unix_time(), &keypair.1);
+
+    assert!(client.timestamp_by_content(&content).unwrap().is_none());
+
+    testkit.create_block_with_transactions(txvec![tx1.clone()]);
+
+    let info = client.timestamp_by_content(&content).unwrap().unwrap();
+    assert_eq!(info.timestamp.pub_key(), &keypair.0);
+    assert_eq!(info.tx_hash, tx1.hash());
+    assert_eq!(info.height, 1);
+
+    // The same document gets the same key, another one gets a different key
+    assert_eq!(client::document_keypair(&owner.1, &content).0, keypair.0);
+    assert_ne!(client::document_keypair(&owner.1, &crypto::hash(b"Cry Over Spilt Milk")).0, keypair.0);
+}
+
+#[test]
+fn test_keystore() {
+    let dir = TempDir::new("keystore").unwrap();
+    let keystore = Keystore::open(dir.path()).unwrap();
+
+    let public_key = keystore.generate("alice", "correct horse").unwrap();
+    let (loaded_public, secret_key) = keystore.load("alice", "correct horse").unwrap();
+    assert_eq!(loaded_public, public_key);
+
+    // The loaded key signs for its public key
+    let tx1 = TxTimestamp::new(&public_key, &crypto::hash(b"Down To Earth"), unix_time(), &secret_key);
+    assert!(tx1.verify_signature(&public_key));
+
+    match keystore.load("alice", "battery staple") {
+        Err(KeystoreError::WrongPassword) => (),
+        _ => panic!("Expected a wrong password"),
+    }
+    match keystore.generate("alice", "correct horse") {
+        Err(KeystoreError::AlreadyExists(_)) => (),
+        _ => panic!("Expected an existing key"),
+    }
+    assert!(keystore.generate("../bob", "correct horse").is_err());
+
+    // Exported keys move between keystores with their password
+    let other_dir = TempDir::new("keystore").unwrap();
+    let other = Keystore::open(other_dir.path()).unwrap();
+    other.import("alice", &keystore.export("alice").unwrap()).unwrap();
+    assert_eq!(other.load("alice", "correct horse").unwrap().1, secret_key);
+
+    let keypair = gen_keypair();
+    keystore.add("bob", &keypair.1, "hunter2").unwrap();
+    let names: Vec<String> = keystore.list().unwrap().into_iter().map(|key| key.name).collect();
+    assert_eq!(names, vec!["alice".to_owned(), "bob".to_owned()]);
+
+    // Key files are readable by their owner only
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(dir.path().join("alice.json")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    // A key file asking for an unbounded key derivation is refused
+    let mut key = keystore.export("alice").unwrap();
+    key.kdf.memlimit = usize::MAX;
+    match key.decrypt("correct horse") {
+        Err(KeystoreError::Format(_)) => (),
+        other => panic!("Unexpected result {:?}", other.map(|_| ())),
+    }
+
+    // A malformed file is listed with its error, the other keys still are
+    fs::write(dir.path().join("carol.json"), b"{").unwrap();
+    let keys = keystore.list().unwrap();
+    assert_eq!(keys.len(), 3);
+    assert!(keys[0].public_key.is_ok());
+    assert!(keys[1].public_key.is_ok());
+    match keys[2].public_key {
+        Err(KeystoreError::Format(_)) => (),
+        _ => panic!("Malformed key file is listed as valid"),
+    }
+}
+
+#[test]
+fn test_watch_journal() {
+    let dir = TempDir::new("journal").unwrap();
+    let path = dir.path().join("journal");
+    let (first, second) = (dir.path().join("first.txt"), dir.path().join("second.txt"));
+    let files = vec![(first.clone(), crypto::hash(b"Sober")), (second.clone(), crypto::hash(b"Lateralus"))];
+
+    let mut journal = Journal::open(&path).unwrap();
+    assert_eq!(journal.unsent(files.clone()).len(), 2);
+    let tx_hash = crypto::hash(b"tx");
+    journal
+        .record(JournalEntry {
+            path: first.clone(),
+            content: files[0].1,
+            tx_hash: Some(tx_hash),
+            receipt: None,
+            sent: unix_time(),
+        })
+        .unwrap();
+    drop(journal);
+
+    // A line cut short by a crash is skipped
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(b"{\"path\":\"second.t").unwrap();
+    drop(file);
+
+    // After a restart a sent file is not stamped again, unless its content changes
+    let mut journal = Journal::open(&path).unwrap();
+    let unsent: Vec<PathBuf> = journal.unsent(files.clone()).into_iter().map(|(path, _)| path).collect();
+    assert_eq!(unsent, vec![second.clone()]);
+    assert_eq!(journal.unsent(vec![(first.clone(), crypto::hash(b"Opiate"))]).len(), 1);
+
+    // The transaction still waits for its commit
+    let pending = journal.pending();
+    assert_eq!(pending.len(), 1);
+    assert_eq!((&pending[0].path, pending[0].tx_hash), (&first, Some(tx_hash)));
+
+    let mut entry = pending[0].clone();
+    entry.receipt = Some(Receipt {
+        content: files[0].1,
+        pub_key: gen_keypair().0,
+        time: unix_time(),
+        height: 1,
+        tx_hash,
+        salt: None,
+    });
+    journal.record(entry).unwrap();
+    drop(journal);
+
+    let journal = Journal::open(&path).unwrap();
+    assert!(journal.pending().is_empty());
+    assert!(journal.is_known(&first, &files[0].1));
+}
+
+#[test]
+fn test_watch_resends_dropped_submission() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new())
+        .create();
+    let client = Client::with_transport(TestKitTransport { api: testkit.api() });
+    let owner = gen_keypair();
+
+    let dir = TempDir::new("journal").unwrap();
+    let mut journal = Journal::open(&dir.path().join("journal")).unwrap();
+    let path = dir.path().join("song.txt");
+    let content = crypto::hash(b"Schism");
+
+    // The first submission never reaches a block
+    let dropped = client::document_tx(&owner.0, &owner.1, &content, unix_time());
+    let sent = unix_time();
+    journal
+        .record(JournalEntry {
+            path: path.clone(),
+            content,
+            tx_hash: Some(dropped.hash()),
+            receipt: None,
+            sent,
+        })
+        .unwrap();
+
+    // It may still be committed within the grace period
+    let checked = journal.check_pending(&client, sent + LOST_AFTER - 1).unwrap();
+    match checked[0].1 {
+        Pending::Waiting => (),
+        _ => panic!("Expected a waiting entry"),
+    }
+
+    // Then it is lost and sent again, under a new hash kept by the journal
+    let now = sent + LOST_AFTER;
+    let mut checked = journal.check_pending(&client, now).unwrap();
+    let entry = match checked.pop().unwrap() {
+        (entry, Pending::Lost) => entry,
+        _ => panic!("Expected a lost entry"),
+    };
+    let resent = client::document_tx(&owner.0, &owner.1, &content, unix_time() + 1);
+    assert_ne!(resent.hash(), dropped.hash());
+    let tx_hash = client.submit(&resent).unwrap();
+    journal
+        .record(JournalEntry { tx_hash: Some(tx_hash), sent: now, ..entry })
+        .unwrap();
+    assert_eq!(journal.pending()[0].tx_hash, Some(resent.hash()));
+    testkit.create_block();
+
+    // The committed content gets its receipt and is not sent again, even after the grace period
+    let mut checked = journal.check_pending(&client, now + 10 * LOST_AFTER).unwrap();
+    let (mut entry, receipt) = match checked.pop().unwrap() {
+        (entry, Pending::Committed(receipt)) => (entry, receipt),
+        _ => panic!("Expected a committed entry"),
+    };
+    assert_eq!((receipt.tx_hash, receipt.content), (resent.hash(), content));
+    entry.receipt = Some(receipt);
+    journal.record(entry).unwrap();
+    assert!(journal.check_pending(&client, now + 10 * LOST_AFTER).unwrap().is_empty());
+
+    // A late commit of the first submission changes nothing for the owner
+    testkit.create_block_with_transactions(txvec![dropped.clone()]);
+    let info = client.timestamp_by_content(&content).unwrap().unwrap();
+    assert_eq!(info.tx_hash, resent.hash());
+}
+
+#[test]
+fn test_watch_backoff() {
+    let start = Instant::now();
+    let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(4));
+    assert!(backoff.ready(start));
+
+    // The delay doubles with every failure up to the maximum
+    assert_eq!(backoff.failed(start), Duration::from_secs(1));
+    assert!(!backoff.ready(start));
+    assert!(backoff.ready(start + Duration::from_secs(1)));
+    let delays: Vec<u64> = (0..3).map(|_| backoff.failed(start).as_secs()).collect();
+    assert_eq!(delays, vec![2, 4, 4]);
+    assert!(!backoff.ready(start + Duration::from_secs(3)));
+
+    // A success resets it
+    backoff.succeeded();
+    assert!(backoff.ready(start));
+    assert_eq!(backoff.failed(start), Duration::from_secs(1));
+}
+
+#[test]
+fn test_commit_reveal() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(TimestampService::new())
+        .create();
+
+    let client = Client::with_transport(TestKitTransport { api: testkit.api() });
+
+    let keypair = gen_keypair();
+    let document = crypto::hash(b"Down To Earth");
+    let salt = client::gen_salt();
+    let content = commitment(&salt, &document);
+    let tx1 = TxTimestamp::new(&keypair.0, &content, unix_time(), &keypair.1);
+
+    testkit.create_block_with_transactions(txvec![tx1.clone()]);
+
+    // The document cannot be found until it is revealed
+    assert!(client.timestamp_by_content(&document).unwrap().is_none());
+
+    // A wrong salt does not reveal anything
+    let wrong = TxReveal::new(&keypair.0, &client::gen_salt(), &document, &keypair.1);
+    testkit.create_block_with_transactions(txvec![wrong.clone()]);
+    assert!(client.reveal(&keypair.0).unwrap().is_none());
+
+    let reveal = TxReveal::new(&keypair.0, &salt, &document, &keypair.1);
+    testkit.create_block_with_transactions(txvec![reveal.clone()]);
+
+    let res = client.reveal(&keypair.0).unwrap().unwrap();
+    assert_eq!(res.document(), &document);
+    assert_eq!(res.salt(), &salt[..]);
+    assert_eq!(res.tx_hash(), &reveal.hash());
 
+    let res = client.get::<serde_json::Value>(&format!("v1/revealed/{}", document.to_hex())).unwrap();
+    assert_eq!(&res["pub_key"], &keypair.0.to_hex());
+
+    // A short salt is rejected before execution
+    let short = TxReveal::new(&keypair.0, &[0; 4], &document, &keypair.1);
+    assert!(!short.verify());
+}
+
+#[test]
+fn test_earliest_reveal_wins() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new())
+        .create();
+
+    let client = Client::with_transport(TestKitTransport { api: testkit.api() });
+
+    // Two commitments to the same document, revealed one after another
+    let document = crypto::hash(b"Down To Earth");
+    let (keypair1, salt1) = (gen_keypair(), client::gen_salt());
+    let (keypair2, salt2) = (gen_keypair(), client::gen_salt());
+    testkit.create_block_with_transactions(txvec![
+        TxTimestamp::new(&keypair1.0, &commitment(&salt1, &document), unix_time(), &keypair1.1),
+        TxTimestamp::new(&keypair2.0, &commitment(&salt2, &document), unix_time(), &keypair2.1),
+    ]);
+    testkit.create_block_with_transactions(txvec![TxReveal::new(&keypair1.0, &salt1, &document, &keypair1.1)]);
+    testkit.create_block_with_transactions(txvec![TxReveal::new(&keypair2.0, &salt2, &document, &keypair2.1)]);
+
+    // Both reveals are kept, the document points to the first one
+    assert!(client.reveal(&keypair1.0).unwrap().is_some());
+    assert!(client.reveal(&keypair2.0).unwrap().is_some());
+    let res = client.get::<Reveal>(&format!("v1/revealed/{}", document.to_hex())).unwrap();
+    assert_eq!(res.pub_key(), &keypair1.0);
+    assert_eq!(res.height(), 2);
+}
+
+#[test]
+fn test_relayed_submission() {
+    let relayer = gen_keypair();
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(TimestampService::new().with_relayer(&relayer.0, 1))
+        .create();
+
+    let client = Client::with_transport(TestKitTransport { api: testkit.api() });
+
+    // Authors sign their transactions offline and hand them to the relayer
+    let author1 = gen_keypair();
+    let tx1 = TxTimestamp::new(&author1.0, &crypto::hash(b"Hallowed Ground"), unix_time(), &author1.1);
+    let relayed1 = TxRelayed::wrap(&tx1, &relayer.0, &relayer.1);
+    assert!(relayed1.verify());
+
+    testkit.create_block_with_transactions(txvec![relayed1.clone()]);
+
+    // The author owns the timestamp
+    let res = client.timestamp(&author1.0).unwrap().unwrap();
+    assert_eq!(res.content(), tx1.content());
+    let info = client.timestamp_by_content(tx1.content()).unwrap().unwrap();
+    assert_eq!(info.tx_hash, relayed1.hash());
+
+    let res = client.get::<Relayer>(&format!("v1/relayer/{}", relayer.0.to_hex())).unwrap();
+    assert_eq!(res.quota(), 1);
+    assert_eq!(res.used(), 1);
+
+    // The quota of the relayer is used up
+    let author2 = gen_keypair();
+    let tx2 = TxTimestamp::new(&author2.0, &crypto::hash(b"Oceans"), unix_time(), &author2.1);
+    testkit.create_block_with_transactions(txvec![TxRelayed::wrap(&tx2, &relayer.0, &relayer.1)]);
+    assert!(client.timestamp(&author2.0).unwrap().is_none());
+
+    // Unregistered relayers are refused
+    let stranger = gen_keypair();
+    testkit.create_block_with_transactions(txvec![TxRelayed::wrap(&tx2, &stranger.0, &stranger.1)]);
+    assert!(client.timestamp(&author2.0).unwrap().is_none());
+
+    // The author's signature cannot be forged by the relayer
+    let signature = tx2.raw().signature();
+    let forged = TxRelayed::new(&relayer.0, &author2.0, &crypto::hash(b"Deceit"), unix_time(), signature, &relayer.1);
+    assert!(!forged.verify());
+}
+
+#[test]
+fn test_anchoring() {
+    let ledger = MockLedger::new();
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new().with_anchoring(2, Box::new(ledger.clone())))
+        .create();
+
+    let client = Client::with_transport(TestKitTransport { api: testkit.api() });
+
+    let keypair = gen_keypair();
+    let tx = TxTimestamp::new(&keypair.0, &crypto::hash(b"Alive"), unix_time(), &keypair.1);
+    testkit.create_block_with_transactions(txvec![tx.clone()]);
+
+    // Nothing is anchored before the schedule
+    assert!(ledger.entries().is_empty());
+    assert!(client.get_optional::<Anchor>("v1/anchor/1").unwrap().is_none());
+    let proof_path = format!("v1/anchor/proof/{}", tx.hash().to_hex());
+    assert!(client.get_optional::<AnchorProof>(&proof_path).unwrap().is_none());
+
+    // Block 2 is anchored on commit, and the anchor is recorded in the next block
+    testkit.create_block();
+    let entries = ledger.entries();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].height, 2);
+    testkit.create_block();
+
+    let anchors: Vec<Anchor> = client.get("v1/anchors").unwrap();
+    assert_eq!(anchors.len(), 1);
+    assert_eq!(anchors[0].reference(), "mock:0");
+    assert_eq!(anchors[0].block_hash(), &entries[0].block_hash);
+
+    // The timestamp of block 1 is covered by the anchor of block 2
+    let anchor: Anchor = client.get("v1/anchor/1").unwrap();
+    assert_eq!(anchor.height(), 2);
+
+    let snapshot = testkit.snapshot();
+    let block = Schema::new(&snapshot).blocks().get(anchor.block_hash()).unwrap();
+    assert_eq!(block.state_hash(), &entries[0].state_hash);
+
+    // The proof links the transaction to the external record through the headers of blocks 1 and 2
+    let proof: AnchorProof = client.get(&proof_path).unwrap();
+    assert_eq!(proof.blocks.len(), 2);
+    assert_eq!(proof.anchor.reference(), "mock:0");
+    proof.verify(&entries[0]).unwrap();
+
+    let invalid = |proof: &AnchorProof, external: &AnchorPayload| match proof.verify(external) {
+        Err(AnchorError::InvalidProof(_)) => (),
+        _ => panic!("A forged proof is accepted"),
+    };
+    // another record in the external system
+    let mut external = entries[0].clone();
+    external.block_hash = crypto::hash(b"forged");
+    invalid(&proof, &external);
+    // another transaction
+    let mut forged: AnchorProof = client.get(&proof_path).unwrap();
+    forged.tx_hash = crypto::hash(b"forged");
+    invalid(&forged, &entries[0]);
+    // a header missing from the chain
+    let mut forged: AnchorProof = client.get(&proof_path).unwrap();
+    forged.blocks.remove(0);
+    invalid(&forged, &entries[0]);
+    let mut forged: AnchorProof = client.get(&proof_path).unwrap();
+    forged.blocks.pop();
+    invalid(&forged, &entries[0]);
+}
+
+#[test]
+fn test_node_anchors_to_file() {
+    let dir = TempDir::new("anchoring").unwrap();
+    let mut cluster = Command::new(node_exe())
+        .args(["cluster", "--validators", "1", "--peer-port", "12400", "--api-port", "18400"])
+        .args(["--anchor-interval", "2", "--dir"])
+        .arg(dir.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let client = Client::new("http://127.0.0.1:18400");
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while client.get::<StatsResponse>("v1/stats").is_err() {
+        assert!(Instant::now() < deadline, "The node has not started");
+        thread::sleep(Duration::from_millis(500));
+    }
+
+    let keypair = gen_keypair();
+    let tx = TxTimestamp::new(&keypair.0, &crypto::hash(b"Ashes"), unix_time(), &keypair.1);
+    let tx_hash = client.submit(&tx).unwrap();
+    client.wait_for_commit(&tx_hash, Duration::from_secs(60)).unwrap();
+
+    // the node anchors to its log and serves a proof checked against the line it wrote
+    let proof_path = format!("v1/anchor/proof/{}", tx_hash.to_hex());
+    let proof = loop {
+        match client.get_optional::<AnchorProof>(&proof_path).unwrap() {
+            Some(proof) => break proof,
+            None if Instant::now() > deadline => panic!("The timestamp has not been anchored"),
+            None => thread::sleep(Duration::from_millis(500)),
+        }
+    };
+    let log = fs::read_to_string(dir.path().join("anchors_0.log")).unwrap();
+    let external = log
+        .lines()
+        .map(|line| serde_json::from_str::<AnchorPayload>(line).unwrap())
+        .find(|payload| payload.height == proof.anchor.height())
+        .unwrap();
+    proof.verify(&external).unwrap();
+
+    drop(cluster.stdin.take());
+    assert!(cluster.wait().unwrap().success());
+}
+
+#[test]
+fn test_file_anchor_sink() {
+    let dir = TempDir::new("anchors").unwrap();
+    let path = dir.path().join("anchors.log");
+    let sink = FileSink::new(&path);
+
+    let payload = |height: u64| AnchorPayload {
+        height,
+        block_hash: crypto::hash(&[height as u8]),
+        state_hash: crypto::hash(b"state"),
+    };
+
+    let first = sink.anchor(&payload(1)).unwrap();
+    let second = sink.anchor(&payload(2)).unwrap();
+    assert!(first.ends_with(":0"));
+    assert_ne!(first, second);
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<_> = log.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(serde_json::from_str::<AnchorPayload>(lines[1]).unwrap(), payload(2));
+}
+
+#[test]
+fn test_private_api() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new())
+        .create();
+
+    let api = testkit.api();
+    let keypair = gen_keypair();
+    let tx = TxTimestamp::new(&keypair.0, &crypto::hash(b"Meteora"), unix_time(), &keypair.1);
+    api.post::<TxTimestamp, serde_json::Value>(ApiKind::Service("timestamp"), "v1/submit", &tx);
+
+    // The timestamp waits in the pool until it is committed
+    let pending: Vec<TxTimestamp> =
+        api.get_private(ApiKind::Service("timestamp"), "v1/local_pending");
+    assert_eq!(pending, vec![tx.clone()]);
+    let stats: ServiceStats = api.get_private(ApiKind::Service("timestamp"), "v1/stats");
+    assert_eq!(stats.pending, 1);
+    assert_eq!(stats.timestamps, 0);
+
+    testkit.create_block();
+
+    let pending: Vec<TxTimestamp> =
+        api.get_private(ApiKind::Service("timestamp"), "v1/local_pending");
+    assert!(pending.is_empty());
+    let stats: ServiceStats = api.get_private(ApiKind::Service("timestamp"), "v1/stats");
+    assert_eq!((stats.height, stats.timestamps, stats.pending), (1, 1, 0));
+
+    let config: ConfigResponse = api.get_private(ApiKind::Service("timestamp"), "v1/config");
+    assert!(config.genesis.relayers.is_empty());
+    assert_eq!(config.max_hash_size, timestamping::DEFAULT_MAX_HASH_SIZE);
+    assert_eq!(config.anchor_interval, None);
+
+    // A quota override registers a relayer
+    let relayer = gen_keypair();
+    let request = QuotaRequest { pub_key: relayer.0, quota: 2 };
+    api.post_private::<QuotaRequest, TimestampResponse>(ApiKind::Service("timestamp"), "v1/quota", &request);
+    testkit.create_block();
+
+    let res: Relayer = api.get(ApiKind::Service("timestamp"), &format!("v1/relayer/{}", relayer.0.to_hex()));
+    assert_eq!((res.quota(), res.used()), (2, 0));
+
+    let sizes: serde_json::Value = api.get_private(ApiKind::Service("timestamp"), "v1/index_sizes");
+    assert_eq!(sizes["timestamps"], json!(1));
+    assert_eq!(sizes["history"], json!(1));
+    assert_eq!(sizes["relayers"], json!(1));
+    assert_eq!(sizes["anchors"], json!(0));
+    // map sizes are counted as entries are added
+    assert_eq!(sizes["by_content"], json!(1));
+    assert_eq!(sizes["daily_counts"], json!(1));
+    assert_eq!(sizes["submitter_counts"], json!(1));
+    assert_eq!(sizes["reveals"], json!(0));
+    assert_eq!(sizes["owners"], json!(0));
+}
+
+#[test]
+fn test_pending_eviction() {
+    let txs: Vec<TxTimestamp> = (0..3u8)
+        .map(|i| {
+            let keypair = gen_keypair();
+            TxTimestamp::new(&keypair.0, &crypto::hash(&[i]), unix_time(), &keypair.1)
+        })
+        .collect();
+
+    // The oldest transactions go first once the cap is reached
+    let pending = PendingTransactions::with_limits(2, Duration::from_secs(3600));
+    for tx in &txs {
+        pending.add(tx.clone());
+    }
+    let mut expected = vec![txs[1].clone(), txs[2].clone()];
+    expected.sort_by_key(|tx| tx.hash());
+    assert_eq!(pending.list(), expected);
+
+    // A transaction submitted again is kept as a new one
+    pending.add(txs[1].clone());
+    pending.add(txs[0].clone());
+    let mut expected = vec![txs[0].clone(), txs[1].clone()];
+    expected.sort_by_key(|tx| tx.hash());
+    assert_eq!(pending.list(), expected);
+
+    // Transactions which were never committed are forgotten after the maximum age
+    let pending = PendingTransactions::with_limits(10, Duration::from_millis(50));
+    pending.add(txs[0].clone());
+    assert_eq!(pending.len(), 1);
+    thread::sleep(Duration::from_millis(100));
+    pending.add(txs[1].clone());
+    assert_eq!(pending.list(), vec![txs[1].clone()]);
+}
+
+#[test]
+fn test_metrics() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new())
+        .create();
+
+    let api = testkit.api();
+
+    let keypair = gen_keypair();
+    let tx = TxTimestamp::new(&keypair.0, &crypto::hash(b"Ascension"), unix_time(), &keypair.1);
+    api.post::<TxTimestamp, serde_json::Value>(ApiKind::Service("timestamp"), "v1/submit", &tx);
+
+    let signature = Signature::new([0; SIGNATURE_LENGTH]);
+    let forged = TxTimestamp::new_with_signature(&keypair.0, &crypto::hash(b"Decline"), unix_time(), &signature);
+    let (status, _) = post_raw(&api, "v1/submit", &serde_json::to_string(&forged).unwrap());
+    assert_eq!(status, Status::BadRequest);
+
+    testkit.create_block();
+
+    // A reveal of a missing timestamp fails on execution
+    let stranger = gen_keypair();
+    let reveal = TxReveal::new(&stranger.0, &client::gen_salt(), &crypto::hash(b"Void"), &stranger.1);
+    testkit.create_block_with_transactions(txvec![reveal]);
+
+    let (status, body) = get_raw(&api, "metrics");
+    assert_eq!(status, Status::Ok);
+    let lines: Vec<&str> = body.lines().collect();
+    for expected in &[
+        "timestamp_transactions_submitted_total 2",
+        "timestamp_transactions_accepted_total 1",
+        "timestamp_transactions_rejected_total{reason=\"invalid_signature\"} 1",
+        "timestamp_transactions_rejected_total{reason=\"timestamp_not_found\"} 1",
+        "timestamp_http_request_duration_seconds_count{route=\"submit\"} 2",
+        "timestamp_commit_visible_seconds_count 1",
+        "timestamp_index_entries{index=\"timestamps\"} 1",
+    ] {
+        assert!(lines.contains(expected), "missing `{}` in:\n{}", expected, body);
+    }
+}
+
+#[test]
+fn test_stats() {
+    let relayer = gen_keypair();
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new().with_relayer(&relayer.0, 10))
+        .create();
+
+    let client = Client::with_transport(TestKitTransport { api: testkit.api() });
+
+    let txs: Vec<TxTimestamp> = (0..4u8)
+        .map(|i| {
+            let keypair = gen_keypair();
+            TxTimestamp::new(&keypair.0, &crypto::hash(&[i]), unix_time(), &keypair.1)
+        })
+        .collect();
+    testkit.create_block_with_transactions(txvec![
+        txs[0].clone(),
+        txs[1].clone(),
+        TxRelayed::wrap(&txs[2], &relayer.0, &relayer.1),
+        TxRelayed::wrap(&txs[3], &relayer.0, &relayer.1),
+    ]);
+
+    // Relayed timestamps are counted for the relayer
+    let stats: StatsResponse = client.get("v1/stats?bucket=day").unwrap();
+    assert_eq!(stats.buckets.len(), 1);
+    assert_eq!((stats.buckets[0].timestamps, stats.buckets[0].submitters), (4, 3));
+    assert_eq!((stats.total_timestamps, stats.total_submitters), (4, 3));
+
+    let day = stats.buckets[0].start;
+    assert_eq!(day % timestamping::SECONDS_PER_DAY, 0);
+
+    let endpoint = format!("v1/stats?bucket=day&from={}&to={}&pub_key={}", day, day + 1, relayer.0.to_hex());
+    let stats: StatsResponse = client.get(&endpoint).unwrap();
+    assert_eq!((stats.buckets[0].timestamps, stats.buckets[0].submitters), (2, 1));
+
+    // Buckets outside of the range are skipped, totals are not
+    let next_day = day + timestamping::SECONDS_PER_DAY;
+    let stats: StatsResponse = client.get(&format!("v1/stats?from={}", next_day)).unwrap();
+    assert!(stats.buckets.is_empty());
+    assert_eq!(stats.total_timestamps, 4);
+
+    let (status, _) = get_raw(&client.transport().api, "v1/stats?bucket=week");
+    assert_eq!(status, Status::BadRequest);
+}
+
+#[test]
+fn test_export() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new())
+        .create();
+
+    let client = Client::with_transport(TestKitTransport { api: testkit.api() });
+
+    let keypairs: Vec<_> = (0..3).map(|_| gen_keypair()).collect();
+    let txs: Vec<TxTimestamp> = keypairs
+        .iter()
+        .enumerate()
+        .map(|(i, keypair)| TxTimestamp::new(&keypair.0, &crypto::hash(&[i as u8]), unix_time(), &keypair.1))
+        .collect();
+    testkit.create_block_with_transactions(txvec![txs[0].clone(), txs[1].clone(), txs[2].clone()]);
+
+    let (status, body) = get_raw(&client.transport().api, "v1/export?format=csv");
+    assert_eq!(status, Status::Ok);
+    let lines: Vec<&str> = body.lines().collect();
+    assert_eq!(lines[0], "pub_key,content,time,height,tx_hash");
+    assert_eq!(lines.len(), 4);
+    let line = format!("{},{}", keypairs[1].0.to_hex(), txs[1].content().to_hex());
+    assert!(lines.iter().any(|l| l.starts_with(&line) && l.ends_with(&txs[1].hash().to_hex())));
+
+    // Only timestamps of the given keys are exported
+    let filter = ExportFilter {
+        keys: vec![keypairs[0].0, keypairs[2].0],
+        ..Default::default()
+    };
+    let mut out = Vec::new();
+    client.export(ExportFormat::Ndjson, &filter, &mut out).unwrap();
+    let records: Vec<ExportRecord> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].pub_key, keypairs[0].0);
+    assert_eq!(records[1].tx_hash, txs[2].hash());
+    assert_eq!(records[1].height, 1);
+
+    // A key given twice is exported once
+    let endpoint = format!("v1/export?pub_key={0}&pub_key={0}", keypairs[1].0.to_hex());
+    let (_, body) = get_raw(&client.transport().api, &endpoint);
+    assert_eq!(body.lines().count(), 2);
+
+    // Nothing was timestamped before the epoch
+    let filter = ExportFilter { to: Some(1), ..Default::default() };
+    let mut out = Vec::new();
+    client.export(ExportFormat::Csv, &filter, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap().lines().count(), 1);
+
+    // Time ranges are read from the time index
+    let first = txs.iter().map(|tx| tx.time()).min().unwrap();
+    let last = txs.iter().map(|tx| tx.time()).max().unwrap();
+    let (_, body) = get_raw(&client.transport().api, &format!("v1/export?from={}&to={}", first, last + 1));
+    assert_eq!(body.lines().count(), 4);
+    let (_, body) = get_raw(&client.transport().api, &format!("v1/export?from={}", last + 1));
+    assert_eq!(body.lines().count(), 1);
+
+    let (status, _) = get_raw(&client.transport().api, "v1/export?format=xml");
+    assert_eq!(status, Status::BadRequest);
+}
+
+#[test]
+fn test_owned_timestamps() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new())
+        .create();
+
+    let client = Client::with_transport(TestKitTransport { api: testkit.api() });
+
+    let owner = gen_keypair();
+    let contents = [crypto::hash(b"Glass"), crypto::hash(b"Onion")];
+    let txs: Vec<TxOwned> = contents
+        .iter()
+        .map(|content| client::document_tx(&owner.0, &owner.1, content, unix_time()))
+        .collect();
+    for tx in &txs {
+        client.submit(tx).unwrap();
+    }
+    testkit.create_block();
+
+    // Timestamps belong to the derived keys and name their owner
+    let info = client.timestamp_by_content(&contents[0]).unwrap().unwrap();
+    let (document_key, _) = client::document_keypair(&owner.1, &contents[0]);
+    assert_eq!(info.timestamp.pub_key(), &document_key);
+    assert_eq!(info.owner, Some(owner.0));
+    assert_eq!(info.tx_hash, txs[0].hash());
+
+    // The owner is counted in statistics and finds its timestamps in exports
+    let stats: StatsResponse = client.get(&format!("v1/stats?pub_key={}", owner.0.to_hex())).unwrap();
+    assert_eq!(stats.buckets[0].timestamps, 2);
+    let filter = ExportFilter {
+        keys: vec![owner.0],
+        ..Default::default()
+    };
+    let mut out = Vec::new();
+    client.export(ExportFormat::Csv, &filter, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap().lines().count(), 3);
+
+    // A document key listed next to its owner is exported once
+    let filter = ExportFilter {
+        keys: vec![document_key, owner.0],
+        ..Default::default()
+    };
+    let mut out = Vec::new();
+    client.export(ExportFormat::Csv, &filter, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap().lines().count(), 3);
+
+    // A document key cannot be claimed without its signature
+    let stranger = gen_keypair();
+    let forged = TxOwned::new(
+        &stranger.0,
+        &document_key,
+        &contents[0],
+        unix_time(),
+        txs[1].author_signature(),
+        &stranger.1,
+    );
+    assert!(!forged.verify());
+
+    // Owners are kept by snapshots
+    let snapshot: ServiceSnapshot = client
+        .transport()
+        .api
+        .get_private(ApiKind::Service("timestamp"), "v1/snapshot");
+    assert!(snapshot.timestamps.iter().all(|entry| entry.owner == Some(owner.0)));
+    let mut restored = TestKitBuilder::validator()
+        .with_service(TimestampService::new().with_snapshot(snapshot))
+        .create();
+    restored.create_block();
+    let client = Client::with_transport(TestKitTransport { api: restored.api() });
+    let info = client.timestamp_by_content(&contents[1]).unwrap().unwrap();
+    assert_eq!(info.owner, Some(owner.0));
+}
+
+#[test]
+fn test_snapshot() {
+    let relayer = gen_keypair();
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(TimestampService::new().with_relayer(&relayer.0, 5))
+        .create();
+
+    let keypairs: Vec<_> = (0..3).map(|_| gen_keypair()).collect();
+    let document = crypto::hash(b"Dialogue");
+    let salt = client::gen_salt();
+    let tx1 = TxTimestamp::new(&keypairs[0].0, &commitment(&salt, &document), unix_time(), &keypairs[0].1);
+    let tx2 = TxTimestamp::new(&keypairs[1].0, &crypto::hash(b"Sleepwalker"), unix_time(), &keypairs[1].1);
+    let tx3 = TxTimestamp::new(&keypairs[2].0, &crypto::hash(b"Uninvited"), unix_time(), &keypairs[2].1);
+    testkit.create_block_with_transactions(txvec![tx1.clone(), tx2.clone()]);
+    let (owner, document_key) = (gen_keypair(), gen_keypair());
+    let tx4 = TxTimestamp::new(&document_key.0, &crypto::hash(b"Bleed"), unix_time(), &document_key.1);
+    testkit.create_block_with_transactions(txvec![
+        TxRelayed::wrap(&tx3, &relayer.0, &relayer.1),
+        TxReveal::new(&keypairs[0].0, &salt, &document, &keypairs[0].1),
+        TxOwned::wrap(&tx4, &owner.0, &owner.1),
+    ]);
+
+    let api = testkit.api();
+    let snapshot: ServiceSnapshot = api.get_private(ApiKind::Service("timestamp"), "v1/snapshot");
+    assert_eq!(snapshot.height(), 2);
+    assert_eq!(snapshot.timestamps.len(), 4);
+    let relayed = snapshot.timestamps.iter().position(|entry| entry.submitter == relayer.0).unwrap();
+    assert_eq!(snapshot.timestamps[relayed].submitter, relayer.0);
+    let owned = snapshot.timestamps.iter().position(|entry| entry.owner.is_some()).unwrap();
+    assert_eq!(snapshot.timestamps[owned].owner, Some(owner.0));
+
+    let validators: Vec<PublicKey> = Schema::new(&testkit.snapshot())
+        .actual_configuration()
+        .validator_keys
+        .iter()
+        .map(|keys| keys.consensus_key)
+        .collect();
+    snapshot.verify(&validators).unwrap();
+
+    // Tampered content does not match the proven roots
+    let mut tampered = snapshot.clone();
+    let forged = timestamping::Timestamp::new(&keypairs[1].0, &crypto::hash(b"Forged"), 0);
+    tampered.timestamps[1].timestamp = forged;
+    match tampered.verify(&validators) {
+        Err(SnapshotError::Content(_)) => {}
+        _ => panic!("Tampered timestamps are accepted"),
+    }
+
+    // So do commit records, submitters and owners
+    let rejected = |tampered: ServiceSnapshot, field: &str| match tampered.verify(&validators) {
+        Err(SnapshotError::Content(_)) => {}
+        _ => panic!("Tampered {} are accepted", field),
+    };
+    let mut tampered = snapshot.clone();
+    let record = tampered.timestamps[1].record.clone();
+    tampered.timestamps[1].record = TimestampRecord::new(&crypto::hash(b"Forged"), record.height());
+    rejected(tampered, "transaction hashes");
+    let mut tampered = snapshot.clone();
+    tampered.timestamps[0].record = TimestampRecord::new(&tx1.hash(), 0);
+    rejected(tampered, "heights");
+    let mut tampered = snapshot.clone();
+    tampered.timestamps[relayed].submitter = keypairs[2].0;
+    rejected(tampered, "submitters");
+    let mut tampered = snapshot.clone();
+    tampered.timestamps[owned].owner = Some(gen_keypair().0);
+    rejected(tampered, "owners");
+    let mut tampered = snapshot.clone();
+    tampered.timestamps[owned].owner = None;
+    rejected(tampered, "owners");
+
+    // Keys of another network do not sign the block
+    let strangers: Vec<PublicKey> = (0..4).map(|_| gen_keypair().0).collect();
+    match snapshot.verify(&strangers) {
+        Err(SnapshotError::Precommits(_)) => {}
+        _ => panic!("Precommits of unknown validators are accepted"),
+    }
+
+    // A fresh network starts with the state of the snapshot
+    let mut restored = TestKitBuilder::validator()
+        .with_service(TimestampService::new().with_snapshot(snapshot))
+        .create();
+    restored.create_block();
+    let client = Client::with_transport(TestKitTransport { api: restored.api() });
+
+    let info = client.timestamp_by_content(tx2.content()).unwrap().unwrap();
+    assert_eq!(info.tx_hash, tx2.hash());
+    assert_eq!(info.height, 1);
+    assert_eq!(client.reveal(&keypairs[0].0).unwrap().unwrap().document(), &document);
+    let res: Relayer = client.get(&format!("v1/relayer/{}", relayer.0.to_hex())).unwrap();
+    assert_eq!(res.used(), 1);
+    let info = client.timestamp_by_content(tx4.content()).unwrap().unwrap();
+    assert_eq!(info.owner, Some(owner.0));
+    let stats: StatsResponse = client.get("v1/stats").unwrap();
+    assert_eq!((stats.total_timestamps, stats.total_submitters), (4, 4));
+}
+
+#[test]
+fn test_snapshot_at_height() {
+    let dir = TempDir::new("snapshots").unwrap();
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new().with_snapshots(2, dir.path()))
+        .create();
+    let validators: Vec<PublicKey> = Schema::new(&testkit.snapshot())
+        .actual_configuration()
+        .validator_keys
+        .iter()
+        .map(|keys| keys.consensus_key)
+        .collect();
+
+    let keypairs: Vec<_> = (0..2).map(|_| gen_keypair()).collect();
+    let tx1 = TxTimestamp::new(&keypairs[0].0, &crypto::hash(b"Lucid"), unix_time(), &keypairs[0].1);
+    let tx2 = TxTimestamp::new(&keypairs[1].0, &crypto::hash(b"Dreams"), unix_time(), &keypairs[1].1);
+    testkit.create_block_with_transactions(txvec![tx1.clone()]);
+    testkit.create_block();
+    testkit.create_block_with_transactions(txvec![tx2.clone()]);
+    assert!(dir.path().join("snapshot_2.json").exists());
+
+    let api = testkit.api();
+    let config: ConfigResponse = api.get_private(ApiKind::Service("timestamp"), "v1/config");
+    assert_eq!(config.snapshot_interval, Some(2));
+
+    // the saved snapshot keeps the state of its block, the latest one is taken on request
+    let saved: ServiceSnapshot = api.get_private(ApiKind::Service("timestamp"), "v1/snapshot?height=2");
+    assert_eq!((saved.height(), saved.timestamps.len()), (2, 1));
+    saved.verify(&validators).unwrap();
+    let latest: ServiceSnapshot = api.get_private(ApiKind::Service("timestamp"), "v1/snapshot?height=3");
+    assert_eq!((latest.height(), latest.timestamps.len()), (3, 2));
+
+    let url = "http://localhost:3000/api/services/timestamp/v1/snapshot?height=1";
+    let resp = match request::get(url, Headers::new(), api.private_handler()) {
+        Ok(resp) => resp,
+        Err(e) => e.response,
+    };
+    assert_eq!(resp.status, Some(Status::NotFound));
+    assert!(response::extract_body_to_string(resp).contains("every 2 blocks"));
+
+    // the saved snapshot restores the network as of its block
+    let mut restored = TestKitBuilder::validator()
+        .with_service(TimestampService::new().with_snapshot(saved))
+        .create();
+    restored.create_block();
+    let client = Client::with_transport(TestKitTransport { api: restored.api() });
+    assert!(client.timestamp_by_content(tx1.content()).unwrap().is_some());
+    assert!(client.timestamp_by_content(tx2.content()).unwrap().is_none());
+}
+
+#[test]
+fn test_node_restores_snapshot() {
+    let dir = TempDir::new("restore").unwrap();
+    let run_cluster = |dir: &Path, ports: (&str, &str), args: &[&str]| {
+        Command::new(node_exe())
+            .args(["cluster", "--validators", "1", "--peer-port", ports.0, "--api-port", ports.1])
+            .args(args)
+            .arg("--dir")
+            .arg(dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap()
+    };
+    let wait_for_start = |client: &Client| {
+        let deadline = Instant::now() + Duration::from_secs(60);
+        while client.get::<StatsResponse>("v1/stats").is_err() {
+            assert!(Instant::now() < deadline, "The node has not started");
+            thread::sleep(Duration::from_millis(500));
+        }
+    };
+
+    let original = dir.path().join("original");
+    let mut cluster = run_cluster(&original, ("12500", "18500"), &["--snapshot-interval", "2"]);
+    let client = Client::new("http://127.0.0.1:18500");
+    wait_for_start(&client);
+
+    let keypair = gen_keypair();
+    let tx = TxTimestamp::new(&keypair.0, &crypto::hash(b"Ashes"), unix_time(), &keypair.1);
+    let tx_hash = client.submit(&tx).unwrap();
+    client.wait_for_commit(&tx_hash, Duration::from_secs(60)).unwrap();
+
+    // the first snapshot saved after the timestamp is read from the private API
+    let height = client.timestamp_by_content(tx.content()).unwrap().unwrap().height;
+    let path = format!("v1/snapshot?height={}", height + height % 2);
+    let admin = Client::new("http://127.0.0.1:18501");
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let snapshot = loop {
+        match admin.get_optional::<ServiceSnapshot>(&path).unwrap() {
+            Some(snapshot) => break snapshot,
+            None if Instant::now() > deadline => panic!("The snapshot has not been saved"),
+            None => thread::sleep(Duration::from_millis(500)),
+        }
+    };
+    let snapshot_file = dir.path().join("snapshot.json");
+    fs::write(&snapshot_file, serde_json::to_string(&snapshot).unwrap()).unwrap();
+    drop(cluster.stdin.take());
+    assert!(cluster.wait().unwrap().success());
+
+    // the consensus key the original validator shared in its public config
+    let config = fs::read_to_string(original.join("pub_0.toml")).unwrap();
+    let key = config
+        .lines()
+        .find(|line| line.starts_with("consensus_key"))
+        .and_then(|line| line.split('"').nth(1))
+        .unwrap();
+
+    let restored = dir.path().join("restored");
+    let snapshot_arg = snapshot_file.to_str().unwrap();
+    let mut cluster = run_cluster(
+        &restored,
+        ("12600", "18600"),
+        &["--restore-snapshot", snapshot_arg, "--snapshot-validators", key],
+    );
+    let client = Client::new("http://127.0.0.1:18600");
+    wait_for_start(&client);
+    let info = client.timestamp_by_content(tx.content()).unwrap().unwrap();
+    assert_eq!((info.tx_hash, info.height), (tx_hash, height));
+    drop(cluster.stdin.take());
+    assert!(cluster.wait().unwrap().success());
+
+    // a snapshot is not restored with keys which did not sign it
+    let stranger = gen_keypair().0.to_hex();
+    let rejected = dir.path().join("rejected");
+    let mut cluster = run_cluster(
+        &rejected,
+        ("12600", "18600"),
+        &["--restore-snapshot", snapshot_arg, "--snapshot-validators", &stranger],
+    );
+    let deadline = Instant::now() + Duration::from_secs(60);
+    loop {
+        let log = fs::read_to_string(rejected.join("node_0.log")).unwrap_or_default();
+        if log.contains("Cannot restore the snapshot") {
+            break;
+        }
+        assert!(Instant::now() < deadline, "The node has started from a forged snapshot");
+        thread::sleep(Duration::from_millis(500));
+    }
+    assert!(client.get::<StatsResponse>("v1/stats").is_err());
+    drop(cluster.stdin.take());
+    assert!(cluster.wait().unwrap().success());
+}
+
+#[test]
+fn test_explorer() {
+    let testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new())
+        .create();
+    let api = testkit.api();
+
+    let (status, body) = get_raw(&api, "explorer");
+    assert_eq!(status, Status::Ok);
+    assert!(body.contains("<title>Timestamp explorer</title>"));
+    assert!(body.contains("explorer/app.js"));
+
+    let (status, body) = get_raw(&api, "explorer/app.js");
+    assert_eq!(status, Status::Ok);
+    assert!(body.contains("crypto.subtle.digest"));
+
+    let (status, _) = get_raw(&api, "explorer/style.css");
+    assert_eq!(status, Status::Ok);
+
+    let (status, _) = get_raw(&api, "explorer/secrets.txt");
+    assert_eq!(status, Status::NotFound);
+}
+
+// Collects `$ref`s of a JSON document
+fn collect_refs(value: &serde_json::Value, refs: &mut Vec<String>) {
+    match *value {
+        serde_json::Value::Object(ref fields) => {
+            for (key, value) in fields {
+                match (key.as_str(), value.as_str()) {
+                    ("$ref", Some(reference)) => refs.push(reference.to_owned()),
+                    _ => collect_refs(value, refs),
+                }
+            }
+        }
+        serde_json::Value::Array(ref items) => {
+            for item in items {
+                collect_refs(item, refs);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[test]
+fn test_openapi() {
+    let testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new())
+        .create();
+    let api = testkit.api();
+
+    let (status, body) = get_raw(&api, "v1/openapi.json");
+    assert_eq!(status, Status::Ok);
+    let spec: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(spec["openapi"], "3.0.0");
+    let schemas = &spec["components"]["schemas"];
+    assert!(schemas["Timestamp"]["properties"]["content"].is_object());
+    assert!(schemas["TimestampResponse"]["properties"]["tx_hash"].is_object());
+
+    let mut refs = Vec::new();
+    collect_refs(&spec, &mut refs);
+    for reference in refs {
+        let name = reference.trim_start_matches("#/components/schemas/");
+        assert!(schemas[name].is_object(), "{} is not defined", reference);
+    }
+
+    // routes of the document in the router's syntax
+    let mut documented = Vec::new();
+    for (path, operations) in spec["paths"].as_object().unwrap() {
+        let path = path.replace('{', ":").replace('}', "");
+        for method in operations.as_object().unwrap().keys() {
+            documented.push((method.clone(), path.clone()));
+        }
+    }
+    documented.sort();
+
+    // `TimestampApi::wire` registers the routes of the same table
+    let mut registered: Vec<_> = openapi::ROUTES
+        .iter()
+        .map(|route| (route.method.as_str().to_owned(), route.path.to_owned()))
+        .collect();
+    registered.sort();
+    assert_eq!(documented, registered);
+
+    // every documented route is found by the router, which answers unknown ones with an empty 404
+    for (method, path) in &documented {
+        let endpoint: Vec<String> = path[1..]
+            .split('/')
+            .map(|segment| match segment {
+                ":id" | ":height" => "1".to_owned(),
+                ":file" => "index.html".to_owned(),
+                segment if segment.starts_with(':') => "0".repeat(64),
+                segment => segment.to_owned(),
+            })
+            .collect();
+        let endpoint = endpoint.join("/");
+        let (status, body) = if method == "post" {
+            post_raw(&api, &endpoint, "{}")
+        } else if endpoint == "v1/events" {
+            get_raw(&api, "v1/events?limit=0")
+        } else {
+            get_raw(&api, &endpoint)
+        };
+        assert!(status != Status::NotFound || !body.is_empty(), "{} {} is not routed", method, path);
+    }
+}
+
+#[test]
+fn test_v2_envelope() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new())
+        .create();
+    let api = testkit.api();
+
+    let (alice, bob) = (gen_keypair(), gen_keypair());
+    let tx1 = TxTimestamp::new(&alice.0, &crypto::hash(b"Down To Earth"), unix_time(), &alice.1);
+    let tx2 = TxTimestamp::new(&bob.0, &crypto::hash(b"Cry Over Spilt Milk"), unix_time(), &bob.1);
+
+    for tx in [&tx1, &tx2] {
+        let (status, body) = post_raw(&api, "v2/submit", &serde_json::to_string(tx).unwrap());
+        assert_eq!(status, Status::Ok);
+        let res: Envelope<TimestampResponse> = serde_json::from_str(&body).unwrap();
+        assert_eq!(res.data.unwrap().tx_hash, tx.hash());
+        assert!(res.error.is_none());
+        assert_eq!(res.meta.height, 0);
+    }
+    testkit.create_block();
+
+    let (status, body) = get_raw(&api, &format!("v2/timestamps/{}", alice.0.to_hex()));
+    assert_eq!(status, Status::Ok);
+    let res: Envelope<Timestamp> = serde_json::from_str(&body).unwrap();
+    assert_eq!(res.data.unwrap().content(), tx1.content());
+    assert_eq!(res.meta.height, 1);
+    assert!(res.meta.pagination.is_none());
+
+    // lists are paged even without a range
+    let (_, body) = get_raw(&api, "v2/timestamps?limit=1");
+    let first: Envelope<Vec<Timestamp>> = serde_json::from_str(&body).unwrap();
+    assert_eq!(first.data.unwrap().len(), 1);
+    let next = first.meta.pagination.unwrap().next.unwrap();
+    let (_, body) = get_raw(&api, &format!("v2/timestamps?limit=1&cursor={}", next));
+    let second: Envelope<Vec<Timestamp>> = serde_json::from_str(&body).unwrap();
+    assert_eq!(second.data.unwrap().len(), 1);
+    assert!(second.meta.pagination.unwrap().next.is_none());
+
+    let (status, body) = get_raw(&api, &format!("v2/transactions/{}", tx2.hash().to_hex()));
+    assert_eq!(status, Status::Ok);
+    let res: Envelope<TransactionStatus> = serde_json::from_str(&body).unwrap();
+    assert_eq!(res.data.unwrap().height, Some(1));
+
+    let (status, body) = get_raw(&api, "v2/blocks/1");
+    assert_eq!(status, Status::Ok);
+    let res: Envelope<serde_json::Value> = serde_json::from_str(&body).unwrap();
+    assert_eq!(res.data.unwrap()["txs"].as_array().unwrap().len(), 2);
+
+    // failures have proper statuses and an error in place of data
+    let (status, body) = get_raw(&api, "v2/blocks/5");
+    assert_eq!(status, Status::NotFound);
+    let res: Envelope<serde_json::Value> = serde_json::from_str(&body).unwrap();
+    assert!(res.data.is_none());
+    assert_eq!(res.error.unwrap().code, "not_found");
+    assert_eq!(res.meta.height, 1);
+
+    let (status, body) = get_raw(&api, &format!("v2/timestamps/{}", gen_keypair().0.to_hex()));
+    assert_eq!(status, Status::NotFound);
+    let res: Envelope<Timestamp> = serde_json::from_str(&body).unwrap();
+    assert_eq!(res.error.unwrap().code, "not_found");
+
+    let (status, body) = get_raw(&api, "v2/timestamps/xyz");
+    assert_eq!(status, Status::BadRequest);
+    let res: Envelope<Timestamp> = serde_json::from_str(&body).unwrap();
+    assert_eq!(res.error.unwrap().code, "bad_request");
+
+    let (status, body) = post_raw(&api, "v2/submit", "{}");
+    assert_eq!(status, Status::BadRequest);
+    let res: Envelope<TimestampResponse> = serde_json::from_str(&body).unwrap();
+    assert_eq!(res.error.unwrap().code, "bad_request");
+
+    // batches answer per transaction, as in v1
+    let carol = gen_keypair();
+    let tx3 = TxTimestamp::new(&carol.0, &crypto::hash(b"Sunny Side Up"), unix_time(), &carol.1);
+    let batch = format!("{}\n{{}}", serde_json::to_string(&tx3).unwrap());
+    let (status, body) = post_raw(&api, "v2/submit_batch", &batch);
+    assert_eq!(status, Status::Ok);
+    let res: Envelope<Vec<BatchItemResponse>> = serde_json::from_str(&body).unwrap();
+    let items = res.data.unwrap();
+    assert_eq!(items[0].tx_hash, Some(tx3.hash()));
+    assert!(items[1].error.is_some());
+
+    let huge = " ".repeat(timestamping::MAX_BATCH_BODY_SIZE as usize + 1);
+    let (status, body) = post_raw(&api, "v2/submit_batch", &huge);
+    assert_eq!(status, Status::PayloadTooLarge);
+    let res: Envelope<Vec<BatchItemResponse>> = serde_json::from_str(&body).unwrap();
+    assert_eq!(res.error.unwrap().code, "payload_too_large");
+}
+
+#[test]
+fn test_v1_kept_alongside_v2() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new())
+        .create();
+    let api = testkit.api();
+
+    let keypair = gen_keypair();
+    let tx = TxTimestamp::new(&keypair.0, &crypto::hash(b"Down To Earth"), unix_time(), &keypair.1);
+    testkit.create_block_with_transactions(txvec![tx.clone()]);
+
+    // v1 answers with bare values
+    let res = api.get::<serde_json::Value>(ApiKind::Service("timestamp"), "v1/timestamps");
+    assert_eq!(res.as_array().unwrap().len(), 1);
+
+    let (status, body) = get_raw(&api, &format!("v1/timestamp/{}", gen_keypair().0.to_hex()));
+    assert_eq!(status, Status::NotFound);
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap(), json!("Not found"));
+
+    let res = api.get::<serde_json::Value>(ApiKind::Service("timestamp"), "v1/block_stats/5");
+    assert!(res.as_str().unwrap().contains("not found"));
+}
+
+#[test]
+fn test_content_negotiation() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(TimestampService::new())
+        .create();
+    let api = testkit.api();
+
+    let keypair = gen_keypair();
+    let tx = TxTimestamp::new(&keypair.0, &crypto::hash(b"Down To Earth"), unix_time(), &keypair.1);
+
+    // bodies of `/v1/submit` in every encoding are the same transaction
+    let binary = tx.clone().into_bytes();
+    let bodies = vec![
+        (Encoding::Json, serde_json::to_vec(&tx).unwrap()),
+        (Encoding::Cbor, negotiation::to_cbor(&tx)),
+        (Encoding::Binary, binary.clone()),
+    ];
+    for (encoding, body) in bodies {
+        match negotiation::decode_transaction(encoding, &body).unwrap() {
+            TimestampServiceTransactions::TxTimestamp(decoded) => assert_eq!(decoded.hash(), tx.hash()),
+            _ => panic!("Unexpected transaction"),
+        }
+    }
+    assert!(negotiation::decode_transaction(Encoding::Binary, &binary[..20]).is_err());
+    assert!(negotiation::decode_transaction(Encoding::Cbor, b"garbage").is_err());
+    // bodies beyond the size of a transaction are refused before decoding
+    for content_type in &["application/cbor", "application/octet-stream"] {
+        let mut headers = Headers::new();
+        headers.set_raw("Content-Type", vec![content_type.as_bytes().to_vec()]);
+        let body = "a".repeat(64 * 1024 + 1);
+        let url = "http://localhost:3000/api/services/timestamp/v1/submit";
+        let resp = match request::post(url, headers, &body, api.public_handler()) {
+            Ok(resp) => resp,
+            Err(e) => e.response,
+        };
+        assert_eq!(resp.status.unwrap(), Status::PayloadTooLarge);
+    }
+    // hex strings are still taken in CBOR bodies
+    let body = serde_cbor::to_vec(&tx).unwrap();
+    assert!(negotiation::decode_transaction(Encoding::Cbor, &body).is_ok());
+
+    testkit.create_block_with_transactions(txvec![tx.clone()]);
+    let endpoint = format!("v1/timestamp/{}", keypair.0.to_hex());
+    let json: Timestamp = api.get(ApiKind::Service("timestamp"), &endpoint);
+
+    let (status, body) = get_encoded(&api, &endpoint, "application/cbor");
+    assert_eq!(status, Status::Ok);
+    let cbor: Timestamp = negotiation::from_cbor(&body).unwrap();
+    assert_eq!((cbor.content(), cbor.time()), (json.content(), json.time()));
+
+    // hashes, keys and signatures are byte strings, which halves them
+    let value: serde_cbor::Value = serde_cbor::from_slice(&body).unwrap();
+    let content = value.as_object().unwrap()[&serde_cbor::ObjectKey::String("content".into())].clone();
+    assert_eq!(content.as_bytes().unwrap(), &json.content().as_ref().to_vec());
+    let json_size = serde_json::to_vec(&json).unwrap().len();
+    assert!(body.len() * 10 < json_size * 6, "{} of {} bytes", body.len(), json_size);
+
+    let (status, body) = get_encoded(&api, &endpoint, "application/octet-stream");
+    assert_eq!(status, Status::Ok);
+    assert_eq!(body, json.clone().into_bytes());
+
+    let (_, body) = get_encoded(&api, "v1/stats", "application/cbor");
+    let stats: StatsResponse = negotiation::from_cbor(&body).unwrap();
+    assert_eq!(stats.total_timestamps, 1);
+
+    // lists of stored values are sequences of values with their lengths
+    let other = gen_keypair();
+    testkit.create_block_with_transactions(txvec![
+        TxTimestamp::new(&other.0, &crypto::hash(b"Tornado"), unix_time(), &other.1),
+    ]);
+    let (status, body) = get_encoded(&api, "v1/timestamps", "application/octet-stream");
+    assert_eq!(status, Status::Ok);
+    let values = negotiation::split_list(&body).unwrap();
+    assert_eq!(values.len(), 2);
+    assert!(values.iter().any(|value| Timestamp::from_bytes(value.into()).content() == tx.content()));
+    assert!(negotiation::split_list(&body[..body.len() - 1]).is_err());
+
+    let url = "http://localhost:3000/api/services/timestamp/v1/timestamps?from_height=0&limit=1";
+    let mut headers = Headers::new();
+    headers.set_raw("Accept", vec![b"application/octet-stream".to_vec()]);
+    let resp = request::get(url, headers, api.public_handler()).unwrap();
+    let cursor = resp.headers.get_raw(negotiation::NEXT_CURSOR).unwrap()[0].clone();
+    let values = negotiation::split_list(&response::extract_body_to_bytes(resp)).unwrap();
+    assert_eq!(values.len(), 1);
+    let next_page = format!("v1/timestamps?from_height=0&limit=1&cursor={}", String::from_utf8(cursor).unwrap());
+    let page: TimestampsPage = api.get(ApiKind::Service("timestamp"), &next_page);
+    assert_eq!(page.timestamps.len(), 1);
+    assert_ne!(page.timestamps[0].clone().into_bytes(), values[0]);
+
+    // documents which are not stored values have no binary encoding
+    let (status, _) = get_encoded(&api, "v1/stats", "application/octet-stream");
+    assert_eq!(status, Status::NotAcceptable);
+
+    // JSON stays the default
+    let (status, body) = get_encoded(&api, &endpoint, "text/html, */*");
+    assert_eq!(status, Status::Ok);
+    let res: Timestamp = serde_json::from_slice(&body).unwrap();
+    assert_eq!(res.content(), tx.content());
+}
+
+#[test]
+fn test_cbor_read_endpoints() {
+    let relayer = gen_keypair();
+    let ledger = MockLedger::new();
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(
+            TimestampService::new()
+                .with_relayer(&relayer.0, 1)
+                .with_anchoring(1, Box::new(ledger.clone())),
+        )
+        .create();
+    let api = testkit.api();
+
+    let keypair = gen_keypair();
+    let document = crypto::hash(b"Holy Wars");
+    let salt = client::gen_salt();
+    let tx = TxTimestamp::new(&keypair.0, &commitment(&salt, &document), unix_time(), &keypair.1);
+    let author = gen_keypair();
+    let relayed = TxRelayed::wrap(
+        &TxTimestamp::new(&author.0, &crypto::hash(b"Tornado"), unix_time(), &author.1),
+        &relayer.0,
+        &relayer.1,
+    );
+    testkit.create_block_with_transactions(txvec![tx.clone(), relayed]);
+    // the anchor of the first block is committed before a later anchor could take its place
+    testkit.create_block();
+    testkit.create_block_with_transactions(txvec![TxReveal::new(&keypair.0, &salt, &document, &keypair.1)]);
+    testkit.create_block();
+
+    // every negotiated read endpoint answers in CBOR
+    let get = |endpoint: &str| {
+        let (status, body) = get_encoded(&api, endpoint, "application/cbor");
+        assert_eq!(status, Status::Ok, "{}", endpoint);
+        body
+    };
+    let decode = |endpoint: &str| -> serde_json::Value { negotiation::from_cbor(&get(endpoint)).unwrap() };
+
+    let anchors: Vec<Anchor> = negotiation::from_cbor(&get("v1/anchors")).unwrap();
+    assert!(!anchors.is_empty());
+    let anchor: Anchor = negotiation::from_cbor(&get("v1/anchor/1")).unwrap();
+    assert_eq!(anchor.height(), 1);
+
+    let timestamp: Timestamp = negotiation::from_cbor(&get(&format!("v1/timestamp/{}", keypair.0.to_hex()))).unwrap();
+    assert_eq!(timestamp.content(), tx.content());
+    let page: TimestampsPage = negotiation::from_cbor(&get("v1/timestamps?from_height=0&limit=10")).unwrap();
+    assert_eq!(page.timestamps.len(), 2);
+    let info: TimestampInfo = negotiation::from_cbor(&get(&format!("v1/content/{}", tx.content().to_hex()))).unwrap();
+    assert_eq!(info.tx_hash, tx.hash());
+    let status: TransactionStatus =
+        negotiation::from_cbor(&get(&format!("v1/transaction/{}", tx.hash().to_hex()))).unwrap();
+    assert_eq!(status.height, Some(1));
+
+    let reveal: Reveal = negotiation::from_cbor(&get(&format!("v1/reveal/{}", keypair.0.to_hex()))).unwrap();
+    assert_eq!(reveal.document(), &document);
+    decode(&format!("v1/revealed/{}", document.to_hex()));
+    let res: Relayer = negotiation::from_cbor(&get(&format!("v1/relayer/{}", relayer.0.to_hex()))).unwrap();
+    assert_eq!(res.used(), 1);
+    let stats: StatsResponse = negotiation::from_cbor(&get("v1/stats")).unwrap();
+    assert_eq!(stats.total_timestamps, 2);
+    decode("v1/block_stats/1");
+}
+
+#[test]
+fn test_cluster_reaches_consensus() {
+    let dir = TempDir::new("cluster").unwrap();
+    let mut cluster = Command::new(node_exe())
+        .args(["cluster", "--validators", "4", "--peer-port", "12200", "--api-port", "18200", "--dir"])
+        .arg(dir.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // validators start on their own, the first one is polled until its API is up
+    let client = Client::new("http://127.0.0.1:18200");
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let height = loop {
+        match client.get::<Envelope<StatsResponse>>("v2/stats") {
+            Ok(ref envelope) if envelope.meta.height > 0 => break envelope.meta.height,
+            _ if Instant::now() > deadline => panic!("The cluster has not committed a block"),
+            _ => thread::sleep(Duration::from_millis(500)),
+        }
+    };
+    assert!(height > 0);
+
+    // the validators agree on a timestamp and its time
+    let keypair = gen_keypair();
+    let tx = TxTimestamp::new(&keypair.0, &crypto::hash(b"Ashes"), unix_time(), &keypair.1);
+    let tx_hash = client.submit(&tx).unwrap();
+    client.wait_for_commit(&tx_hash, Duration::from_secs(60)).unwrap();
+    let other = Client::new("http://127.0.0.1:18203");
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while other.timestamp(&keypair.0).unwrap().is_none() {
+        assert!(Instant::now() < deadline, "The timestamp has not reached another validator");
+        thread::sleep(Duration::from_millis(500));
+    }
+    assert_eq!(other.timestamp(&keypair.0).unwrap().unwrap().time(), tx.time());
+
+    // three of four validators are a quorum, so the network commits without the stopped one
+    let stdin = cluster.stdin.as_mut().unwrap();
+    writeln!(stdin, "stop 3").unwrap();
+    stdin.flush().unwrap();
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while other.get::<StatsResponse>("v1/stats").is_ok() {
+        assert!(Instant::now() < deadline, "The validator has not stopped");
+        thread::sleep(Duration::from_millis(500));
+    }
+    let keypair = gen_keypair();
+    let tx = TxTimestamp::new(&keypair.0, &crypto::hash(b"Embers"), unix_time(), &keypair.1);
+    let tx_hash = client.submit(&tx).unwrap();
+    let height = client.wait_for_commit(&tx_hash, Duration::from_secs(60)).unwrap();
+
+    // the restarted validator catches up on the blocks it missed
+    writeln!(stdin, "start 3").unwrap();
+    stdin.flush().unwrap();
+    let deadline = Instant::now() + Duration::from_secs(60);
+    loop {
+        match other.timestamp_by_content(tx.content()) {
+            Ok(Some(info)) => {
+                assert_eq!((info.tx_hash, info.height), (tx_hash, height));
+                break;
+            }
+            _ if Instant::now() > deadline => panic!("The restarted validator has not caught up"),
+            _ => thread::sleep(Duration::from_millis(500)),
+        }
+    }
+
+    // the end of input stops the cluster
+    drop(cluster.stdin.take());
+    assert!(cluster.wait().unwrap().success());
+}
+
+#[test]
+fn test_http_transport() {
+    let dir = TempDir::new("http").unwrap();
+    let mut cluster = Command::new(node_exe())
+        .args(["cluster", "--validators", "1", "--peer-port", "12800", "--api-port", "18800", "--dir"])
+        .arg(dir.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // a trailing slash of the address is dropped
+    let client = Client::new("http://127.0.0.1:18800/");
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while client.get::<StatsResponse>("v1/stats").is_err() {
+        assert!(Instant::now() < deadline, "The node has not started");
+        thread::sleep(Duration::from_millis(500));
+    }
+
+    let keypair = gen_keypair();
+    let content = crypto::hash(b"Hysteria");
+    assert!(client.timestamp(&keypair.0).unwrap().is_none());
+    assert!(client.timestamp_by_content(&content).unwrap().is_none());
+
+    // POST with a JSON body and waiting for the commit
+    let tx = TxTimestamp::new(&keypair.0, &content, unix_time(), &keypair.1);
+    let tx_hash = client.submit(&tx).unwrap();
+    assert_eq!(tx_hash, tx.hash());
+    let height = client.wait_for_commit(&tx_hash, Duration::from_secs(60)).unwrap();
+    assert_eq!(client.timestamp(&keypair.0).unwrap().unwrap().content(), &content);
+    let info = client.timestamp_by_content(&content).unwrap().unwrap();
+    assert_eq!((info.tx_hash, info.height), (tx_hash, height));
+    assert_eq!(client.timestamps().unwrap().len(), 1);
+
+    // a streamed body
+    let mut out = Vec::new();
+    client.export(ExportFormat::Ndjson, &ExportFilter::default(), &mut out).unwrap();
+    let record: ExportRecord = serde_json::from_slice(&out).unwrap();
+    assert_eq!(record.tx_hash, tx_hash);
+
+    // error statuses and their messages
+    let forged = TxTimestamp::new(&keypair.0, &content, 0, &keypair.1);
+    match client.submit(&forged) {
+        Err(ClientError::Api { status: 400, ref message }) => assert!(message.contains("Time")),
+        other => panic!("Unexpected result {:?}", other),
+    }
+    match client.get::<StatsResponse>("v1/no_such_endpoint") {
+        Err(ClientError::Api { status: 404, .. }) => {}
+        _ => panic!("An unknown endpoint is found"),
+    }
+
+    drop(cluster.stdin.take());
+    assert!(cluster.wait().unwrap().success());
+
+    // a node which is not running
+    match client.get::<StatsResponse>("v1/stats") {
+        Err(ClientError::Transport(_)) => {}
+        _ => panic!("A stopped node answers"),
+    }
+}
+
+// runs git in `repo`, returns its output
+fn git(repo: &Path, args: &[&str]) -> String {
+    let output = Command::new("git").arg("-C").arg(repo).args(args).output().unwrap();
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_git_stamp_and_verify() {
+    let dir = TempDir::new("git").unwrap();
+    let mut cluster = Command::new(node_exe())
+        .args(["cluster", "--validators", "1", "--peer-port", "12300", "--api-port", "18300", "--dir"])
+        .arg(dir.path().join("cluster"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let node = "http://127.0.0.1:18300";
+    let client = Client::new(node);
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while client.get::<StatsResponse>("v1/stats").is_err() {
+        assert!(Instant::now() < deadline, "The node has not started");
+        thread::sleep(Duration::from_millis(500));
+    }
+
+    let keys = dir.path().join("keys");
+    Keystore::open(&keys).unwrap().generate("default", "correct horse").unwrap();
+    let ts = |args: &[&str]| {
+        Command::new(ts_exe())
+            .args(args)
+            .env("TS_NODE", node)
+            .env("TS_KEYSTORE", &keys)
+            .env("TS_PASSWORD", "correct horse")
+            .output()
+            .unwrap()
+    };
+
+    let repo = dir.path().join("repo");
+    fs::create_dir(&repo).unwrap();
+    git(&repo, &["init", "--quiet"]);
+    // notes are commits too, so they need an identity as well
+    git(&repo, &["config", "user.name", "ts"]);
+    git(&repo, &["config", "user.email", "ts@localhost"]);
+    git(&repo, &["commit", "--quiet", "--allow-empty", "-m", "First"]);
+    let oid = git(&repo, &["rev-parse", "HEAD"]).trim().to_owned();
+    let repo_arg = repo.to_str().unwrap();
+
+    let output = ts(&["git", "stamp", "--repo", repo_arg]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    // The receipt is a note of the commit, for SHA-256 of the commit id in hex
+    let note = git(&repo, &["notes", "--ref", "refs/notes/timestamps", "show", &oid]);
+    let receipt: Receipt = serde_json::from_str(&note).unwrap();
+    assert_eq!(receipt.content, crypto::hash(oid.as_bytes()));
+    let info = client.timestamp_by_content(&receipt.content).unwrap().unwrap();
+    assert_eq!(info.tx_hash, receipt.tx_hash);
+
+    let output = ts(&["git", "verify", "--repo", repo_arg]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Receipt matches the chain"));
+
+    // Another commit has no receipt
+    git(&repo, &["commit", "--quiet", "--allow-empty", "-m", "Second"]);
+    assert!(!ts(&["git", "verify", "--repo", repo_arg]).status.success());
+
+    drop(cluster.stdin.take());
+    assert!(cluster.wait().unwrap().success());
+}
+
+#[test]
+fn test_ts_stamp_and_check() {
+    let dir = TempDir::new("ts").unwrap();
+    let mut cluster = Command::new(node_exe())
+        .args(["cluster", "--validators", "1", "--peer-port", "12700", "--api-port", "18700", "--dir"])
+        .arg(dir.path().join("cluster"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let node = "http://127.0.0.1:18700";
+    let client = Client::new(node);
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while client.get::<StatsResponse>("v1/stats").is_err() {
+        assert!(Instant::now() < deadline, "The node has not started");
+        thread::sleep(Duration::from_millis(500));
+    }
+
+    let keys = dir.path().join("keys");
+    let keystore = Keystore::open(&keys).unwrap();
+    let owner = keystore.generate("default", "correct horse").unwrap();
+    keystore.generate("other", "correct horse").unwrap();
+    let ts = |args: &[&str]| {
+        let output = Command::new(ts_exe())
+            .args(args)
+            .env("TS_NODE", node)
+            .env("TS_KEYSTORE", &keys)
+            .env("TS_PASSWORD", "correct horse")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        stdout
+    };
+
+    let report = dir.path().join("report.pdf");
+    fs::write(&report, b"Quarterly report").unwrap();
+    let report_arg = report.to_str().unwrap();
+    ts(&["stamp", report_arg]);
+
+    // the receipt next to the file describes the committed timestamp of its owner
+    let receipt_file = fs::read_to_string(dir.path().join("report.pdf.ts.json")).unwrap();
+    let receipt: Receipt = serde_json::from_str(&receipt_file).unwrap();
+    assert_eq!(receipt.content, crypto::hash(b"Quarterly report"));
+    let info = client.timestamp_by_content(&receipt.content).unwrap().unwrap();
+    assert_eq!((info.tx_hash, info.owner), (receipt.tx_hash, Some(owner)));
+    assert!(ts(&["check", report_arg]).contains("matches"));
+
+    // stamping the same content again reports whose timestamp is already there
+    assert!(ts(&["stamp", report_arg]).contains("Already timestamped with this key"));
+    assert!(ts(&["--key", "other", "stamp", report_arg]).contains("Already timestamped by another key"));
+
+    // an anonymous timestamp is a bare TxTimestamp
+    let notes = dir.path().join("notes.txt");
+    fs::write(&notes, b"Meeting notes").unwrap();
+    ts(&["stamp", "--anonymous", notes.to_str().unwrap()]);
+    let info = client.timestamp_by_content(&crypto::hash(b"Meeting notes")).unwrap().unwrap();
+    assert_eq!(info.owner, None);
+
+    // a file which is not timestamped is reported
+    let draft = dir.path().join("draft.txt");
+    fs::write(&draft, b"Draft").unwrap();
+    let output = Command::new(ts_exe())
+        .args(["check", draft.to_str().unwrap()])
+        .env("TS_NODE", node)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is not timestamped"));
+
+    drop(cluster.stdin.take());
+    assert!(cluster.wait().unwrap().success());
+}